@@ -0,0 +1,37 @@
+use std::collections::HashSet;
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+use brutal_tetris_hacker::algorithm::{Configuration, NoopStats, SearchMode};
+use brutal_tetris_hacker::util::{Pos, Size};
+
+/// Each search step looks for a fitting placement of every tetra, so the fit check dominates
+fn fit_check(c: &mut Criterion) {
+    let exhaustive_4x4 =
+        Configuration::new(Size::new(4, 4), HashSet::new()).with_mode(SearchMode::Exhaustive);
+    c.bench_function("exhaustive 4x4", |b| {
+        b.iter(|| black_box(&exhaustive_4x4).run(&mut NoopStats))
+    });
+
+    let unavailable = [(0, 0), (0, 1), (1, 0), (1, 1)]
+        .map(|(row, col)| Pos::new(row, col))
+        .into_iter()
+        .collect();
+    let first_of_8x8 = Configuration::new(Size::new(8, 8), unavailable)
+        .with_mode(SearchMode::Exhaustive)
+        .with_complete(true);
+    c.bench_function("first complete 8x8", |b| {
+        b.iter(|| black_box(&first_of_8x8).solve_one())
+    });
+
+    // deep enough for the search steps to outweigh collecting the candidates
+    let steps_of_8x8 = Configuration::new(Size::new(8, 8), HashSet::new())
+        .with_mode(SearchMode::Exhaustive)
+        .with_max_recursions(20_000);
+    c.bench_function("20k steps of 8x8", |b| {
+        b.iter(|| black_box(&steps_of_8x8).run(&mut NoopStats))
+    });
+}
+
+criterion_group!(benches, fit_check);
+criterion_main!(benches);
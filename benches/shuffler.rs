@@ -0,0 +1,88 @@
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use std::collections::HashSet;
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+use brutal_tetris_hacker::algorithm::{Configuration, NoopStats, SearchMode};
+use brutal_tetris_hacker::tetra::{Shuffler, TETRAS};
+use brutal_tetris_hacker::util::Size;
+
+/// Counts allocations, so that the hot path of the random search could be checked for them
+struct Counting;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+fn allocations_of(f: impl FnOnce()) -> usize {
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    f();
+    ALLOCATIONS.load(Ordering::Relaxed) - before
+}
+
+const STEPS: usize = 20_000;
+
+/// The random search draws a tetra with the shuffler on every step, which shouldn't allocate
+fn draws(c: &mut Criterion) {
+    let mut shuffler = Shuffler::with_rng(StdRng::seed_from_u64(0));
+    let ids: Vec<_> = (0..TETRAS.len()).collect();
+
+    let picks = allocations_of(|| {
+        for _ in 0..STEPS {
+            black_box(shuffler.pick_from(black_box(&ids)));
+        }
+    });
+    let shuffles = allocations_of(|| {
+        for _ in 0..STEPS {
+            shuffler.finite_ids().for_each(|id| {
+                black_box(id);
+            });
+        }
+    });
+    assert_eq!((picks, shuffles), (0, 0));
+
+    c.bench_function("20k picks", |b| {
+        b.iter(|| {
+            for _ in 0..STEPS {
+                black_box(shuffler.pick_from(black_box(&ids)));
+            }
+        })
+    });
+}
+
+fn random_steps(c: &mut Criterion) {
+    let random = Configuration::new(Size::new(8, 8), HashSet::new())
+        .with_mode(SearchMode::Random)
+        .with_max_recursions(STEPS);
+
+    let allocations = allocations_of(|| {
+        black_box(random.run(&mut NoopStats));
+    });
+    println!(
+        "allocations per random step: {:.2}",
+        allocations as f64 / STEPS as f64
+    );
+
+    c.bench_function("20k random steps of 8x8", |b| {
+        b.iter(|| black_box(&random).run(&mut NoopStats))
+    });
+}
+
+criterion_group!(benches, draws, random_steps);
+criterion_main!(benches);
@@ -1,11 +1,28 @@
-use std::collections::{BTreeSet, HashSet};
+use std::borrow::Cow;
+use std::cmp::Reverse;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::hash::{Hash, Hasher};
 use std::num::NonZeroUsize;
 use std::ops::ControlFlow;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
 
 use grid::Grid;
+use miette::Diagnostic;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
 
-use crate::tetra::{Placed, PlacedBoundariesChecked, Shuffler, Tetra};
-use crate::util::{Pos, PosInGrid, Size, SizeOf};
+use crate::parse_field::{ParseError, ParsedField, Parser};
+use crate::profile::Phase;
+use crate::score::ScoreMode;
+use crate::symmetry::Transform;
+use crate::tetra::{PieceKind, Placed, PlacedBoundariesChecked, Shuffler, Tetra, TETRAS};
+use crate::util::{stable_hash, Pos, PosInGrid, Size, SizeOf};
 
 pub type Placement = BTreeSet<PlacedBoundariesChecked>;
 
@@ -14,295 +31,3864 @@ pub struct Configuration {
     pub size: Size,
     /// What cells are unavailable to put Tetras into
     pub unavailable: HashSet<Pos>,
+    /// What cells are not part of the field at all, for non-rectangular fields
+    pub outside: HashSet<Pos>,
+    /// Cells tetras might cover, but which don't have to be covered: they don't count as free,
+    /// e.g. for the complete mode
+    pub optional: HashSet<Pos>,
     /// How many results to generate
     pub results_limit: Option<NonZeroUsize>,
+    /// Tetras placed by the user before the search starts
+    pub prefilled: Vec<PlacedBoundariesChecked>,
+    /// Additional rules a placement should satisfy
+    pub constraints: Vec<Box<dyn Constraint>>,
+    /// How to pick tetras on each step of the search
+    pub mode: SearchMode,
+    /// Search state to continue from
+    pub resume: Option<Checkpoint>,
+    /// Tetras the search might place, all of [`TETRAS`] by default. Prefilled tetras could be
+    /// any
+    pub pieces: Vec<&'static Tetra>,
+    /// Tetras each recorded placement should contain at least once
+    pub required_pieces: HashSet<&'static Tetra>,
+    /// How many times each tetra should appear at least in a recorded placement
+    pub min_piece_counts: HashMap<&'static Tetra, usize>,
+    /// Record only placements in which no unavailable cell is connected to the field border
+    /// through empty cells
+    pub enclose_blocked: bool,
+    /// Place tetras only where they rest on the field floor, on an unavailable cell or on another
+    /// tetra, like in Tetris
+    pub gravity: bool,
+    /// Record only placements which cover every playable cell
+    pub complete: bool,
+    /// Skip the search of [`Configuration::complete`] placements if
+    /// [`Configuration::is_parity_feasible`] proves there are none
+    pub parity_prune: bool,
+    /// What to do if nothing is found
+    pub no_solution: NoSolutionPolicy,
+    /// In which order cells are tried when looking for a place for a tetra
+    pub scan_order: ScanOrder,
+    /// Which of the places found for a tetra is taken
+    pub fit_policy: FitPolicy,
+    /// Which places are preferred before the [`FitPolicy`] picks one of them
+    pub heuristic: Heuristic,
+    /// How many tetras a recorded placement should consist of, including prefilled ones
+    pub piece_count: Option<PieceCount>,
+    /// How many tetras, including prefilled ones, could be in the field before the search stops
+    /// descending. Unlike [`PieceCount::Max`], placements at this depth are recorded only if they
+    /// cover enough cells
+    pub max_depth: Option<usize>,
+    /// How many cells more than usual could be left free in a recorded placement
+    pub free_cells_slack: usize,
+    /// Record every placement to which no tetra could be added, however many cells it leaves
+    /// free
+    pub record_all_maximal: bool,
+    /// How many search steps to take at most. Unlike a time limit, gives the same results on
+    /// every run, see [`CollectStats::search_truncated`]
+    pub max_recursions: Option<usize>,
+    /// How long to search at most, keeping what is found by then, see
+    /// [`CollectStats::search_truncated`]
+    pub time_limit: Option<Duration>,
+    /// Seeds the random choices of the search, so that the same seed gives the same results
+    pub seed: Option<u64>,
+    /// Keep the order tetras were placed in, see [`PlacementResult::order`]
+    pub placement_order: bool,
+    /// Keep one result of those which are mirror images of each other, see
+    /// [`Configuration::with_symmetry_breaking`]
+    pub symmetry_breaking: bool,
+    /// How to rank the results, see [`Configuration::ranked`]
+    pub score: Option<ScoreMode>,
 }
 
-impl Configuration {
-    pub fn new(size: Size, unavailable: HashSet<Pos>) -> Self {
-        Self {
-            size,
-            unavailable,
-            results_limit: None,
+/// What a cell of the field bounding box is
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CellKind {
+    /// Tetras might be placed here
+    Playable,
+    /// Part of the field, but unavailable for tetras
+    Blocked,
+    /// Not part of the field
+    Outside,
+}
+
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq)]
+pub enum NoSolutionPolicy {
+    /// Return no results
+    #[default]
+    Empty,
+    /// Return the placement which covers the most cells, even if it isn't accepted otherwise
+    BestPartial,
+    /// Fail with [`NoSolutionError`], see [`Configuration::try_run`]
+    Error,
+}
+
+#[derive(Debug, Error, PartialEq, Diagnostic)]
+#[error("No placement satisfies the configuration")]
+pub struct NoSolutionError;
+
+/// Why a search stopped, see [`Configuration::run_with_outcome`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RunOutcome {
+    /// Every branch is explored, so nothing else could be found
+    Complete,
+    /// [`Configuration::results_limit`] results are found
+    LimitReached,
+    /// [`Configuration::max_recursions`] steps are taken or [`Configuration::time_limit`] is
+    /// over, see [`CollectStats::search_truncated`]
+    Truncated,
+    /// The search is stopped from outside, which only [`Configuration::run_cancellable`] allows
+    Cancelled,
+}
+
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq)]
+pub enum SearchMode {
+    /// Try randomly picked tetras on each step
+    #[default]
+    Random,
+    /// Try every tetra on each step, in order. Slower, but deterministic
+    Exhaustive,
+}
+
+/// Limits the number of tetras in a placement. Tetras cover 4 cells each, so when the limit is
+/// reached the placement is recorded regardless of how many cells are left free.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PieceCount {
+    /// Record only placements of exactly this many tetras
+    Exact(usize),
+    /// Stop placing tetras once there are this many
+    Max(usize),
+}
+
+impl PieceCount {
+    fn limit(self) -> usize {
+        match self {
+            PieceCount::Exact(count) | PieceCount::Max(count) => count,
         }
     }
+}
 
-    pub fn with_results_limit(mut self, value: NonZeroUsize) -> Self {
-        self.results_limit = Some(value);
-        self
-    }
+/// Order in which cells of the field are tried when looking for a place for a tetra.
+///
+/// It biases which placements are found first, e.g. with [`SearchMode::Random`] and
+/// [`Configuration::with_results_limit`].
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq)]
+pub enum ScanOrder {
+    /// Row by row, left to right
+    #[default]
+    Row,
+    /// Column by column, top to bottom
+    Column,
+    /// Clockwise from the top left corner to the center
+    Spiral,
+    /// Shuffled once per search. A search continued with [`Configuration::with_resume`] doesn't
+    /// yield the same results as the uninterrupted one would
+    Random,
+    /// On every step, the empty cell which the fewest placements could cover is filled first,
+    /// with every placement covering it. Ties are broken row by row. Prunes dead ends early, but
+    /// takes longer per step
+    MostConstrainedFirst,
+}
 
-    pub fn run<S>(&self, stats: &'_ mut S) -> BTreeSet<PlacementResult>
-    where
-        S: CollectStats,
-    {
-        RecursionState::find_placements(self, stats)
-    }
+/// Which place a search step takes for a tetra, among all the places where it fits. Each step
+/// tries a single place for each tetra, so the policy changes which placements are found and how
+/// soon. Not used with [`ScanOrder::MostConstrainedFirst`], which takes the place covering its
+/// cell
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq)]
+pub enum FitPolicy {
+    /// The first one in the [`ScanOrder`]
+    #[default]
+    First,
+    /// The last one in the [`ScanOrder`]
+    Last,
+    /// The one covering the empty cell which the fewest placements of any tetra could cover, the
+    /// first one among equal ones
+    MostConstrainedCell,
+    /// A random one. The generator is seeded from entropy unless the seed is given, which makes
+    /// the picks reproducible
+    Random(Option<u64>),
 }
 
-#[derive(Clone, Copy, derive_more::DebugCustom)]
-pub enum Cell {
-    #[debug(fmt = "-")]
-    Empty,
-    #[debug(fmt = "#")]
-    Unavailable,
-    #[debug(fmt = "+")]
-    Occupied,
+/// Narrows down the places found for a tetra before the [`FitPolicy`] picks one. Places are
+/// only preferred, never ruled out, so the same placements could still be found
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq)]
+pub enum Heuristic {
+    #[default]
+    None,
+    /// Places in the largest connected area of empty cells, so that the field doesn't break up
+    /// into small areas which tetras can't fill. Sizes of the areas are refreshed every
+    /// [`REGIONS_REFRESH`] steps rather than on each one, so they might be slightly off
+    FillLargest,
 }
 
-struct DebugGrid<'a>(&'a Grid<Cell>);
+/// Steps of the search between recounting the areas for [`Heuristic::FillLargest`]
+pub const REGIONS_REFRESH: usize = 16;
 
-impl std::fmt::Debug for DebugGrid<'_> {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        for row in 0..self.0.rows() {
-            for col in 0..self.0.cols() {
-                self.0[row][col].fmt(f)?;
+impl ScanOrder {
+    /// Every cell of the field, in this order
+    pub fn cells(self, size: Size, rng: &mut impl Rng) -> Vec<Pos> {
+        let Size { rows, cols } = size;
+        let mut cells: Vec<_> = match self {
+            ScanOrder::Row | ScanOrder::Random | ScanOrder::MostConstrainedFirst => (0..rows)
+                .flat_map(|row| (0..cols).map(move |col| Pos::new(row, col)))
+                .collect(),
+            ScanOrder::Column => (0..cols)
+                .flat_map(|col| (0..rows).map(move |row| Pos::new(row, col)))
+                .collect(),
+            ScanOrder::Spiral => Self::spiral(size),
+        };
+        if self == ScanOrder::Random {
+            cells.shuffle(rng);
+        }
+        cells
+    }
+
+    fn spiral(Size { rows, cols }: Size) -> Vec<Pos> {
+        let mut cells = Vec::with_capacity(rows * cols);
+        let (mut top, mut left) = (0, 0);
+        let (mut bottom, mut right) = (rows, cols);
+        while top < bottom && left < right {
+            cells.extend((left..right).map(|col| Pos::new(top, col)));
+            cells.extend((top + 1..bottom).map(|row| Pos::new(row, right - 1)));
+            if bottom - top > 1 && right - left > 1 {
+                cells.extend((left..right - 1).rev().map(|col| Pos::new(bottom - 1, col)));
+                cells.extend((top + 1..bottom - 1).rev().map(|row| Pos::new(row, left)));
             }
-            f.write_str("\n")?;
+            (top, left, bottom, right) = (top + 1, left + 1, bottom - 1, right - 1);
         }
-        Ok(())
+        cells
     }
 }
 
-#[derive(Debug)]
-struct RecursionState<'a, S>
-where
-    S: CollectStats,
-{
-    grid: Grid<Cell>,
-    how_many_free: usize,
-    stack: Vec<PlacedBoundariesChecked>,
-    results: BTreeSet<PlacementResult>,
-    positions_for_lookup: Vec<Pos>,
-    stats: &'a mut S,
+/// Restricts where tetras might be placed during the search.
+///
+/// Consulted for every candidate placement, after it is checked that the candidate lays within
+/// the field boundaries and covers only empty cells.
+pub trait Constraint: std::fmt::Debug + Send + Sync {
+    fn allows(&self, grid: &Grid<Cell>, candidate: &PlacedBoundariesChecked) -> bool;
+}
 
-    results_limit: Option<NonZeroUsize>,
+#[derive(Debug, Error, PartialEq, Diagnostic)]
+pub enum PlacementError {
+    #[error("Tetra placed at {0} doesn't fit into the field")]
+    OutOfBoundaries(Pos),
+    #[error("Tetra overlaps an unavailable or already occupied cell at {0}")]
+    Overlap(Pos),
+}
 
-    acceptance_threshold: usize,
-    random_tetras: Shuffler,
+#[derive(Debug, Error, PartialEq, Diagnostic)]
+pub enum ResumeError {
+    #[error(transparent)]
+    Placement(#[from] PlacementError),
+    #[error("Checkpoint is inconsistent, maybe it was taken for another configuration")]
+    Inconsistent,
 }
 
-impl<'a, S> RecursionState<'a, S>
-where
-    S: CollectStats,
-{
-    fn find_placements(cfg: &Configuration, stats: &'a mut S) -> BTreeSet<PlacementResult> {
-        let mut recursion = RecursionState::with_configuration(cfg, stats);
-        recursion.run();
-        recursion.results
+impl Configuration {
+    pub fn new(size: Size, unavailable: HashSet<Pos>) -> Self {
+        Self {
+            size,
+            unavailable,
+            outside: HashSet::new(),
+            optional: HashSet::new(),
+            results_limit: None,
+            prefilled: Vec::new(),
+            constraints: Vec::new(),
+            mode: SearchMode::default(),
+            resume: None,
+            pieces: TETRAS.iter().collect(),
+            required_pieces: HashSet::new(),
+            min_piece_counts: HashMap::new(),
+            enclose_blocked: false,
+            gravity: false,
+            complete: false,
+            parity_prune: false,
+            no_solution: NoSolutionPolicy::default(),
+            scan_order: ScanOrder::default(),
+            fit_policy: FitPolicy::default(),
+            heuristic: Heuristic::default(),
+            piece_count: None,
+            max_depth: None,
+            free_cells_slack: 0,
+            record_all_maximal: false,
+            max_recursions: None,
+            time_limit: None,
+            seed: None,
+            placement_order: false,
+            symmetry_breaking: false,
+            score: None,
+        }
+    }
+
+    /// Builds the configuration from a field literal in the default [`Parser`] format.
+    ///
+    /// ```
+    /// use brutal_tetris_hacker::algorithm::Configuration;
+    /// use brutal_tetris_hacker::util::{Pos, Size};
+    ///
+    /// let conf = Configuration::from_board_str("---\n-x-").unwrap();
+    /// assert_eq!(conf.size, Size::new(2, 3));
+    /// assert!(conf.unavailable.contains(&Pos::new(1, 1)));
+    /// ```
+    pub fn from_board_str(board: &str) -> Result<Self, ParseError> {
+        Parser::default()
+            .parse_without_source_code(board)
+            .map(Self::from_parsed)
     }
 
-    fn with_configuration(
-        Configuration {
+    pub fn from_parsed(
+        ParsedField {
             size,
             unavailable,
-            results_limit,
-        }: &Configuration,
-        stats: &'a mut S,
+            outside,
+            optional,
+        }: ParsedField,
     ) -> Self {
-        let (rows, cols) = (size.rows, size.cols);
+        Self::new(size, unavailable)
+            .with_outside(outside)
+            .with_optional(optional)
+    }
 
-        let mut grid = Grid::init(rows, cols, Cell::Empty);
-        let mut how_many_free = rows * cols;
-        let mut initially_unavailable = 0;
-        for Pos { row, col } in unavailable.iter() {
-            grid[*row][*col] = Cell::Unavailable;
-            initially_unavailable += 1;
-            how_many_free -= 1;
+    /// The field without the search options, the reverse of [`Configuration::from_parsed`]
+    pub fn to_parsed(&self) -> ParsedField {
+        ParsedField {
+            size: self.size,
+            unavailable: self.unavailable.clone(),
+            outside: self.outside.clone(),
+            optional: self.optional.clone(),
         }
-        let min_free_cells = (cols * rows - initially_unavailable) % 4;
-        let acceptance_threshold =
-            ((how_many_free - min_free_cells) as f64).powf(0.5).floor() as usize;
+    }
 
-        let stack = Vec::with_capacity(cols * rows);
+    /// Makes the field non-rectangular by excluding the given cells from it
+    pub fn with_outside(mut self, outside: HashSet<Pos>) -> Self {
+        self.outside = outside;
+        self
+    }
 
-        // all positions except unavailable
-        let mut iter_positions = Vec::with_capacity(cols * rows);
-        for row in 0..rows {
-            for col in 0..cols {
-                if let Cell::Empty = grid[row][col] {
-                    iter_positions.push(Pos { row, col });
-                }
-            }
+    /// Marks empty cells as optional: tetras might cover them, but placements leaving them empty
+    /// are as good. Unavailable and outside cells stay such
+    pub fn with_optional(mut self, optional: HashSet<Pos>) -> Self {
+        self.optional = optional;
+        self
+    }
+
+    /// Optional cells are playable too
+    pub fn cell_kind(&self, pos: &Pos) -> CellKind {
+        if self.outside.contains(pos) {
+            CellKind::Outside
+        } else if self.unavailable.contains(pos) {
+            CellKind::Blocked
+        } else {
+            CellKind::Playable
         }
+    }
 
-        Self {
-            grid,
-            how_many_free,
-            acceptance_threshold,
+    /// How many cells of the field are available for tetras
+    pub fn playable_cells(&self) -> usize {
+        self.size.rows * self.size.cols - self.unavailable.union(&self.outside).count()
+    }
 
-            stack,
-            results: BTreeSet::new(),
-            stats,
+    /// How many playable cells should be covered, i.e. the ones which are not optional
+    pub fn required_cells(&self) -> usize {
+        let optional = self
+            .optional
+            .iter()
+            .filter(|pos| self.cell_kind(pos) == CellKind::Playable)
+            .count();
+        self.playable_cells() - optional
+    }
 
-            positions_for_lookup: iter_positions,
+    /// Whether the field could be covered completely at all: every area of empty cells, bounded
+    /// by occupied cells and prefilled tetras, should have a multiple of 4 cells, unless it has
+    /// optional cells. Passing the check doesn't mean the field could be covered, failing it
+    /// means it couldn't
+    pub fn is_complete_feasible(&self) -> bool {
+        let occupied = self.occupied_cells();
+        let mut visited = HashSet::new();
+        for row in 0..self.size.rows {
+            for col in 0..self.size.cols {
+                let start = Pos::new(row, col);
+                if occupied.contains(&start) || visited.contains(&start) {
+                    continue;
+                }
 
-            results_limit: *results_limit,
-            random_tetras: Shuffler::new(),
+                let mut area = 0usize;
+                let mut has_optional = false;
+                let mut queue = vec![start];
+                visited.insert(start);
+                while let Some(pos) = queue.pop() {
+                    area += 1;
+                    has_optional |= self.optional.contains(&pos);
+                    let neighbours = [
+                        pos.row.checked_sub(1).map(|row| Pos::new(row, pos.col)),
+                        Some(Pos::new(pos.row + 1, pos.col)),
+                        pos.col.checked_sub(1).map(|col| Pos::new(pos.row, col)),
+                        Some(Pos::new(pos.row, pos.col + 1)),
+                    ];
+                    for next in neighbours.into_iter().flatten() {
+                        if next.in_bounds(&self.size)
+                            && !occupied.contains(&next)
+                            && visited.insert(next)
+                        {
+                            queue.push(next);
+                        }
+                    }
+                }
+                if !has_optional && !area.is_multiple_of(4) {
+                    return false;
+                }
+            }
         }
+        true
     }
 
-    fn run(&mut self) -> ControlFlow<()> {
-        self.stats.recursions_inc();
-
-        let mut was_any_fit = false;
+    /// Whether a checkerboard coloring lets the tetras cover every playable cell. A tetra covers
+    /// two cells of each color, except a `T`, which covers three of one color and one of the
+    /// other, so the `T` tetras among [`Configuration::pieces`] should make up the difference
+    /// between the colors of the empty cells. Like [`Configuration::is_complete_feasible`],
+    /// failing the check means the field couldn't be covered, passing it doesn't mean it could
+    pub fn is_parity_feasible(&self) -> bool {
+        if !self.optional.is_empty() {
+            return true;
+        }
 
-        for tetra in self.random_tetras.finite_iter() {
-            if let Some(tetra_in_boundaries) = self.find_any_fit_for(tetra) {
-                was_any_fit = true;
-                self.fill_and_push(tetra_in_boundaries);
-                if let ControlFlow::Break(_) = self.run() {
-                    return ControlFlow::Break(());
+        let occupied = self.occupied_cells();
+        let (mut dark, mut light) = (0usize, 0usize);
+        for row in 0..self.size.rows {
+            for col in 0..self.size.cols {
+                if occupied.contains(&Pos::new(row, col)) {
+                    continue;
+                }
+                if (row + col).is_multiple_of(2) {
+                    dark += 1;
+                } else {
+                    light += 1;
                 }
-                self.pop_and_clear();
             }
         }
+        if !(dark + light).is_multiple_of(4) {
+            return false;
+        }
+        let tetras = (dark + light) / 4;
+        let imbalance = dark.abs_diff(light);
 
-        if !was_any_fit && self.how_many_free < self.acceptance_threshold {
-            let result = PlacementResult {
-                placement: self.stack.iter().cloned().collect(),
-                free: self.how_many_free,
-            };
-            if !self.results.contains(&result) {
-                self.results.insert(result);
-                self.stats.results_inc();
-                if let Some(limit) = self.results_limit {
-                    if self.results.len() == limit.get() {
-                        return ControlFlow::Break(());
-                    }
-                }
-            }
+        // how many more cells of one color than of the other a tetra covers, either 0 or 2
+        let shift = |tetra: &Tetra| {
+            let dark = tetra
+                .iter()
+                .filter(|pos| (pos.row + pos.col).is_multiple_of(2))
+                .count();
+            dark.abs_diff(tetra.iter().count() - dark)
+        };
+        let balanced = self.pieces.iter().any(|tetra| shift(tetra) == 0);
+        let unbalanced = self.pieces.iter().any(|tetra| shift(tetra) == 2);
+        match (balanced, unbalanced) {
+            (_, false) => imbalance == 0 && (balanced || tetras == 0),
+            (true, true) => imbalance <= 2 * tetras,
+            // each tetra either adds 2 to the difference or takes 2 from it
+            (false, true) => imbalance <= 2 * tetras && (2 * tetras - imbalance).is_multiple_of(4),
         }
+    }
 
-        ControlFlow::Continue(())
+    /// Proves that nothing could be found before searching, see
+    /// [`Configuration::is_parity_feasible`]. Applies only to [`Configuration::complete`]
+    /// searches, and not with [`NoSolutionPolicy::BestPartial`], which needs the search to find
+    /// the partial placement
+    pub fn with_parity_prune(mut self, value: bool) -> Self {
+        self.parity_prune = value;
+        self
     }
 
-    fn fill_and_push(&mut self, tetra: PlacedBoundariesChecked) {
-        for i in tetra.iter_relative_to_place() {
-            self.grid[i.row][i.col] = Cell::Occupied;
-            self.how_many_free -= 1;
-        }
-        self.stack.push(tetra);
+    pub fn with_mode(mut self, mode: SearchMode) -> Self {
+        self.mode = mode;
+        self
     }
 
-    fn pop_and_clear(&mut self) {
-        let placed_tetra = self.stack.pop().unwrap();
-        for i in placed_tetra.iter_relative_to_place() {
-            self.grid[i.row][i.col] = Cell::Empty;
-            self.how_many_free += 1;
-        }
+    pub fn with_scan_order(mut self, order: ScanOrder) -> Self {
+        self.scan_order = order;
+        self
     }
 
-    fn find_any_fit_for(&self, tetra: &'static Tetra) -> Option<PlacedBoundariesChecked> {
-        self.positions_for_lookup
-            .iter()
-            .map(|pos| {
-                PlacedBoundariesChecked::in_boundaries(
-                    Placed::new(tetra, *pos),
-                    self.grid.size_of(),
-                )
-            })
-            .find(|in_boundaries| {
-                if let Some(in_boundaries) = in_boundaries {
-                    let all_empty = in_boundaries
-                        .iter_relative_to_place()
-                        .all(|pos| matches!(self.grid.pos(&pos), Cell::Empty));
-                    return all_empty;
-                }
-                false
-            })
-            .flatten()
+    pub fn with_fit_policy(mut self, policy: FitPolicy) -> Self {
+        self.fit_policy = policy;
+        self
     }
-}
 
-#[derive(Debug, Hash, Ord, PartialOrd, Eq, PartialEq)]
-pub struct PlacementResult {
-    pub placement: Placement,
-    pub free: usize,
-}
+    pub fn with_heuristic(mut self, heuristic: Heuristic) -> Self {
+        self.heuristic = heuristic;
+        self
+    }
 
-pub trait CollectStats {
-    fn recursions_inc(&mut self);
+    pub fn with_constraint(mut self, constraint: impl Constraint + 'static) -> Self {
+        self.constraints.push(Box::new(constraint));
+        self
+    }
 
-    fn results_inc(&mut self);
-}
+    /// Places only the given tetras, e.g. to leave some shapes out. Tetras which are not in
+    /// [`TETRAS`] are ignored, since tetras are identified by their index there
+    pub fn with_pieces(mut self, pieces: Vec<&'static Tetra>) -> Self {
+        self.pieces = pieces;
+        self
+    }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    /// Records only placements which contain each of the given tetras at least once
+    pub fn with_required_pieces(mut self, pieces: HashSet<&'static Tetra>) -> Self {
+        self.required_pieces = pieces;
+        self
+    }
 
-    #[derive(Debug)]
-    struct StatsDummy;
+    /// Records only placements which contain each of the given tetras at least the given number
+    /// of times, e.g. two `I` tetras for a puzzle. A count of 1 is the same as requiring the tetra
+    /// with [`Configuration::with_required_pieces`]
+    pub fn with_min_piece_counts(mut self, counts: HashMap<&'static Tetra, usize>) -> Self {
+        self.min_piece_counts = counts;
+        self
+    }
 
-    impl CollectStats for StatsDummy {
-        fn recursions_inc(&mut self) {}
+    pub fn with_complete(mut self, value: bool) -> Self {
+        self.complete = value;
+        self
+    }
 
-        fn results_inc(&mut self) {}
+    pub fn on_no_solution(mut self, policy: NoSolutionPolicy) -> Self {
+        self.no_solution = policy;
+        self
     }
 
-    // #[test]
-    mod caching {
-        use super::*;
-        use crate::tetra::I_HORIZONTAL;
+    pub fn with_exact_piece_count(mut self, count: usize) -> Self {
+        self.piece_count = Some(PieceCount::Exact(count));
+        self
+    }
 
-        fn config_factory() -> Configuration {
-            Configuration::new(Size::new(8, 8), HashSet::new())
+    pub fn with_max_piece_count(mut self, count: usize) -> Self {
+        self.piece_count = Some(PieceCount::Max(count));
+        self
+    }
+
+    pub fn with_max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = Some(depth);
+        self
+    }
+
+    pub fn with_max_recursions(mut self, recursions: usize) -> Self {
+        self.max_recursions = Some(recursions);
+        self
+    }
+
+    pub fn with_time_limit(mut self, limit: Duration) -> Self {
+        self.time_limit = Some(limit);
+        self
+    }
+
+    /// Seeds the tetras drawn in [`SearchMode::Random`], the [`ScanOrder::Random`] order and the
+    /// [`FitPolicy::Random`] picks, unless the policy has its own seed
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Loosens the acceptance threshold, so that placements leaving up to `slack` more free
+    /// cells are recorded too
+    pub fn with_free_cells_slack(mut self, slack: usize) -> Self {
+        self.free_cells_slack = slack;
+        self
+    }
+
+    /// Records every placement to which no tetra could be added, with its actual number of free
+    /// cells, even in the complete mode. Useful to study why a field can't be covered, but
+    /// produces many results. Required pieces and enclosing are still checked, and placements
+    /// cut short by piece count or depth limits aren't maximal, so they are accepted as usual
+    pub fn record_all_maximal(mut self, value: bool) -> Self {
+        self.record_all_maximal = value;
+        self
+    }
+
+    /// Records the order tetras were placed in, e.g. to replay how the field was filled. Takes
+    /// memory for another copy of each placement
+    pub fn with_placement_order(mut self, value: bool) -> Self {
+        self.placement_order = value;
+        self
+    }
+
+    /// Restricts the first tetra the search places to a fundamental domain of the field
+    /// symmetries: the first cell covered is the one kept in place by the most of them, e.g. the
+    /// center of an odd square field, and of the placements covering it which turn into each other
+    /// under those symmetries only one is tried. Every result is still found up to a symmetry,
+    /// though some of them may be found along with their images.
+    ///
+    /// This holds only if every placement covering a cell is tried and only complete placements
+    /// are recorded, so the search is restricted only in [`SearchMode::Exhaustive`] with
+    /// [`ScanOrder::MostConstrainedFirst`] and [`Configuration::complete`]. Does nothing if the
+    /// field has no symmetries or there are constraints. With gravity, only the reflection across
+    /// the vertical axis is used
+    pub fn with_symmetry_breaking(mut self, value: bool) -> Self {
+        self.symmetry_breaking = value;
+        self
+    }
+
+    /// Ranks the results by the score, doesn't change the search itself
+    pub fn with_score(mut self, score: ScoreMode) -> Self {
+        self.score = Some(score);
+        self
+    }
+
+    /// The score of the result, if [`Configuration::score`] is set
+    pub fn score_of(&self, result: &PlacementResult) -> Option<f64> {
+        self.score.as_ref().map(|score| score.of(result, self))
+    }
+
+    /// The results from the best score to the worst, keeping only the `top` ones if set. Without
+    /// a score, in the given order
+    pub fn ranked<'a>(
+        &self,
+        results: impl IntoIterator<Item = &'a PlacementResult>,
+        top: Option<NonZeroUsize>,
+    ) -> Vec<&'a PlacementResult> {
+        match &self.score {
+            Some(score) => score.rank(results, self, top),
+            None => results
+                .into_iter()
+                .take(top.map_or(usize::MAX, NonZeroUsize::get))
+                .collect(),
         }
+    }
 
-        impl<'a, S> RecursionState<'a, S>
-        where
-            S: CollectStats,
-        {
-            fn force_fill(&mut self, tetra: &'static Tetra) {
-                self.fill_and_push(self.find_any_fit_for(tetra).unwrap());
+    pub fn with_enclose_blocked(mut self, value: bool) -> Self {
+        self.enclose_blocked = value;
+        self
+    }
+
+    /// Forbids floating tetras: some cell right below the bottom cells of each tetra should be
+    /// the floor, an unavailable cell or a cell of another tetra. Cells outside of the field
+    /// count as the floor
+    pub fn with_gravity(mut self, value: bool) -> Self {
+        self.gravity = value;
+        self
+    }
+
+    pub fn with_results_limit(mut self, value: NonZeroUsize) -> Self {
+        self.results_limit = Some(value);
+        self
+    }
+
+    /// Puts a tetra into the field before the search, so that every result contains it
+    pub fn with_prefilled(mut self, placed: Placed) -> Result<Self, PlacementError> {
+        let placed = self.check_placement(placed, &mut self.occupied_cells())?;
+        self.prefilled.push(placed);
+        Ok(self)
+    }
+
+    /// Continues the search from the given checkpoint instead of starting it over.
+    ///
+    /// Should be called after all tetras are prefilled.
+    pub fn with_resume(mut self, checkpoint: Checkpoint) -> Result<Self, ResumeError> {
+        let Checkpoint {
+            frames,
+            stack,
+            results,
+        } = &checkpoint;
+
+        let cells_per_id = match self.scan_order {
+            ScanOrder::MostConstrainedFirst => TETRA_CELLS,
+            _ => 1,
+        };
+        let ids = TETRAS.len() * cells_per_id;
+        let frames_consistent = frames.iter().all(|frame| {
+            frame.next <= frame.len(cells_per_id)
+                && frame.tetras.iter().all(|id| *id < ids)
+                && frame.drawn.is_none_or(|id| id < TETRAS.len())
+        });
+        if frames.len() != stack.len() + 1 || !frames_consistent {
+            return Err(ResumeError::Inconsistent);
+        }
+
+        let mut occupied = self.occupied_cells();
+        for placed in stack {
+            self.check_placement(placed.clone(), &mut occupied)?;
+        }
+        for result in results {
+            for placed in &result.placement {
+                PlacedBoundariesChecked::in_boundaries(placed.clone(), self.size)
+                    .ok_or(PlacementError::OutOfBoundaries(placed.position))?;
             }
         }
 
-        #[test]
-        fn all_positions_initially() {
-            let mut stats = StatsDummy;
-            let rec = RecursionState::with_configuration(&config_factory(), &mut stats);
+        self.resume = Some(checkpoint);
+        Ok(self)
+    }
 
-            assert_eq!(rec.positions_for_lookup.len(), 8 * 8);
+    /// Puts the tetras into the field without searching, e.g. to check a placement made
+    /// elsewhere before rendering it. Prefilled tetras are put too, and skipped if given again,
+    /// so that [`PlacementResult::placement`] could be applied as is
+    pub fn apply(
+        &self,
+        placements: &[PlacedBoundariesChecked],
+    ) -> Result<Grid<Cell>, PlacementError> {
+        let mut occupied = self.occupied_cells();
+        let mut placed = Vec::with_capacity(placements.len());
+        for candidate in placements
+            .iter()
+            .filter(|candidate| !self.is_prefilled(candidate))
+        {
+            // checked against the boundaries of another field, maybe
+            placed.push(self.check_placement(Placed::from(candidate.clone()), &mut occupied)?);
         }
 
-        #[test]
-        fn cache_behaviour() {
-            let mut stats = StatsDummy;
-            let mut rec = RecursionState::with_configuration(&config_factory(), &mut stats);
+        let mut grid = self.field_grid();
+        for pos in self
+            .prefilled
+            .iter()
+            .chain(&placed)
+            .flat_map(|placed| placed.iter_relative_to_place())
+        {
+            grid[pos.row][pos.col] = Cell::Occupied;
+        }
+        Ok(grid)
+    }
 
-            rec.force_fill(I_HORIZONTAL);
-            rec.force_fill(I_HORIZONTAL);
-            rec.force_fill(I_HORIZONTAL);
+    /// The field before any tetra is put into it
+    fn field_grid(&self) -> Grid<Cell> {
+        let mut grid = Grid::init(self.size.rows, self.size.cols, Cell::Empty);
+        for Pos { row, col } in self.optional.iter() {
+            grid[*row][*col] = Cell::Optional;
+        }
+        for Pos { row, col } in self.unavailable.iter() {
+            grid[*row][*col] = Cell::Unavailable;
+        }
+        for Pos { row, col } in self.outside.iter() {
+            grid[*row][*col] = Cell::Outside;
+        }
+        grid
+    }
 
-            assert_eq!(rec.positions_for_lookup.len(), 8 * 8);
+    /// Unavailable and outside cells, and cells occupied by prefilled tetras
+    fn occupied_cells(&self) -> HashSet<Pos> {
+        self.prefilled
+            .iter()
+            .flat_map(|placed| placed.iter_relative_to_place())
+            .chain(self.unavailable.iter().copied())
+            .chain(self.outside.iter().copied())
+            .collect()
+    }
 
-            rec.force_fill(I_HORIZONTAL);
+    /// Checks that the tetra fits into the field, and marks its cells as occupied
+    fn check_placement(
+        &self,
+        placed: Placed,
+        occupied: &mut HashSet<Pos>,
+    ) -> Result<PlacedBoundariesChecked, PlacementError> {
+        let position = placed.position;
+        let placed = PlacedBoundariesChecked::in_boundaries(placed, self.size)
+            .ok_or(PlacementError::OutOfBoundaries(position))?;
 
-            assert_eq!(rec.positions_for_lookup.len(), 8 * 8 - 4 * 4);
+        for pos in placed.iter_relative_to_place() {
+            if !occupied.insert(pos) {
+                return Err(PlacementError::Overlap(pos));
+            }
+        }
 
-            rec.force_fill(I_HORIZONTAL);
+        Ok(placed)
+    }
 
-            // still
-            assert_eq!(rec.positions_for_lookup.len(), 8 * 8 - 4 * 4);
+    pub fn is_prefilled(&self, placed: &PlacedBoundariesChecked) -> bool {
+        self.prefilled.contains(placed)
+    }
 
-            rec.pop_and_clear();
-            rec.pop_and_clear();
+    /// Returns no results if nothing is found, whatever the [`NoSolutionPolicy`] is
+    pub fn run<S>(&self, stats: &'_ mut S) -> BTreeSet<PlacementResult>
+    where
+        S: CollectStats,
+    {
+        self.run_with_outcome(stats).0
+    }
 
-            assert_eq!(rec.positions_for_lookup.len(), 8 * 8);
-        }
+    /// Like [`Configuration::run`], but also tells whether the search explored everything or
+    /// stopped at a limit, so that the results could be told complete or not
+    pub fn run_with_outcome<S>(&self, stats: &'_ mut S) -> (BTreeSet<PlacementResult>, RunOutcome)
+    where
+        S: CollectStats,
+    {
+        let (results, outcome) = RecursionState::find_placements(self, None, None, None, stats);
+        (results.unwrap_or_default(), outcome)
     }
 
-    #[test]
-    fn results_count_for_empty_4x4() {
-        let cfg = Configuration::new(Size::new(4, 4), HashSet::new());
+    /// Passes results into the sink as they are found instead of keeping them, so that how many
+    /// can be found isn't bound by memory. Whether to skip results found again and when to stop
+    /// is up to the sink: [`Configuration::results_limit`] and [`Configuration::no_solution`]
+    /// are not consulted. [`CollectStats::results_inc`] is called for every result passed in
+    pub fn run_into<S>(&self, sink: &mut dyn ResultSink, stats: &'_ mut S) -> RunOutcome
+    where
+        S: CollectStats,
+    {
+        RecursionState::find_placements(self, None, None, Some(sink), stats).1
+    }
+
+    /// Fails if nothing is found and the policy is [`NoSolutionPolicy::Error`]
+    pub fn try_run<S>(&self, stats: &'_ mut S) -> Result<BTreeSet<PlacementResult>, NoSolutionError>
+    where
+        S: CollectStats,
+    {
+        RecursionState::find_placements(self, None, None, None, stats).0
+    }
+
+    /// Stops the search once `cancel` is set, e.g. from another thread, returning what is found
+    /// by then
+    pub fn run_cancellable<S>(&self, stats: &'_ mut S, cancel: &AtomicBool) -> Vec<PlacementResult>
+    where
+        S: CollectStats,
+    {
+        RecursionState::find_placements(self, None, Some(cancel), None, stats)
+            .0
+            .unwrap_or_default()
+            .into_iter()
+            .collect()
+    }
+
+    /// Runs `workers` searches on their own threads, each skipping the results another one has
+    /// already found, see [`SharedSignatures`]. Useful with [`SearchMode::Random`], so that workers
+    /// explore the field differently. [`Configuration::results_limit`] is for all the workers
+    /// together. Returns no results if nothing is found, whatever the [`NoSolutionPolicy`] is
+    pub fn run_parallel(&self, workers: NonZeroUsize) -> BTreeSet<PlacementResult> {
+        let seen = SharedSignatures::default();
+        let found: Vec<_> = std::thread::scope(|scope| {
+            let handles: Vec<_> = (0..workers.get())
+                .map(|_| {
+                    scope.spawn(|| {
+                        let mut stats = NoopStats;
+                        let mut recursion = RecursionState::with_configuration(self, &mut stats);
+                        recursion.memory.shared = Some(&seen);
+                        let _ = recursion.run();
+                        recursion.memory.results
+                    })
+                })
+                .collect();
+            handles
+                .into_iter()
+                .map(|handle| handle.join().expect("search worker panicked"))
+                .collect()
+        });
+        // workers never find the same result, so there is nothing to deduplicate here. They could
+        // find a few more than the limit together before they notice it is reached
+        let limit = self.results_limit.map_or(usize::MAX, NonZeroUsize::get);
+        found.into_iter().flatten().take(limit).collect()
+    }
+
+    /// The first accepted placement, if any. The search stops as soon as it is found, and
+    /// [`Configuration::no_solution`] is not consulted
+    pub fn solve_one(&self) -> Option<PlacementResult> {
+        let mut stats = NoopStats;
+        let mut recursion = RecursionState::with_configuration(self, &mut stats);
+        recursion.memory.limit = NonZeroUsize::new(1);
+        let _ = recursion.run();
+        recursion.memory.results.pop_first()
+    }
+
+    /// The fewest free cells a placement found by the search leaves, or `None` if nothing is
+    /// found, e.g. because of [`Configuration::required_pieces`]. Every maximal placement counts,
+    /// as with [`Configuration::record_all_maximal`], and none of them are kept. The search stops
+    /// once a placement leaves only the cells no tetra could cover, e.g. at 0 if the field can be
+    /// tiled
+    pub fn min_free(&self) -> Option<usize> {
+        let mut stats = NoopStats;
+        let mut recursion = RecursionState::with_configuration(self, &mut stats);
+        recursion.record_all_maximal = true;
+        // incomplete placements aren't found up to a symmetry
+        recursion.root_symmetries.clear();
+        // tetras may cover any number of optional cells, so with them every count is possible
+        let floor = if self.optional.is_empty() {
+            recursion.how_many_free % 4
+        } else {
+            0
+        };
+        let mut sink = MinFreeSink { min: None, floor };
+        recursion.sink = Some(&mut sink);
+        let _ = recursion.run();
+        sink.min
+    }
+
+    /// Estimates how hard the search is, without running it
+    pub fn estimate(&self) -> SearchEstimate {
+        RecursionState::with_configuration(self, &mut NoopStats).estimate()
+    }
+
+    /// A cheap measure of how hard the field is, to sort a collection of fields by. Unlike
+    /// [`Configuration::estimate`], it doesn't look at the candidates, only at the counts of cells:
+    ///
+    /// `free * (1 + obstacles / cells) / (1 + threshold)`
+    ///
+    /// where `free` are the cells which must be covered, `obstacles` the unavailable and outside
+    /// ones, `cells` all cells of the field and `threshold` how many cells a placement may leave
+    /// free and still be accepted. More cells to cover make the field harder, obstacles make its
+    /// shape irregular, and a looser threshold makes it easier
+    pub fn difficulty_estimate(&self) -> f64 {
+        let cells = self.size.rows * self.size.cols;
+        if cells == 0 {
+            return 0.0;
+        }
+        let free = self.required_cells();
+        let obstacles = cells - self.playable_cells();
+        let threshold = acceptance_threshold(free, free % 4) + self.free_cells_slack;
+
+        free as f64 * (1.0 + obstacles as f64 / cells as f64) / (1.0 + threshold as f64)
+    }
+
+    /// How many placements of any tetra could cover the cell before the search starts, i.e.
+    /// with only prefilled tetras in the field
+    pub fn placement_options(&self, pos: Pos) -> usize {
+        RecursionState::with_configuration(self, &mut NoopStats).placement_options(pos)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SearchEstimate {
+    /// How many cells are left to cover
+    pub free_cells: usize,
+    /// Whether free cells could be covered by tetras completely
+    pub divisible: bool,
+    /// How many (tetra, position) pairs fit into the initial field
+    pub candidates: usize,
+    /// How many distinct tetras fit into the initial field
+    pub branching: usize,
+    /// How many tetras could be placed at most
+    pub depth: usize,
+    /// Rough upper bound of the search tree size, `branching ^ depth`
+    pub tree_size: f64,
+}
+
+impl std::fmt::Display for SearchEstimate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "free cells: {}", self.free_cells)?;
+        writeln!(
+            f,
+            "divisible by 4: {}",
+            if self.divisible { "yes" } else { "no" }
+        )?;
+        writeln!(f, "candidate placements: {}", self.candidates)?;
+        writeln!(f, "branching factor: {}", self.branching)?;
+        writeln!(f, "max depth: {}", self.depth)?;
+        write!(f, "search tree size: ~{:.2e}", self.tree_size)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, derive_more::DebugCustom)]
+pub enum Cell {
+    #[debug(fmt = "-")]
+    Empty,
+    /// Empty, but not required to be covered
+    #[debug(fmt = "?")]
+    Optional,
+    #[debug(fmt = "#")]
+    Unavailable,
+    #[debug(fmt = "+")]
+    Occupied,
+    #[debug(fmt = " ")]
+    Outside,
+}
+
+#[derive(Debug)]
+struct RecursionState<'a, S>
+where
+    S: CollectStats,
+{
+    grid: Grid<Cell>,
+    /// The same as [`Self::grid`], but only whether cells are free, to check candidates quickly
+    occupancy: Occupancy,
+    how_many_free: usize,
+    stack: Vec<PlacedBoundariesChecked>,
+    /// Where results go unless there is [`Self::sink`]
+    memory: MemorySink<'a>,
+    /// See [`Configuration::run_into`]
+    sink: Option<&'a mut dyn ResultSink>,
+    candidates: Cow<'a, Candidates>,
+    stats: &'a mut S,
+
+    acceptance_threshold: usize,
+    random_tetras: Shuffler,
+    fit_policy: FitPolicy,
+    /// Picks the places with [`FitPolicy::Random`]
+    random_fits: StdRng,
+    heuristic: Heuristic,
+    /// [`empty_region_sizes`] of the grid, and the step they were counted at, for
+    /// [`Heuristic::FillLargest`]
+    regions: Option<(usize, Grid<usize>)>,
+    optional: &'a HashSet<Pos>,
+    /// Indices in [`TETRAS`] of [`Configuration::pieces`], in ascending order
+    piece_ids: Vec<usize>,
+    constraints: &'a [Box<dyn Constraint>],
+    required_pieces: &'a HashSet<&'static Tetra>,
+    min_piece_counts: &'a HashMap<&'static Tetra, usize>,
+    enclose_blocked: bool,
+    gravity: bool,
+    complete: bool,
+    no_solution: NoSolutionPolicy,
+    /// Placement covering the most cells among not accepted ones, for
+    /// [`NoSolutionPolicy::BestPartial`]
+    best_partial: Option<PlacementResult>,
+    piece_count: Option<PieceCount>,
+    max_depth: Option<usize>,
+    record_all_maximal: bool,
+    max_recursions: Option<usize>,
+    /// When [`Configuration::time_limit`] is over
+    deadline: Option<Instant>,
+    placement_order: bool,
+    /// Symmetries to restrict the first placement by, see
+    /// [`Configuration::with_symmetry_breaking`]
+    root_symmetries: Vec<Transform>,
+    /// The search stops once it is set
+    cancel: Option<&'a AtomicBool>,
+    /// See [`ScanOrder::MostConstrainedFirst`]
+    most_constrained_first: bool,
+
+    mode: SearchMode,
+    /// One frame per search step, i.e. per tetra placed by the search, plus the root one
+    frames: Vec<Frame>,
+    /// How many tetras in the stack are prefilled rather than placed by the search
+    prefilled_count: usize,
+    recursions: usize,
+    /// Time spent telling whether results are found already, see [`Phase::Dedup`]
+    dedup_elapsed: Duration,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+struct Frame {
+    /// Ids of tetras to try on this step, in order. With [`ScanOrder::MostConstrainedFirst`],
+    /// `tetra id * TETRA_CELLS + index of the tetra cell` to put onto [`Frame::focus`]
+    tetras: Vec<usize>,
+    /// How many of them are already tried
+    next: usize,
+    /// How many tetras to draw at random when [`Frame::tetras`] is empty, in
+    /// [`SearchMode::Random`]. They are drawn one at a time, as the search gets to them
+    #[serde(default)]
+    draws: usize,
+    /// The tetra drawn last, whose cells are yet to be tried with
+    /// [`ScanOrder::MostConstrainedFirst`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    drawn: Option<usize>,
+    was_any_fit: bool,
+    /// The cell to cover on this step, with [`ScanOrder::MostConstrainedFirst`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    focus: Option<Pos>,
+}
+
+/// How many cells each tetra has
+const TETRA_CELLS: usize = 4;
+
+impl Frame {
+    /// How many ids there are to try, `cells_per_id` for each drawn tetra
+    fn len(&self, cells_per_id: usize) -> usize {
+        self.tetras.len() + self.draws * cells_per_id
+    }
+
+    /// Takes the next id to try, drawing a new tetra from `piece_ids` if needed
+    fn next_id(
+        &mut self,
+        cells_per_id: usize,
+        shuffler: &mut Shuffler,
+        piece_ids: &[usize],
+    ) -> Option<usize> {
+        let id = match self.tetras.get(self.next) {
+            Some(&id) => id,
+            None if self.next < self.len(cells_per_id) => {
+                let cell = self.next % cells_per_id;
+                if cell == 0 {
+                    self.drawn = Some(shuffler.pick_from(piece_ids));
+                }
+                self.drawn? * cells_per_id + cell
+            }
+            None => return None,
+        };
+        self.next += 1;
+        Some(id)
+    }
+}
+
+/// Search state, enough to continue the search from the point it was taken at.
+///
+/// In [`SearchMode::Exhaustive`] the continued search yields exactly the same results as the
+/// uninterrupted one would.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Checkpoint {
+    frames: Vec<Frame>,
+    /// Tetras placed by the search so far, excluding prefilled ones
+    stack: Vec<Placed>,
+    results: Vec<CheckpointResult>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+struct CheckpointResult {
+    placement: Vec<Placed>,
+    free: usize,
+}
+
+impl<'a, S> RecursionState<'a, S>
+where
+    S: CollectStats,
+{
+    /// Builds candidates if they are not provided
+    fn find_placements(
+        cfg: &'a Configuration,
+        candidates: Option<&'a Candidates>,
+        cancel: Option<&'a AtomicBool>,
+        sink: Option<&'a mut dyn ResultSink>,
+        stats: &'a mut S,
+    ) -> (
+        Result<BTreeSet<PlacementResult>, NoSolutionError>,
+        RunOutcome,
+    ) {
+        let start = Instant::now();
+        let candidates = match candidates {
+            Some(candidates) => Cow::Borrowed(candidates),
+            None => Cow::Owned(Candidates::new(cfg)),
+        };
+        let mut recursion = RecursionState::with_candidates(cfg, candidates, stats);
+        recursion.cancel = cancel;
+        recursion.sink = sink;
+        recursion
+            .stats
+            .phase_finished(Phase::Setup, start.elapsed());
+
+        let start = Instant::now();
+        let pruned = cfg.parity_prune
+            && cfg.complete
+            && cfg.no_solution != NoSolutionPolicy::BestPartial
+            && !cfg.is_parity_feasible();
+        let outcome = if pruned {
+            RunOutcome::Complete
+        } else {
+            match recursion.run() {
+                ControlFlow::Continue(()) => RunOutcome::Complete,
+                ControlFlow::Break(outcome) => outcome,
+            }
+        };
+        if !recursion.memory.spilled.is_empty() {
+            recursion.spill_results();
+        }
+        let dedup = recursion.dedup_elapsed;
+        recursion
+            .stats
+            .phase_finished(Phase::Search, start.elapsed().saturating_sub(dedup));
+        recursion.stats.phase_finished(Phase::Dedup, dedup);
+
+        let found_nothing =
+            recursion.memory.results.is_empty() && recursion.memory.spilled.is_empty();
+        let results = match recursion.no_solution {
+            NoSolutionPolicy::Error if found_nothing => Err(NoSolutionError),
+            NoSolutionPolicy::BestPartial if found_nothing => {
+                Ok(recursion.best_partial.into_iter().collect())
+            }
+            _ => Ok(recursion.memory.results),
+        };
+        (results, outcome)
+    }
+
+    fn with_configuration(cfg: &'a Configuration, stats: &'a mut S) -> Self {
+        Self::with_candidates(cfg, Cow::Owned(Candidates::new(cfg)), stats)
+    }
+
+    fn with_candidates(
+        cfg @ Configuration {
+            size,
+            // put into the grid by `field_grid`
+            unavailable: _,
+            outside: _,
+            optional,
+            results_limit,
+            prefilled,
+            constraints,
+            mode,
+            resume,
+            pieces,
+            required_pieces,
+            min_piece_counts,
+            enclose_blocked,
+            gravity,
+            complete,
+            no_solution,
+            // consumed by the candidates, except for the dynamic order
+            scan_order,
+            fit_policy,
+            heuristic,
+            piece_count,
+            max_depth,
+            free_cells_slack,
+            record_all_maximal,
+            max_recursions,
+            time_limit,
+            // consumed by the candidates too
+            seed,
+            placement_order,
+            symmetry_breaking,
+            // results are ranked after the search
+            score: _,
+            // checked before the search
+            parity_prune: _,
+        }: &'a Configuration,
+        candidates: Cow<'a, Candidates>,
+        stats: &'a mut S,
+    ) -> Self {
+        let (rows, cols) = (size.rows, size.cols);
+
+        let grid = cfg.field_grid();
+        let how_many_free = cfg.required_cells();
+        let acceptance_threshold =
+            acceptance_threshold(how_many_free, how_many_free % 4) + free_cells_slack;
+
+        let stack = Vec::with_capacity(cols * rows);
+
+        let mut occupancy = Occupancy::new(*size);
+        for row in 0..rows {
+            for col in 0..cols {
+                if !matches!(grid[row][col], Cell::Empty | Cell::Optional) {
+                    occupancy.set(Pos::new(row, col));
+                }
+            }
+        }
+
+        let mut state = Self {
+            grid,
+            occupancy,
+            how_many_free,
+            acceptance_threshold,
+
+            stack,
+            memory: MemorySink::new(*results_limit),
+            sink: None,
+            stats,
+
+            candidates,
+
+            random_tetras: Shuffler::with_rng(seeded_rng(*seed)),
+            fit_policy: *fit_policy,
+            random_fits: match fit_policy {
+                FitPolicy::Random(Some(seed)) => StdRng::seed_from_u64(*seed),
+                _ => seeded_rng(*seed),
+            },
+            heuristic: *heuristic,
+            regions: None,
+            optional,
+            piece_ids: (0..TETRAS.len())
+                .filter(|id| pieces.contains(&&TETRAS[*id]))
+                .collect(),
+            constraints,
+            required_pieces,
+            min_piece_counts,
+            enclose_blocked: *enclose_blocked,
+            gravity: *gravity,
+            complete: *complete,
+            no_solution: *no_solution,
+            best_partial: None,
+            piece_count: *piece_count,
+            max_depth: *max_depth,
+            record_all_maximal: *record_all_maximal,
+            max_recursions: *max_recursions,
+            deadline: time_limit.map(|limit| Instant::now() + limit),
+            placement_order: *placement_order,
+            root_symmetries: Vec::new(),
+            cancel: None,
+            most_constrained_first: *scan_order == ScanOrder::MostConstrainedFirst,
+
+            mode: *mode,
+            frames: Vec::new(),
+            prefilled_count: prefilled.len(),
+            recursions: 0,
+            dedup_elapsed: Duration::ZERO,
+        };
+
+        for placed in prefilled {
+            state.fill_and_push(placed.clone());
+        }
+        let breaks_symmetry = *symmetry_breaking
+            && *mode == SearchMode::Exhaustive
+            && *complete
+            && *scan_order == ScanOrder::MostConstrainedFirst;
+        if breaks_symmetry {
+            state.root_symmetries = state.symmetries();
+        }
+
+        if let Some(checkpoint) = resume {
+            state.restore(checkpoint);
+        }
+
+        state
+    }
+
+    /// Transforms mapping the field, the pieces to place and the required ones onto themselves.
+    /// None if there are constraints, which could tell a placement from its images
+    fn symmetries(&self) -> Vec<Transform> {
+        if !self.constraints.is_empty() {
+            return Vec::new();
+        }
+        let size = Size::new(self.grid.rows(), self.grid.cols());
+        let maps_pieces = |transform: Transform| {
+            self.piece_ids.iter().all(|id| {
+                let image = TETRAS[*id].transformed(transform);
+                self.piece_ids.iter().any(|other| &TETRAS[*other] == image)
+            }) && self
+                .required_pieces
+                .iter()
+                .all(|tetra| self.required_pieces.contains(tetra.transformed(transform)))
+                && self.min_piece_counts.iter().all(|(tetra, count)| {
+                    self.min_piece_counts.get(tetra.transformed(transform)) == Some(count)
+                })
+        };
+        let maps_field = |transform: Transform| {
+            transform.apply_to_size(size) == size
+                && (0..size.rows)
+                    .flat_map(|row| (0..size.cols).map(move |col| Pos::new(row, col)))
+                    .all(|pos| {
+                        let image = transform.apply_to_pos(pos, size);
+                        self.grid[image.row][image.col] == self.grid[pos.row][pos.col]
+                    })
+        };
+
+        Transform::ALL
+            .into_iter()
+            .filter(|transform| *transform != Transform::Identity)
+            // gravity pulls tetras down, which only the reflection across the vertical axis keeps
+            .filter(|transform| !self.gravity || *transform == Transform::FlipHorizontal)
+            .filter(|transform| maps_field(*transform) && maps_pieces(*transform))
+            .collect()
+    }
+
+    fn estimate(&self) -> SearchEstimate {
+        let mut candidates = 0;
+        let mut branching = 0;
+        for id in 0..TETRAS.len() {
+            let fits = self.iter_fits_for(id).count();
+            candidates += fits;
+            if fits > 0 {
+                branching += 1;
+            }
+        }
+
+        let depth = self.how_many_free / 4;
+
+        SearchEstimate {
+            free_cells: self.how_many_free,
+            divisible: self.how_many_free.is_multiple_of(4),
+            candidates,
+            branching,
+            depth,
+            tree_size: (branching as f64).powi(depth as i32),
+        }
+    }
+
+    /// Walks the search tree depth-first, keeping the frames on the heap rather than on the call
+    /// stack, so that the search could be interrupted and continued at any step.
+    fn run(&mut self) -> ControlFlow<RunOutcome> {
+        if self.frames.is_empty() {
+            self.enter_frame();
+        }
+
+        while let Some(frame) = self.frames.last_mut() {
+            if self
+                .cancel
+                .is_some_and(|cancel| cancel.load(Ordering::Relaxed))
+            {
+                return ControlFlow::Break(RunOutcome::Cancelled);
+            }
+
+            let cells_per_id = if self.most_constrained_first {
+                TETRA_CELLS
+            } else {
+                1
+            };
+            if let Some(id) = frame.next_id(cells_per_id, &mut self.random_tetras, &self.piece_ids)
+            {
+                let fit = if self.most_constrained_first {
+                    let focus = frame.focus;
+                    focus.and_then(|focus| self.find_fit_covering(focus, id))
+                } else {
+                    self.find_any_fit_for(id)
+                };
+                if let Some(tetra_in_boundaries) = fit {
+                    if self
+                        .max_recursions
+                        .is_some_and(|max| self.recursions >= max)
+                        || self
+                            .deadline
+                            .is_some_and(|deadline| Instant::now() >= deadline)
+                    {
+                        self.stats.search_truncated();
+                        return ControlFlow::Break(RunOutcome::Truncated);
+                    }
+                    self.frames.last_mut().unwrap().was_any_fit = true;
+                    self.fill_and_push(tetra_in_boundaries);
+                    self.enter_frame();
+                }
+                continue;
+            }
+
+            let frame = self.frames.pop().unwrap();
+            self.accept_leaf(&frame)?;
+            // every frame except the root one is entered after a placement
+            if !self.frames.is_empty() {
+                self.pop_and_clear();
+            }
+        }
+
+        ControlFlow::Continue(())
+    }
+
+    fn enter_frame(&mut self) {
+        self.stats.recursions_inc();
+        self.recursions += 1;
+
+        // nothing to try, so that the frame becomes a leaf
+        let is_at_max_depth = self
+            .max_depth
+            .is_some_and(|depth| self.stack.len() >= depth);
+        let (mut tetras, mut draws) = (Vec::new(), 0);
+        if !self.is_at_piece_limit() && !is_at_max_depth {
+            match self.mode {
+                SearchMode::Random => draws = self.piece_ids.len(),
+                SearchMode::Exhaustive => tetras = self.piece_ids.clone(),
+            }
+        }
+        let mut focus = None;
+        if self.most_constrained_first && (!tetras.is_empty() || draws > 0) {
+            let breaks_symmetry = self.frames.is_empty() && !self.root_symmetries.is_empty();
+            focus = if breaks_symmetry {
+                self.most_symmetric_cell()
+            } else {
+                self.most_constrained_cell()
+            };
+            tetras = tetras
+                .into_iter()
+                .flat_map(|id| (0..TETRA_CELLS).map(move |cell| id * TETRA_CELLS + cell))
+                .collect();
+            if let (true, Some(focus)) = (breaks_symmetry, focus) {
+                tetras.retain(|id| self.is_canonical_covering(focus, *id));
+            }
+        }
+        self.frames.push(Frame {
+            tetras,
+            next: 0,
+            draws,
+            drawn: None,
+            was_any_fit: false,
+            focus,
+        });
+
+        if let Some(interval) = self.stats.checkpoint_interval() {
+            if self.recursions.is_multiple_of(interval.get()) {
+                let checkpoint = self.checkpoint();
+                self.stats.checkpoint(&checkpoint);
+            }
+        }
+    }
+
+    /// Records the current placement if no tetra fits anymore and it covers enough cells
+    fn accept_leaf(&mut self, frame: &Frame) -> ControlFlow<RunOutcome> {
+        if frame.was_any_fit {
+            return ControlFlow::Continue(());
+        }
+        if !self.has_required_pieces() || (self.enclose_blocked && !self.is_blocked_enclosed()) {
+            self.stats.leaf_reached(false);
+            return ControlFlow::Continue(());
+        }
+
+        let count_matches = match self.piece_count {
+            Some(PieceCount::Exact(count)) => self.stack.len() == count,
+            _ => true,
+        };
+        // tetras are tried unless a limit is reached, so none of them fit
+        let is_maximal = frame.len(1) > 0;
+        let covers_enough = (self.record_all_maximal && is_maximal)
+            || (count_matches
+                && if self.complete {
+                    self.how_many_free == 0
+                } else {
+                    self.is_at_piece_limit() || self.how_many_free < self.acceptance_threshold
+                });
+        self.stats.leaf_reached(covers_enough);
+        if !covers_enough {
+            let is_best = self
+                .best_partial
+                .as_ref()
+                .is_none_or(|best| self.how_many_free < best.free);
+            if self.no_solution == NoSolutionPolicy::BestPartial && is_best {
+                self.best_partial = Some(self.current_result());
+            }
+        } else {
+            let result = self.current_result();
+            let flow = match self.sink.as_deref_mut() {
+                Some(sink) => {
+                    self.stats.results_inc();
+                    sink.accept(result)
+                }
+                None => {
+                    let kept = self.memory.results.len();
+                    let dedup_start = Instant::now();
+                    let flow = self.memory.accept(result);
+                    self.dedup_elapsed += dedup_start.elapsed();
+                    if self.memory.results.len() > kept {
+                        self.stats.results_inc();
+                        if let Some(threshold) = self.stats.spill_threshold() {
+                            if self.memory.results.len() > threshold.get() {
+                                self.spill_results();
+                            }
+                        }
+                    }
+                    flow
+                }
+            };
+            if flow.is_break() {
+                return ControlFlow::Break(RunOutcome::LimitReached);
+            }
+        }
+
+        ControlFlow::Continue(())
+    }
+
+    fn current_result(&self) -> PlacementResult {
+        PlacementResult {
+            placement: self.stack.iter().cloned().collect(),
+            free: self.how_many_free,
+            order: self.placement_order.then(|| self.stack.clone()),
+        }
+    }
+
+    fn is_at_piece_limit(&self) -> bool {
+        self.piece_count
+            .is_some_and(|count| self.stack.len() >= count.limit())
+    }
+
+    /// Presence of a tetra can't be checked before the placement is complete, so it is checked
+    /// only for leaves, together with [`Configuration::min_piece_counts`]
+    fn has_required_pieces(&self) -> bool {
+        let count = |tetra: &Tetra| {
+            self.stack
+                .iter()
+                .filter(|placed| placed.tetra == tetra)
+                .count()
+        };
+        self.required_pieces
+            .iter()
+            .all(|required| self.stack.iter().any(|placed| placed.tetra == *required))
+            && self
+                .min_piece_counts
+                .iter()
+                .all(|(tetra, min)| count(tetra) >= *min)
+    }
+
+    /// Flood fills from unavailable cells through unavailable and empty ones, looking for the
+    /// field border. Cells next to outside ones are on the border too.
+    fn is_blocked_enclosed(&self) -> bool {
+        let size = self.grid.size_of();
+        let mut visited = HashSet::new();
+        let mut queue: Vec<Pos> = (0..size.rows)
+            .flat_map(|row| (0..size.cols).map(move |col| Pos::new(row, col)))
+            .filter(|pos| matches!(self.grid.pos(pos), Cell::Unavailable))
+            .collect();
+
+        while let Some(pos) = queue.pop() {
+            if !visited.insert(pos) {
+                continue;
+            }
+
+            let neighbours = [
+                pos.row.checked_sub(1).map(|row| Pos::new(row, pos.col)),
+                Some(Pos::new(pos.row + 1, pos.col)),
+                pos.checked_sub_col(1),
+                Some(Pos::new(pos.row, pos.col + 1)),
+            ];
+            for neighbour in neighbours {
+                match neighbour.filter(|x| x.in_bounds(&size)) {
+                    None => return false,
+                    Some(next) => match self.grid.pos(&next) {
+                        Cell::Outside => return false,
+                        Cell::Empty | Cell::Optional | Cell::Unavailable => queue.push(next),
+                        Cell::Occupied => {}
+                    },
+                }
+            }
+        }
+
+        true
+    }
+
+    /// Moves the results found so far out of memory, into [`CollectStats::spill`]
+    fn spill_results(&mut self) {
+        let results = std::mem::take(&mut self.memory.results);
+        self.memory.spilled.extend(results.iter().map(fingerprint));
+        self.stats.spill(results);
+    }
+
+    fn checkpoint(&self) -> Checkpoint {
+        let placed = |x: &PlacedBoundariesChecked| Placed::from(x.clone());
+
+        Checkpoint {
+            frames: self.frames.clone(),
+            stack: self.stack[self.prefilled_count..]
+                .iter()
+                .map(placed)
+                .collect(),
+            results: self
+                .memory
+                .results
+                .iter()
+                .map(|result| CheckpointResult {
+                    placement: result.placement.iter().map(placed).collect(),
+                    free: result.free,
+                })
+                .collect(),
+        }
+    }
+
+    fn restore(&mut self, checkpoint: &Checkpoint) {
+        let size = self.grid.size_of();
+        let checked = |placed: &Placed| {
+            PlacedBoundariesChecked::in_boundaries(placed.clone(), size)
+                .expect("Checkpoint is validated in `Configuration::with_resume`")
+        };
+
+        for placed in &checkpoint.stack {
+            self.fill_and_push(checked(placed));
+        }
+        self.frames = checkpoint.frames.clone();
+        self.memory.results = checkpoint
+            .results
+            .iter()
+            .map(|result| PlacementResult {
+                placement: result.placement.iter().map(checked).collect(),
+                free: result.free,
+                order: None,
+            })
+            .collect();
+    }
+
+    fn fill_and_push(&mut self, tetra: PlacedBoundariesChecked) {
+        self.occupancy.fill(&RowMask::new(tetra.tetra), &tetra);
+        for i in tetra.iter_relative_to_place() {
+            if matches!(self.grid[i.row][i.col], Cell::Empty) {
+                self.how_many_free -= 1;
+            }
+            self.grid[i.row][i.col] = Cell::Occupied;
+        }
+        self.stats.tetra_placed(&tetra);
+        self.stack.push(tetra);
+    }
+
+    fn pop_and_clear(&mut self) {
+        let placed_tetra = self.stack.pop().unwrap();
+        self.occupancy
+            .clear(&RowMask::new(placed_tetra.tetra), &placed_tetra);
+        for i in placed_tetra.iter_relative_to_place() {
+            if self.optional.contains(&i) {
+                self.grid[i.row][i.col] = Cell::Optional;
+            } else {
+                self.grid[i.row][i.col] = Cell::Empty;
+                self.how_many_free += 1;
+            }
+        }
+        self.stats.tetra_removed();
+    }
+
+    /// The place of the tetra with the given id chosen by the [`FitPolicy`] among the ones the
+    /// [`Heuristic`] prefers
+    fn find_any_fit_for(&mut self, id: usize) -> Option<PlacedBoundariesChecked> {
+        let region = self.preferred_region_for(id);
+        match self.fit_policy {
+            FitPolicy::First => self.iter_preferred_fits_for(id, region).next(),
+            FitPolicy::Last => self.iter_preferred_fits_for(id, region).last(),
+            FitPolicy::MostConstrainedCell => {
+                let options = self.placement_options_grid();
+                self.iter_preferred_fits_for(id, region)
+                    .min_by_key(|placed| {
+                        placed
+                            .iter_relative_to_place()
+                            .map(|pos| options[pos.row][pos.col])
+                            .min()
+                    })
+            }
+            FitPolicy::Random(_) => {
+                let count = self.iter_preferred_fits_for(id, region).count();
+                if count == 0 {
+                    return None;
+                }
+                let pick = self.random_fits.gen_range(0..count);
+                self.iter_preferred_fits_for(id, region).nth(pick)
+            }
+        }
+        .cloned()
+    }
+
+    /// Size of the largest area of empty cells the tetra fits into, for
+    /// [`Heuristic::FillLargest`]
+    fn preferred_region_for(&mut self, id: usize) -> Option<usize> {
+        if self.heuristic != Heuristic::FillLargest {
+            return None;
+        }
+        let stale = self
+            .regions
+            .as_ref()
+            .is_none_or(|(counted_at, _)| self.recursions >= counted_at + REGIONS_REFRESH);
+        if stale {
+            self.regions = Some((self.recursions, empty_region_sizes(&self.grid)));
+        }
+        self.iter_fits_for(id)
+            .map(|placed| self.region_of(placed))
+            .max()
+    }
+
+    /// Size of the area of empty cells the placement is in, as of the last count
+    fn region_of(&self, placed: &PlacedBoundariesChecked) -> usize {
+        let Some((_, regions)) = &self.regions else {
+            return 0;
+        };
+        placed
+            .iter_relative_to_place()
+            .map(|pos| regions[pos.row][pos.col])
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// [`Self::iter_fits_for`], only in the area of the given size if it is given
+    fn iter_preferred_fits_for(
+        &self,
+        id: usize,
+        region: Option<usize>,
+    ) -> impl Iterator<Item = &PlacedBoundariesChecked> + use<'_, 'a, S> {
+        self.iter_fits_for(id)
+            .filter(move |placed| region.is_none_or(|size| self.region_of(placed) == size))
+    }
+
+    /// The placement putting the given cell of the tetra onto `focus`, see [`Frame::tetras`]
+    fn find_fit_covering(&self, focus: Pos, id: usize) -> Option<PlacedBoundariesChecked> {
+        let (id, cell) = (id / TETRA_CELLS, id % TETRA_CELLS);
+        self.iter_fits_for(id)
+            .find(|placed| placed.iter_relative_to_place().nth(cell) == Some(focus))
+            .cloned()
+    }
+
+    /// How many placements of any tetra into the current grid cover the cell
+    fn placement_options(&self, pos: Pos) -> usize {
+        (0..TETRAS.len())
+            .flat_map(|id| self.iter_fits_for(id))
+            .filter(|placed| placed.iter_relative_to_place().any(|cell| cell == pos))
+            .count()
+    }
+
+    /// The empty cell with the fewest [`Self::placement_options`], but at least one, the first
+    /// one in row order among equal ones
+    fn most_constrained_cell(&self) -> Option<Pos> {
+        let options = self.placement_options_grid();
+        (0..self.grid.rows())
+            .flat_map(|row| (0..self.grid.cols()).map(move |col| Pos::new(row, col)))
+            .filter(|pos| options[pos.row][pos.col] > 0)
+            .min_by_key(|pos| options[pos.row][pos.col])
+    }
+
+    /// Like [`Self::most_constrained_cell`], but among the cells kept in place by the most of
+    /// [`Self::root_symmetries`]
+    fn most_symmetric_cell(&self) -> Option<Pos> {
+        let size = self.grid.size_of();
+        let options = self.placement_options_grid();
+        let kept_by = |pos: Pos| {
+            self.root_symmetries
+                .iter()
+                .filter(|transform| transform.apply_to_pos(pos, size) == pos)
+                .count()
+        };
+        (0..self.grid.rows())
+            .flat_map(|row| (0..self.grid.cols()).map(move |col| Pos::new(row, col)))
+            .filter(|pos| options[pos.row][pos.col] > 0)
+            .min_by_key(|pos| (Reverse(kept_by(*pos)), options[pos.row][pos.col]))
+    }
+
+    /// Whether the placement of [`Self::find_fit_covering`] has the smallest cells of its images
+    /// under the [`Self::root_symmetries`] keeping `focus` in place, which cover `focus` too, so
+    /// that only one of them is tried
+    fn is_canonical_covering(&self, focus: Pos, id: usize) -> bool {
+        let Some(placed) = self.find_fit_covering(focus, id) else {
+            return true;
+        };
+        let size = self.grid.size_of();
+        let cells = |transform: Transform| {
+            let mut cells: Vec<_> = placed
+                .iter_relative_to_place()
+                .map(|pos| transform.apply_to_pos(pos, size))
+                .collect();
+            cells.sort();
+            cells
+        };
+        let own = cells(Transform::Identity);
+        self.root_symmetries
+            .iter()
+            .filter(|transform| transform.apply_to_pos(focus, size) == focus)
+            .all(|transform| own <= cells(*transform))
+    }
+
+    /// [`Self::placement_options`] of every cell at once
+    fn placement_options_grid(&self) -> Grid<usize> {
+        let mut options = Grid::init(self.grid.rows(), self.grid.cols(), 0usize);
+        for placed in (0..TETRAS.len()).flat_map(|id| self.iter_fits_for(id)) {
+            for pos in placed.iter_relative_to_place() {
+                options[pos.row][pos.col] += 1;
+            }
+        }
+        options
+    }
+
+    /// All placements of the tetra with the given id into the current grid, in lookup order.
+    /// Borrowed from the candidates, so that only the chosen one is copied
+    fn iter_fits_for(
+        &self,
+        id: usize,
+    ) -> impl Iterator<Item = &PlacedBoundariesChecked> + use<'_, 'a, S> {
+        let mask = &self.candidates.masks[id];
+        self.candidates.per_tetra[id]
+            .iter()
+            .filter(move |candidate| self.fits(candidate, mask))
+    }
+
+    /// Whether the candidate covers only empty cells and is allowed by the constraints. The
+    /// bounds are checked once, when the candidates are collected. The mask is the one of the
+    /// candidate's tetra
+    fn fits(&self, candidate: &PlacedBoundariesChecked, mask: &RowMask) -> bool {
+        self.occupancy.is_free(mask, candidate)
+            && (!self.gravity || self.is_supported(candidate))
+            && self
+                .constraints
+                .iter()
+                .all(|constraint| constraint.allows(&self.grid, candidate))
+    }
+
+    /// Whether the candidate rests on something, see [`Configuration::with_gravity`]
+    fn is_supported(&self, candidate: &PlacedBoundariesChecked) -> bool {
+        candidate.iter_relative_to_place().any(|pos| {
+            let below = Pos::new(pos.row + 1, pos.col);
+            if candidate.iter_relative_to_place().any(|cell| cell == below) {
+                return false;
+            }
+            below.row == self.grid.rows()
+                || matches!(
+                    self.grid.pos(&below),
+                    Cell::Unavailable | Cell::Occupied | Cell::Outside
+                )
+        })
+    }
+}
+
+/// Cells of a tetra as bits of the rows of its bounding box, the lowest bit for the left column
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct RowMask([u8; TETRA_CELLS]);
+
+impl RowMask {
+    fn new(tetra: &Tetra) -> Self {
+        let mut rows = [0; TETRA_CELLS];
+        for pos in tetra.iter() {
+            rows[pos.row] |= 1 << pos.col;
+        }
+        Self(rows)
+    }
+}
+
+/// Bitset of the cells which are not free, a row after another. Each row starts a new word, so
+/// that a row of a tetra is checked at once, with a word more only if it crosses words
+#[derive(Clone, Debug)]
+struct Occupancy {
+    words: Vec<u64>,
+    words_per_row: usize,
+}
+
+impl Occupancy {
+    const WORD: usize = u64::BITS as usize;
+
+    fn new(size: Size) -> Self {
+        let words_per_row = size.cols.div_ceil(Self::WORD);
+        Self {
+            words: vec![0; size.rows * words_per_row],
+            words_per_row,
+        }
+    }
+
+    fn set(&mut self, pos: Pos) {
+        let word = pos.row * self.words_per_row + pos.col / Self::WORD;
+        self.words[word] |= 1 << (pos.col % Self::WORD);
+    }
+
+    /// Rows of the mask put at the tetra's place: the index of the first word and the bits of
+    /// this word and the next one
+    fn rows<'a>(
+        &self,
+        mask: &'a RowMask,
+        placed: &PlacedBoundariesChecked,
+    ) -> impl Iterator<Item = (usize, u128)> + 'a {
+        let left = placed.position.col - placed.tetra.col_shift();
+        let first_row = placed.position.row;
+        let words_per_row = self.words_per_row;
+        mask.0
+            .iter()
+            .enumerate()
+            .filter(|(_, bits)| **bits != 0)
+            .map(move |(row, bits)| {
+                let word = (first_row + row) * words_per_row + left / Self::WORD;
+                (word, u128::from(*bits) << (left % Self::WORD))
+            })
+    }
+
+    fn is_free(&self, mask: &RowMask, placed: &PlacedBoundariesChecked) -> bool {
+        self.rows(mask, placed).all(|(word, bits)| {
+            let (low, high) = (bits as u64, (bits >> Self::WORD) as u64);
+            self.words[word] & low == 0 && (high == 0 || self.words[word + 1] & high == 0)
+        })
+    }
+
+    fn fill(&mut self, mask: &RowMask, placed: &PlacedBoundariesChecked) {
+        for (word, bits) in self.rows(mask, placed) {
+            let (low, high) = (bits as u64, (bits >> Self::WORD) as u64);
+            self.words[word] |= low;
+            if high != 0 {
+                self.words[word + 1] |= high;
+            }
+        }
+    }
+
+    /// The tetra should be filled in
+    fn clear(&mut self, mask: &RowMask, placed: &PlacedBoundariesChecked) {
+        for (word, bits) in self.rows(mask, placed) {
+            let (low, high) = (bits as u64, (bits >> Self::WORD) as u64);
+            self.words[word] ^= low;
+            if high != 0 {
+                self.words[word + 1] ^= high;
+            }
+        }
+    }
+}
+
+/// Placements of every tetra which lay within the field and cover only playable cells, i.e.
+/// which could fit before the search starts. In lookup order.
+#[derive(Clone, Debug, PartialEq)]
+struct Candidates {
+    /// Position of each cell of the field in the scan order
+    ranks: Grid<usize>,
+    /// Playable cells, in scan order
+    positions: Vec<Pos>,
+    /// Indexed by tetra ids
+    per_tetra: Vec<Vec<PlacedBoundariesChecked>>,
+    /// Masks of the tetras for the fit check, indexed by tetra ids
+    masks: Vec<RowMask>,
+}
+
+impl Candidates {
+    fn new(conf: &Configuration) -> Self {
+        let cells = conf.scan_order.cells(conf.size, &mut seeded_rng(conf.seed));
+        let mut ranks = Grid::new(conf.size.rows, conf.size.cols);
+        for (rank, pos) in cells.iter().enumerate() {
+            ranks[pos.row][pos.col] = rank;
+        }
+        let positions: Vec<_> = cells
+            .into_iter()
+            .filter(|pos| conf.cell_kind(pos) == CellKind::Playable)
+            .collect();
+        // tetras left out of the search have no candidates, so that they never fit
+        let per_tetra = TETRAS
+            .iter()
+            .map(|tetra| {
+                positions
+                    .iter()
+                    .filter(|_| conf.pieces.contains(&tetra))
+                    .filter_map(|pos| Self::candidate(conf, tetra, *pos))
+                    .collect()
+            })
+            .collect();
+
+        Self {
+            ranks,
+            positions,
+            per_tetra,
+            masks: TETRAS.iter().map(RowMask::new).collect(),
+        }
+    }
+
+    fn candidate(
+        conf: &Configuration,
+        tetra: &'static Tetra,
+        pos: Pos,
+    ) -> Option<PlacedBoundariesChecked> {
+        PlacedBoundariesChecked::in_boundaries(Placed::new(tetra, pos), conf.size).filter(
+            |placed| {
+                placed
+                    .iter_relative_to_place()
+                    .all(|pos| conf.cell_kind(&pos) == CellKind::Playable)
+            },
+        )
+    }
+
+    /// Updates only candidates covering the cell, after its kind changed in the configuration
+    fn update(&mut self, conf: &Configuration, cell: Pos) {
+        let playable = conf.cell_kind(&cell) == CellKind::Playable;
+        let rank = |pos: &Pos| *self.ranks.pos(pos);
+        match (
+            self.positions.binary_search_by_key(&rank(&cell), rank),
+            playable,
+        ) {
+            (Ok(idx), false) => {
+                self.positions.remove(idx);
+            }
+            (Err(idx), true) => self.positions.insert(idx, cell),
+            _ => {}
+        }
+
+        let covers_cell = |placed: &PlacedBoundariesChecked| {
+            placed.iter_relative_to_place().any(|pos| pos == cell)
+        };
+        // tetras are at most 4x4, so only positions close to the cell might cover it
+        let close_positions: Vec<_> = self
+            .positions
+            .iter()
+            .filter(|pos| {
+                pos.row <= cell.row && cell.row - pos.row < 4 && pos.col.abs_diff(cell.col) < 4
+            })
+            .collect();
+
+        for (tetra, candidates) in TETRAS.iter().zip(&mut self.per_tetra) {
+            candidates.retain(|placed| !covers_cell(placed));
+            if !playable || !conf.pieces.contains(&tetra) {
+                continue;
+            }
+            for pos in &close_positions {
+                if let Some(placed) =
+                    Self::candidate(conf, tetra, **pos).filter(|placed| covers_cell(placed))
+                {
+                    let idx = candidates.partition_point(|x| {
+                        self.ranks.pos(&x.position) < self.ranks.pos(&placed.position)
+                    });
+                    candidates.insert(idx, placed);
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Error, PartialEq, Diagnostic)]
+pub enum ToggleError {
+    #[error("Cell {0} is not a part of the field")]
+    OutOfField(Pos),
+    #[error("Cell {0} is occupied by a prefilled tetra")]
+    Prefilled(Pos),
+}
+
+/// Keeps what is precomputed for the configuration between searches, so that the field could be
+/// edited and searched again without starting from scratch
+pub struct Solver {
+    conf: Configuration,
+    candidates: Candidates,
+}
+
+impl Solver {
+    pub fn new(conf: Configuration) -> Self {
+        let candidates = Candidates::new(&conf);
+        Self { conf, candidates }
+    }
+
+    pub fn configuration(&self) -> &Configuration {
+        &self.conf
+    }
+
+    /// Makes an empty cell unavailable or vice versa.
+    ///
+    /// Forgets the checkpoint to resume from, if any, since it doesn't fit the new field.
+    pub fn toggle_cell(&mut self, pos: Pos) -> Result<(), ToggleError> {
+        if !pos.in_bounds(&self.conf.size) || self.conf.outside.contains(&pos) {
+            return Err(ToggleError::OutOfField(pos));
+        }
+        let is_prefilled = self
+            .conf
+            .prefilled
+            .iter()
+            .any(|placed| placed.iter_relative_to_place().any(|x| x == pos));
+        if is_prefilled {
+            return Err(ToggleError::Prefilled(pos));
+        }
+
+        if !self.conf.unavailable.remove(&pos) {
+            self.conf.unavailable.insert(pos);
+        }
+        self.conf.resume = None;
+        self.candidates.update(&self.conf, pos);
+        Ok(())
+    }
+
+    pub fn run<S>(&self, stats: &'_ mut S) -> BTreeSet<PlacementResult>
+    where
+        S: CollectStats,
+    {
+        RecursionState::find_placements(&self.conf, Some(&self.candidates), None, None, stats)
+            .0
+            .unwrap_or_default()
+    }
+}
+
+/// A found placement.
+///
+/// Compared and hashed by the set of placed tetras, which is canonical: it doesn't depend on the
+/// order tetras were placed in, and determines the occupied cells.
+#[derive(Debug)]
+pub struct PlacementResult {
+    pub placement: Placement,
+    pub free: usize,
+    /// The same tetras in the order they were placed, prefilled ones first. Recorded only with
+    /// [`Configuration::with_placement_order`], and not restored from checkpoints. If the same
+    /// placement is reached several times, the first order is kept
+    pub order: Option<Vec<PlacedBoundariesChecked>>,
+}
+
+impl PartialEq for PlacementResult {
+    fn eq(&self, other: &Self) -> bool {
+        (&self.placement, self.free) == (&other.placement, other.free)
+    }
+}
+
+impl Eq for PlacementResult {}
+
+impl PartialOrd for PlacementResult {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for PlacementResult {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        (&self.placement, self.free).cmp(&(&other.placement, other.free))
+    }
+}
+
+impl std::hash::Hash for PlacementResult {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.placement.hash(state);
+        self.free.hash(state);
+    }
+}
+
+impl PlacementResult {
+    /// Stable identifier of the placement, e.g. to cache results or to find duplicates across
+    /// runs. Unlike the hash, doesn't change between runs and builds as long as the pieces are
+    /// the same, see [`crate::tetra::pieces_fingerprint`].
+    pub fn signature(&self) -> String {
+        let mut tuples: Vec<_> = self
+            .placement
+            .iter()
+            .map(|placed| {
+                let id = TETRAS.iter().position(|x| x == placed.tetra).unwrap();
+                (id, placed.position.row, placed.position.col)
+            })
+            .collect();
+        tuples.sort_unstable();
+
+        let hash = stable_hash(tuples.into_iter().flat_map(|(id, row, col)| [id, row, col]));
+        format!("{hash:016x}")
+    }
+
+    /// Which tetra occupies each cell of the field, as its index in [`Self::placement`]. Free,
+    /// unavailable and outside cells are `None`.
+    ///
+    /// Index rather than the tetra id tells apart adjacent tetras of the same kind, the id is
+    /// still at hand through the placement.
+    pub fn to_grid(&self, conf: &Configuration) -> Grid<Option<usize>> {
+        let mut grid = Grid::new(conf.size.rows, conf.size.cols);
+        for (idx, placed) in self.placement.iter().enumerate() {
+            for Pos { row, col } in placed.iter_relative_to_place() {
+                grid[row][col] = Some(idx);
+            }
+        }
+        grid
+    }
+
+    /// Tetras in the order a row by row fill covers them, i.e. by their top left cell.
+    ///
+    /// The placement is a set, so the order the search put tetras in is kept only in
+    /// [`PlacementResult::order`], if requested. This one is the same for every run and reads
+    /// naturally, from top to bottom.
+    pub fn fill_order(&self) -> Vec<&PlacedBoundariesChecked> {
+        let mut tetras: Vec<_> = self.placement.iter().collect();
+        tetras.sort_by_key(|placed| placed.iter_relative_to_place().min());
+        tetras
+    }
+
+    /// Tells step by step how the search filled the field: a line for each tetra it placed,
+    /// leaving the prefilled ones out, and a summary. Tetras go in [`PlacementResult::order`] if
+    /// it is recorded, or else in [`PlacementResult::fill_order`]
+    pub fn narrate(&self, conf: &Configuration) -> Vec<String> {
+        let placed: Vec<_> = match &self.order {
+            Some(order) => order.iter().collect(),
+            None => self.fill_order(),
+        };
+        let placed: Vec<_> = placed
+            .into_iter()
+            .filter(|placed| !conf.is_prefilled(placed))
+            .collect();
+
+        // optional cells are left out, as they are from `free`
+        let required_of = |placed: &PlacedBoundariesChecked| {
+            placed
+                .iter_relative_to_place()
+                .filter(|pos| !conf.optional.contains(pos))
+                .count()
+        };
+        let cells = conf.required_cells() - conf.prefilled.iter().map(required_of).sum::<usize>();
+        let mut remain = cells;
+        let mut lines = Vec::with_capacity(placed.len() + 1);
+        for placed in &placed {
+            remain -= required_of(placed);
+            lines.push(format!(
+                "Placed {:?}-piece at {}, {remain} cells remain",
+                PieceKind::of(placed.tetra),
+                placed.position
+            ));
+        }
+        lines.push(match self.free {
+            0 => format!("Covered all {cells} cells with {} pieces", placed.len()),
+            free => format!(
+                "Covered {} of {cells} cells with {} pieces, {free} left free",
+                cells - free,
+                placed.len()
+            ),
+        });
+        lines
+    }
+
+    /// Share of the required cells covered by tetras, from 0 to 100, see
+    /// [`Configuration::required_cells`]. Optional cells count neither way. A field with no
+    /// required cells counts as filled
+    pub fn fill_percent(&self, conf: &Configuration) -> f64 {
+        let required = conf.required_cells();
+        if required == 0 {
+            return 100.0;
+        }
+        (required - self.free) as f64 * 100.0 / required as f64
+    }
+}
+
+/// Without [`Configuration::complete`], a placement is accepted if it leaves fewer free cells than
+/// this, before [`Configuration::free_cells_slack`] is added. `min_free_cells` are the ones no
+/// placement can cover, `how_many_free % 4`. The threshold is the square root of the rest, so
+/// that bigger fields get more room
+pub fn acceptance_threshold(how_many_free: usize, min_free_cells: usize) -> usize {
+    ((how_many_free - min_free_cells) as f64).powf(0.5).floor() as usize
+}
+
+/// Seeded from entropy unless the seed is given
+fn seeded_rng(seed: Option<u64>) -> StdRng {
+    seed.map_or_else(StdRng::from_entropy, StdRng::seed_from_u64)
+}
+
+/// Size of the connected area of empty and optional cells each cell is in, 0 for the other cells
+fn empty_region_sizes(grid: &Grid<Cell>) -> Grid<usize> {
+    let (rows, cols) = (grid.rows(), grid.cols());
+    let is_empty = |pos: Pos| matches!(grid[pos.row][pos.col], Cell::Empty | Cell::Optional);
+    let mut sizes = Grid::init(rows, cols, 0);
+    let mut visited = Grid::init(rows, cols, false);
+    for start in (0..rows).flat_map(|row| (0..cols).map(move |col| Pos::new(row, col))) {
+        if visited[start.row][start.col] || !is_empty(start) {
+            continue;
+        }
+
+        visited[start.row][start.col] = true;
+        let mut region = vec![start];
+        let mut queue = vec![start];
+        while let Some(pos) = queue.pop() {
+            let neighbours = [
+                pos.row.checked_sub(1).map(|row| Pos::new(row, pos.col)),
+                (pos.row + 1 < rows).then(|| Pos::new(pos.row + 1, pos.col)),
+                pos.col.checked_sub(1).map(|col| Pos::new(pos.row, col)),
+                (pos.col + 1 < cols).then(|| Pos::new(pos.row, pos.col + 1)),
+            ];
+            for next in neighbours.into_iter().flatten() {
+                if !visited[next.row][next.col] && is_empty(next) {
+                    visited[next.row][next.col] = true;
+                    region.push(next);
+                    queue.push(next);
+                }
+            }
+        }
+        for pos in &region {
+            sizes[pos.row][pos.col] = region.len();
+        }
+    }
+    sizes
+}
+
+/// How many placements cover each cell of the field. Cells covered by few of them, or by none,
+/// are the hard ones
+pub fn coverage_heatmap<'a>(
+    results: impl IntoIterator<Item = &'a PlacementResult>,
+    conf: &Configuration,
+) -> Grid<usize> {
+    let mut heatmap = Grid::init(conf.size.rows, conf.size.cols, 0);
+    for result in results {
+        add_coverage(&mut heatmap, result);
+    }
+    heatmap
+}
+
+/// Accounts one more placement in the [`coverage_heatmap`]
+pub fn add_coverage(heatmap: &mut Grid<usize>, result: &PlacementResult) {
+    // tetras of a placement don't overlap, so each cell is counted once per placement
+    for placed in &result.placement {
+        for Pos { row, col } in placed.iter_relative_to_place() {
+            heatmap[row][col] += 1;
+        }
+    }
+}
+
+/// How many times each tetra, by id, appears in the placements
+pub fn aggregate_piece_usage<'a>(
+    results: impl IntoIterator<Item = &'a PlacementResult>,
+) -> BTreeMap<usize, usize> {
+    let mut usage = BTreeMap::new();
+    for placed in results.into_iter().flat_map(|result| &result.placement) {
+        let id = TETRAS.iter().position(|x| x == placed.tetra).unwrap();
+        *usage.entry(id).or_default() += 1;
+    }
+    usage
+}
+
+/// How many placements have a tetra of each kind in the top left playable cell, i.e. the one
+/// the search starts from with the default scan order. `None` counts placements which leave
+/// that cell free
+pub fn count_by_leading_kind<'a>(
+    results: impl IntoIterator<Item = &'a PlacementResult>,
+    conf: &Configuration,
+) -> BTreeMap<Option<PieceKind>, usize> {
+    let leading_cell = (0..conf.size.rows)
+        .flat_map(|row| (0..conf.size.cols).map(move |col| Pos::new(row, col)))
+        .find(|pos| conf.cell_kind(pos) == CellKind::Playable);
+
+    let mut counts = BTreeMap::new();
+    for result in results {
+        let kind = leading_cell.and_then(|pos| {
+            result
+                .placement
+                .iter()
+                .find(|placed| placed.iter_relative_to_place().any(|cell| cell == pos))
+                .map(|placed| PieceKind::of(placed.tetra))
+        });
+        *counts.entry(kind).or_default() += 1;
+    }
+    counts
+}
+
+/// Signatures of results found so far, shared by the workers of
+/// [`Configuration::run_parallel`], so that a result found by one worker is skipped by the others
+/// right away rather than deduplicated once all of them finish
+#[derive(Debug, Default)]
+pub struct SharedSignatures {
+    signatures: Mutex<HashSet<String>>,
+}
+
+impl SharedSignatures {
+    /// Whether the result is found for the first time
+    pub fn insert(&self, result: &PlacementResult) -> bool {
+        let signature = result.signature();
+        self.signatures
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .insert(signature)
+    }
+
+    pub fn len(&self) -> usize {
+        self.signatures
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Where the search puts the placements it accepts, see [`Configuration::run_into`]
+pub trait ResultSink: std::fmt::Debug {
+    /// Takes an accepted placement. The same placement may come again, e.g. in
+    /// [`SearchMode::Random`], so skipping it is up to the sink. Breaks to stop the search, e.g.
+    /// once enough results are taken
+    fn accept(&mut self, result: PlacementResult) -> ControlFlow<()>;
+}
+
+/// Keeps distinct results in memory and stops the search once there are as many of them as the
+/// limit. The search uses it unless given another sink
+#[derive(Debug, Default)]
+pub struct MemorySink<'a> {
+    pub results: BTreeSet<PlacementResult>,
+    limit: Option<NonZeroUsize>,
+    /// Fingerprints of results passed into [`CollectStats::spill`], to skip them if found again
+    spilled: HashSet<u64>,
+    /// Results found by other searches running in parallel, to skip them
+    shared: Option<&'a SharedSignatures>,
+}
+
+impl MemorySink<'_> {
+    pub fn new(limit: Option<NonZeroUsize>) -> Self {
+        Self {
+            limit,
+            ..Self::default()
+        }
+    }
+
+    /// Only distinct results count, and with several workers, all of theirs
+    fn found(&self) -> usize {
+        match self.shared {
+            Some(shared) => shared.len(),
+            None => self.results.len() + self.spilled.len(),
+        }
+    }
+}
+
+impl ResultSink for MemorySink<'_> {
+    fn accept(&mut self, result: PlacementResult) -> ControlFlow<()> {
+        let is_spilled = !self.spilled.is_empty() && self.spilled.contains(&fingerprint(&result));
+        let is_new = !is_spilled
+            && !self.results.contains(&result)
+            && self.shared.is_none_or(|shared| shared.insert(&result));
+        if is_new {
+            self.results.insert(result);
+        }
+        match self.limit {
+            Some(limit) if self.found() >= limit.get() => ControlFlow::Break(()),
+            _ => ControlFlow::Continue(()),
+        }
+    }
+}
+
+/// Keeps only the fewest free cells, see [`Configuration::min_free`]
+#[derive(Debug)]
+struct MinFreeSink {
+    min: Option<usize>,
+    /// Stop once it is reached, since no placement could leave fewer
+    floor: usize,
+}
+
+impl ResultSink for MinFreeSink {
+    fn accept(&mut self, result: PlacementResult) -> ControlFlow<()> {
+        let min = self.min.map_or(result.free, |min| min.min(result.free));
+        self.min = Some(min);
+        if min <= self.floor {
+            ControlFlow::Break(())
+        } else {
+            ControlFlow::Continue(())
+        }
+    }
+}
+
+/// Caps how many results several searches find together, e.g. the searches of several fields
+/// read at once, on top of the [`Configuration::results_limit`] of each of them
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ResultsBudget {
+    /// `None` if there is no cap
+    left: Option<usize>,
+}
+
+impl ResultsBudget {
+    pub fn new(total: Option<NonZeroUsize>) -> Self {
+        Self {
+            left: total.map(NonZeroUsize::get),
+        }
+    }
+
+    /// Whether the next searches should be skipped
+    pub fn is_spent(&self) -> bool {
+        self.left == Some(0)
+    }
+
+    /// The limit of the next search: its own one, lowered to what is left. `None` only if
+    /// neither is set
+    pub fn limit(&self, own: Option<NonZeroUsize>) -> Option<NonZeroUsize> {
+        let left = self.left.and_then(NonZeroUsize::new);
+        match (own, left) {
+            (Some(own), Some(left)) => Some(own.min(left)),
+            (own, left) => own.or(left),
+        }
+    }
+
+    /// Accounts the results of a finished search
+    pub fn spend(&mut self, found: usize) {
+        if let Some(left) = &mut self.left {
+            *left = left.saturating_sub(found);
+        }
+    }
+}
+
+pub(crate) fn fingerprint(result: &PlacementResult) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    result.hash(&mut hasher);
+    hasher.finish()
+}
+
+pub trait CollectStats {
+    fn recursions_inc(&mut self);
+
+    fn results_inc(&mut self);
+
+    /// Every how many recursions to pass the search state into [`CollectStats::checkpoint`]
+    fn checkpoint_interval(&self) -> Option<NonZeroUsize> {
+        None
+    }
+
+    fn checkpoint(&mut self, _checkpoint: &Checkpoint) {}
+
+    /// How many results to keep in memory before passing them into [`CollectStats::spill`]
+    fn spill_threshold(&self) -> Option<NonZeroUsize> {
+        None
+    }
+
+    /// Takes the results out of memory, e.g. to write them on disk.
+    ///
+    /// Once anything is spilled, the rest of the results are spilled at the end of the search as
+    /// well, and the search itself returns nothing. Spilled results are not included into
+    /// checkpoints.
+    fn spill(&mut self, _results: BTreeSet<PlacementResult>) {}
+
+    /// Called once the search finishes a phase, with how long it took
+    fn phase_finished(&mut self, _phase: Phase, _elapsed: Duration) {}
+
+    /// Called after a tetra is put into the field, including prefilled ones
+    fn tetra_placed(&mut self, _placed: &PlacedBoundariesChecked) {}
+
+    /// Called after the last placed tetra is taken out of the field
+    fn tetra_removed(&mut self) {}
+
+    /// Called when no more tetras fit into the field, with whether the placement is accepted.
+    /// An accepted placement is not recorded if it is already found.
+    fn leaf_reached(&mut self, _accepted: bool) {}
+
+    /// Called when the search stops early because of [`Configuration::max_recursions`] or
+    /// [`Configuration::time_limit`]
+    fn search_truncated(&mut self) {}
+}
+
+/// Stats collector which ignores everything
+pub struct NoopStats;
+
+impl CollectStats for NoopStats {
+    fn recursions_inc(&mut self) {}
+
+    fn results_inc(&mut self) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug)]
+    struct StatsDummy;
+
+    impl CollectStats for StatsDummy {
+        fn recursions_inc(&mut self) {}
+
+        fn results_inc(&mut self) {}
+    }
+
+    // #[test]
+    mod caching {
+        use super::*;
+
+        fn config_factory() -> Configuration {
+            Configuration::new(Size::new(8, 8), HashSet::new())
+        }
+
+        #[test]
+        fn all_positions_initially() {
+            let mut stats = StatsDummy;
+            let cfg = config_factory();
+            let rec = RecursionState::with_configuration(&cfg, &mut stats);
+
+            assert_eq!(rec.candidates.positions.len(), 8 * 8);
+        }
+    }
+
+    #[derive(Debug)]
+    struct NoFirstColumn;
+
+    impl Constraint for NoFirstColumn {
+        fn allows(&self, _grid: &Grid<Cell>, candidate: &PlacedBoundariesChecked) -> bool {
+            candidate.iter_relative_to_place().all(|pos| pos.col > 0)
+        }
+    }
+
+    #[test]
+    fn custom_constraint_forbids_first_column() {
+        let unavailable = [(0, 0), (1, 0), (2, 0)]
+            .into_iter()
+            .map(Pos::from)
+            .collect();
+        let cfg = Configuration::new(Size::new(4, 5), unavailable).with_constraint(NoFirstColumn);
+
+        let results = cfg.run(&mut StatsDummy);
+
+        assert!(!results.is_empty());
+        for result in results {
+            assert_eq!(result.free, 1);
+            assert!(result
+                .placement
+                .iter()
+                .flat_map(|tetra| tetra.iter_relative_to_place())
+                .all(|pos| pos.col > 0));
+        }
+    }
+
+    #[test]
+    fn every_result_contains_required_pieces() {
+        let (o, i) = (&TETRAS[0], &TETRAS[1]);
+        let cfg = Configuration::new(Size::new(4, 4), HashSet::new())
+            .with_mode(SearchMode::Exhaustive)
+            .with_required_pieces([o, i].into_iter().collect());
+
+        let results = cfg.run(&mut StatsDummy);
+
+        assert!(!results.is_empty());
+        for result in results {
+            assert!(result.placement.iter().any(|placed| placed.tetra == o));
+            assert!(result.placement.iter().any(|placed| placed.tetra == i));
+        }
+    }
+
+    #[test]
+    fn every_result_has_min_piece_counts() {
+        let i = &TETRAS[1];
+        let count = |result: &PlacementResult| {
+            result
+                .placement
+                .iter()
+                .filter(|placed| placed.tetra == i)
+                .count()
+        };
+        let cfg =
+            Configuration::new(Size::new(4, 4), HashSet::new()).with_mode(SearchMode::Exhaustive);
+        assert!(cfg
+            .run(&mut StatsDummy)
+            .iter()
+            .any(|result| count(result) < 2));
+
+        let results = cfg
+            .with_min_piece_counts([(i, 2)].into_iter().collect())
+            .run(&mut StatsDummy);
+
+        assert!(!results.is_empty());
+        assert!(results.iter().all(|result| count(result) >= 2));
+    }
+
+    #[test]
+    fn optional_cells_may_stay_empty_in_complete_mode() {
+        let field = Parser::default()
+            .with_char_optional('?')
+            .parse_without_source_code("----?\n----?")
+            .unwrap();
+        assert_eq!(field.optional.len(), 2);
+        let cfg = Configuration::from_parsed(field)
+            .with_mode(SearchMode::Exhaustive)
+            .with_complete(true);
+
+        let results = cfg.run(&mut StatsDummy);
+
+        assert!(cfg.is_complete_feasible());
+        assert!(results.iter().all(|result| result.free == 0));
+        assert!(results.iter().any(|result| result
+            .placement
+            .iter()
+            .flat_map(|placed| placed.iter_relative_to_place())
+            .all(|pos| pos.col < 4)));
+    }
+
+    #[test]
+    fn places_only_the_given_pieces() {
+        let (o, i) = (&TETRAS[0], &TETRAS[1]);
+        let cfg = || {
+            Configuration::new(Size::new(4, 4), HashSet::new())
+                .with_pieces(vec![o, i])
+                .with_complete(true)
+        };
+
+        let exhaustive = cfg().with_mode(SearchMode::Exhaustive).run(&mut StatsDummy);
+        let random = cfg().run(&mut StatsDummy);
+
+        // only squares, only rows, or two rows and a band of squares at one of three heights
+        assert_eq!(exhaustive.len(), 5);
+        for result in exhaustive.iter().chain(&random) {
+            assert!(result
+                .placement
+                .iter()
+                .all(|placed| placed.tetra == o || placed.tetra == i));
+        }
+    }
+
+    #[test]
+    fn enclose_blocked_keeps_only_enclosing_results() {
+        let cfg = || {
+            Configuration::new(Size::new(4, 5), [Pos::new(1, 1)].into_iter().collect())
+                .with_mode(SearchMode::Exhaustive)
+        };
+
+        let all = cfg().run(&mut StatsDummy);
+        let enclosing = cfg().with_enclose_blocked(true).run(&mut StatsDummy);
+
+        assert!(!enclosing.is_empty());
+        assert!(enclosing.len() < all.len());
+        assert!(enclosing.is_subset(&all));
+    }
+
+    #[test]
+    fn solve_after_toggle_matches_fresh_solve() {
+        let fresh = |unavailable: &[Pos]| {
+            Configuration::new(Size::new(4, 5), unavailable.iter().copied().collect())
+                .with_mode(SearchMode::Exhaustive)
+        };
+        let mut solver = Solver::new(fresh(&[]));
+
+        solver.toggle_cell(Pos::new(1, 1)).unwrap();
+        assert_eq!(
+            solver.candidates,
+            Candidates::new(&fresh(&[Pos::new(1, 1)]))
+        );
+        let results = solver.run(&mut StatsDummy);
+        assert!(!results.is_empty());
+        assert_eq!(results, fresh(&[Pos::new(1, 1)]).run(&mut StatsDummy));
+
+        solver.toggle_cell(Pos::new(1, 1)).unwrap();
+        assert_eq!(solver.candidates, Candidates::new(&fresh(&[])));
+
+        assert_eq!(
+            solver.toggle_cell(Pos::new(4, 0)),
+            Err(ToggleError::OutOfField(Pos::new(4, 0)))
+        );
+    }
+
+    #[test]
+    fn max_piece_count_limits_placements() {
+        let cfg = Configuration::new(Size::new(6, 6), HashSet::new())
+            .with_max_piece_count(3)
+            .with_results_limit(NonZeroUsize::new(50).unwrap());
+
+        let results = cfg.run(&mut StatsDummy);
+
+        assert!(!results.is_empty());
+        assert!(results.iter().all(|result| result.placement.len() <= 3));
+    }
+
+    #[test]
+    fn exact_piece_count() {
+        let cfg = Configuration::new(Size::new(4, 4), HashSet::new())
+            .with_mode(SearchMode::Exhaustive)
+            .with_exact_piece_count(2);
+
+        let results = cfg.run(&mut StatsDummy);
+
+        assert!(!results.is_empty());
+        assert!(results
+            .iter()
+            .all(|result| result.placement.len() == 2 && result.free == 8));
+    }
+
+    #[test]
+    fn max_depth_stops_descending() {
+        let cfg = || {
+            Configuration::from_board_str("----\n----")
+                .unwrap()
+                .with_mode(SearchMode::Exhaustive)
+                .with_max_depth(1)
+        };
+
+        // a single tetra leaves too many free cells to be accepted
+        assert!(cfg().run(&mut StatsDummy).is_empty());
+
+        let partial = cfg()
+            .on_no_solution(NoSolutionPolicy::BestPartial)
+            .run(&mut StatsDummy);
+        assert_eq!(partial.len(), 1);
+        assert!(partial.iter().all(|result| result.placement.len() == 1));
+    }
+
+    #[test]
+    fn complete_feasibility_counts_each_area() {
+        let feasible = |board| {
+            Configuration::from_board_str(board)
+                .unwrap()
+                .is_complete_feasible()
+        };
+
+        assert!(feasible("----x----\n----x----"));
+        // 8 playable cells, but split into areas of 6 and 2
+        assert!(!feasible("---x-x\n---x-x"));
+        assert!(!feasible("---\n---\n---"));
+
+        let prefilled = Configuration::from_board_str("----\n----")
+            .unwrap()
+            .with_prefilled(Placed::new(&TETRAS[7], Pos::new(0, 0)))
+            .unwrap();
+        assert!(prefilled.is_complete_feasible());
+    }
+
+    #[test]
+    fn max_recursions_truncates_search() {
+        #[derive(Default)]
+        struct Truncation {
+            recursions: usize,
+            truncated: bool,
+        }
+
+        impl CollectStats for Truncation {
+            fn recursions_inc(&mut self) {
+                self.recursions += 1;
+            }
+
+            fn results_inc(&mut self) {}
+
+            fn search_truncated(&mut self) {
+                self.truncated = true;
+            }
+        }
+
+        let conf = Configuration::new(Size::new(8, 8), HashSet::new())
+            .with_mode(SearchMode::Exhaustive)
+            .with_max_recursions(50);
+        let mut stats = Truncation::default();
+
+        conf.run(&mut stats);
+
+        assert!(stats.truncated);
+        assert_eq!(stats.recursions, 50);
+    }
+
+    #[test]
+    fn time_limit_truncates_search() {
+        let conf = Configuration::new(Size::new(8, 8), HashSet::new())
+            .with_mode(SearchMode::Exhaustive)
+            .with_time_limit(Duration::ZERO);
+
+        let (_, outcome) = conf.run_with_outcome(&mut StatsDummy);
+
+        assert_eq!(outcome, RunOutcome::Truncated);
+    }
+
+    #[test]
+    fn seed_makes_random_search_reproducible() {
+        let conf = || {
+            Configuration::new(Size::new(6, 6), HashSet::new())
+                .with_scan_order(ScanOrder::Random)
+                .with_max_recursions(500)
+                .with_seed(5)
+        };
+
+        let results = conf().run(&mut StatsDummy);
+        assert!(!results.is_empty());
+        assert_eq!(conf().run(&mut StatsDummy), results);
+    }
+
+    #[test]
+    fn parity_prune_skips_search_of_unbalanced_field() {
+        #[derive(Default)]
+        struct Recursions(usize);
+
+        impl CollectStats for Recursions {
+            fn recursions_inc(&mut self) {
+                self.0 += 1;
+            }
+
+            fn results_inc(&mut self) {}
+        }
+
+        // 12 cells, but 8 of one color and 4 of the other
+        let with_t = Configuration::from_board_str("-x-x\n----\n----\nx-x-")
+            .unwrap()
+            .with_mode(SearchMode::Exhaustive)
+            .with_complete(true);
+        assert!(with_t.is_complete_feasible());
+        assert!(with_t.is_parity_feasible());
+
+        let no_t: Vec<_> = TETRAS
+            .iter()
+            .filter(|tetra| PieceKind::of(tetra) != PieceKind::T)
+            .collect();
+        let cfg = with_t.with_pieces(no_t);
+        assert!(!cfg.is_parity_feasible());
+        let mut searched = Recursions::default();
+        assert!(cfg.run(&mut searched).is_empty());
+        assert!(searched.0 > 0);
+
+        let cfg = cfg.with_parity_prune(true);
+        let mut pruned = Recursions::default();
+        let (results, outcome) = cfg.run_with_outcome(&mut pruned);
+
+        assert!(results.is_empty());
+        assert_eq!(outcome, RunOutcome::Complete);
+        assert_eq!(pruned.0, 0);
+    }
+
+    #[test]
+    fn symmetry_breaking_cuts_recursions() {
+        #[derive(Default)]
+        struct Recursions(usize);
+
+        impl CollectStats for Recursions {
+            fn recursions_inc(&mut self) {
+                self.0 += 1;
+            }
+
+            fn results_inc(&mut self) {}
+        }
+
+        let size = Size::new(4, 4);
+        // the smallest of the cells covered by each tetra, among all symmetric images
+        let class = |result: &PlacementResult| {
+            Transform::ALL
+                .into_iter()
+                .map(|transform| {
+                    result
+                        .placement
+                        .iter()
+                        .map(|placed| {
+                            placed
+                                .iter_relative_to_place()
+                                .map(|pos| transform.apply_to_pos(pos, size))
+                                .collect::<BTreeSet<_>>()
+                        })
+                        .collect::<BTreeSet<_>>()
+                })
+                .min()
+                .unwrap()
+        };
+        let conf = |unavailable| {
+            Configuration::new(size, unavailable)
+                .with_mode(SearchMode::Exhaustive)
+                .with_scan_order(ScanOrder::MostConstrainedFirst)
+                .with_complete(true)
+        };
+        let (mut all_stats, mut broken_stats) = (Recursions::default(), Recursions::default());
+
+        let all = conf(HashSet::new()).run(&mut all_stats);
+        let broken = conf(HashSet::new())
+            .with_symmetry_breaking(true)
+            .run(&mut broken_stats);
+
+        let all_classes: BTreeSet<_> = all.iter().map(class).collect();
+        let broken_classes: BTreeSet<_> = broken.iter().map(class).collect();
+        assert_eq!((all_stats.0, broken_stats.0), (414, 226));
+        assert_eq!((all.len(), broken.len()), (117, 63));
+        assert!(broken.is_subset(&all));
+        assert_eq!(all_classes, broken_classes);
+
+        // a field without symmetries is searched as usual
+        let skewed = || {
+            conf(HashSet::from(
+                [(0, 0), (0, 1), (0, 2), (1, 0)].map(Pos::from),
+            ))
+        };
+        let skewed_results = skewed().run(&mut StatsDummy);
+        assert!(!skewed_results.is_empty());
+        assert_eq!(
+            skewed().with_symmetry_breaking(true).run(&mut StatsDummy),
+            skewed_results
+        );
+    }
+
+    #[test]
+    fn parallel_dedup_matches_serial_one() {
+        // a full 6x6 takes too long to search exhaustively, so half of it is busy
+        let conf = Configuration::from_board_str("------\n------\n----xx\nxxxxxx\nxxxxxx\nxxxxxx")
+            .unwrap()
+            .with_mode(SearchMode::Exhaustive)
+            .with_complete(true);
+
+        let serial = conf.run(&mut StatsDummy);
+        let parallel = conf.run_parallel(NonZeroUsize::new(4).unwrap());
+
+        assert!(!serial.is_empty());
+        assert_eq!(parallel, serial);
+    }
+
+    #[test]
+    fn outcome_tells_why_search_stopped() {
+        let conf = || {
+            Configuration::new(Size::new(4, 4), HashSet::new()).with_mode(SearchMode::Exhaustive)
+        };
+
+        let (results, outcome) = conf()
+            .with_results_limit(NonZeroUsize::new(5).unwrap())
+            .run_with_outcome(&mut StatsDummy);
+        assert_eq!((results.len(), outcome), (5, RunOutcome::LimitReached));
+
+        let (results, outcome) = conf().run_with_outcome(&mut StatsDummy);
+        assert_eq!((results.len(), outcome), (117, RunOutcome::Complete));
+
+        let (_, outcome) = conf()
+            .with_max_recursions(10)
+            .run_with_outcome(&mut StatsDummy);
+        assert_eq!(outcome, RunOutcome::Truncated);
+    }
+
+    #[test]
+    fn limit_counts_distinct_results() {
+        let limit = NonZeroUsize::new(10).unwrap();
+        let conf = Configuration::new(Size::new(4, 4), HashSet::new())
+            .with_mode(SearchMode::Exhaustive)
+            .with_results_limit(limit);
+
+        let serial = conf.run(&mut StatsDummy);
+        let parallel = conf.run_parallel(NonZeroUsize::new(4).unwrap());
+
+        for results in [serial, parallel] {
+            let signatures: HashSet<_> = results.iter().map(PlacementResult::signature).collect();
+            assert_eq!(signatures.len(), limit.get());
+        }
+    }
+
+    #[test]
+    fn sink_takes_every_result() {
+        #[derive(Debug)]
+        struct CountingSink {
+            taken: usize,
+            distinct: HashSet<PlacementResult>,
+            stop_at: usize,
+        }
+
+        impl ResultSink for CountingSink {
+            fn accept(&mut self, result: PlacementResult) -> ControlFlow<()> {
+                self.taken += 1;
+                self.distinct.insert(result);
+                if self.taken == self.stop_at {
+                    ControlFlow::Break(())
+                } else {
+                    ControlFlow::Continue(())
+                }
+            }
+        }
+
+        // the limit is up to the sink
+        let conf = Configuration::new(Size::new(4, 4), HashSet::new())
+            .with_mode(SearchMode::Exhaustive)
+            .with_results_limit(NonZeroUsize::new(5).unwrap());
+
+        let mut sink = CountingSink {
+            taken: 0,
+            distinct: HashSet::new(),
+            stop_at: usize::MAX,
+        };
+        let outcome = conf.run_into(&mut sink, &mut StatsDummy);
+        // some placements are reached by several paths, and skipping them again is up to the sink
+        assert_eq!((sink.taken, outcome), (267, RunOutcome::Complete));
+        assert_eq!(sink.distinct.len(), 117);
+
+        let mut sink = CountingSink {
+            taken: 0,
+            distinct: HashSet::new(),
+            stop_at: 20,
+        };
+        let outcome = conf.run_into(&mut sink, &mut StatsDummy);
+        assert_eq!((sink.taken, outcome), (20, RunOutcome::LimitReached));
+    }
+
+    #[test]
+    fn tall_field_does_not_grow_call_stack() {
+        // a recursive search would need a call per placed tetra, far more than fits into this
+        let search = std::thread::Builder::new()
+            .stack_size(64 * 1024)
+            .spawn(|| {
+                // O-s are tried first, so the first descent covers the whole field
+                let conf = Configuration::new(Size::new(40, 4), HashSet::new())
+                    .with_mode(SearchMode::Exhaustive)
+                    .with_complete(true)
+                    .with_results_limit(NonZeroUsize::new(1).unwrap());
+                conf.run(&mut StatsDummy)
+            })
+            .unwrap();
+
+        let results = search.join().unwrap();
+
+        assert_eq!(results.len(), 1);
+        assert!(results.iter().all(|result| result.placement.len() == 40));
+    }
+
+    #[test]
+    fn records_all_maximal_placements() {
+        let conf = Configuration::from_board_str("---\n---\n---")
+            .unwrap()
+            .with_mode(SearchMode::Exhaustive)
+            .with_complete(true)
+            .record_all_maximal(true);
+
+        let results = conf.run(&mut StatsDummy);
+
+        assert!(!results.is_empty());
+        for result in &results {
+            assert_eq!(result.free, 9 - result.placement.len() * 4);
+            let occupied: HashSet<_> = result
+                .placement
+                .iter()
+                .flat_map(|placed| placed.iter_relative_to_place())
+                .collect();
+            let fits_more = TETRAS.iter().any(|tetra| {
+                (0..3)
+                    .flat_map(|row| (0..3).map(move |col| Pos::new(row, col)))
+                    .filter_map(|pos| {
+                        PlacedBoundariesChecked::in_boundaries(Placed::new(tetra, pos), conf.size)
+                    })
+                    .any(|placed| {
+                        placed
+                            .iter_relative_to_place()
+                            .all(|cell| !occupied.contains(&cell))
+                    })
+            });
+            assert!(!fits_more, "{result:?} isn't maximal");
+        }
+    }
+
+    #[test]
+    fn min_free_is_fewest_cells_left() {
+        let field = |rows, cols| {
+            Configuration::new(Size::new(rows, cols), HashSet::new())
+                .with_mode(SearchMode::Exhaustive)
+        };
+
+        assert_eq!(field(4, 4).min_free(), Some(0));
+        assert_eq!(field(3, 3).min_free(), Some(1));
+        // a 2x3 field fits only one tetra, so none of its placements are complete ones
+        assert_eq!(field(2, 3).with_complete(true).min_free(), Some(2));
+    }
+
+    #[test]
+    fn acceptance_threshold_is_root_of_coverable_cells() {
+        for (how_many_free, min_free_cells, threshold) in [
+            (0, 0, 0),
+            (3, 3, 0),
+            (4, 0, 2),
+            (8, 0, 2),
+            (9, 1, 2),
+            (16, 0, 4),
+            (35, 3, 5),
+            (36, 0, 6),
+        ] {
+            assert_eq!(
+                acceptance_threshold(how_many_free, min_free_cells),
+                threshold,
+                "{how_many_free} free, {min_free_cells} at least"
+            );
+        }
+    }
+
+    #[test]
+    fn free_cells_slack_loosens_threshold() {
+        // the threshold of 8 cells is 2, a single tetra leaves 4 cells free
+        let cfg = Configuration::from_board_str("----\n----")
+            .unwrap()
+            .with_mode(SearchMode::Exhaustive)
+            .with_max_depth(1)
+            .with_free_cells_slack(3);
+
+        let results = cfg.run(&mut StatsDummy);
+
+        assert!(!results.is_empty());
+        assert!(results.iter().all(|result| result.free == 4));
+    }
+
+    #[test]
+    fn solve_one() {
+        let tileable = Configuration::from_board_str("----\n----")
+            .unwrap()
+            .with_mode(SearchMode::Exhaustive)
+            .with_complete(true);
+        let infeasible = Configuration::from_board_str("---\n---\n---")
+            .unwrap()
+            .with_mode(SearchMode::Exhaustive)
+            .with_complete(true);
+
+        let solution = tileable.solve_one().unwrap();
+
+        assert_eq!(solution.free, 0);
+        assert_eq!(solution.placement.len(), 2);
+        assert!(tileable.run(&mut StatsDummy).contains(&solution));
+        assert_eq!(infeasible.solve_one(), None);
+    }
+
+    #[test]
+    fn cancelled_search_stops() {
+        struct CancelAfter<'a> {
+            results: usize,
+            cancel: &'a AtomicBool,
+        }
+
+        impl CollectStats for CancelAfter<'_> {
+            fn recursions_inc(&mut self) {}
+
+            fn results_inc(&mut self) {
+                self.results += 1;
+                if self.results == 3 {
+                    self.cancel.store(true, Ordering::Relaxed);
+                }
+            }
+        }
+
+        let cfg =
+            Configuration::new(Size::new(4, 4), HashSet::new()).with_mode(SearchMode::Exhaustive);
+        let cancel = AtomicBool::new(false);
+        let mut stats = CancelAfter {
+            results: 0,
+            cancel: &cancel,
+        };
+
+        assert_eq!(cfg.run_cancellable(&mut stats, &cancel).len(), 3);
+        assert!(cfg.run_cancellable(&mut StatsDummy, &cancel).is_empty());
+    }
+
+    #[test]
+    fn piece_usage_sums_up_to_placed_pieces() {
+        let cfg = Configuration::new(Size::new(6, 6), HashSet::new())
+            .with_results_limit(NonZeroUsize::new(20).unwrap());
+        let results = cfg.run(&mut StatsDummy);
+        assert!(!results.is_empty());
+
+        let usage = aggregate_piece_usage(&results);
+
+        let placed: usize = results.iter().map(|result| result.placement.len()).sum();
+        assert_eq!(usage.values().sum::<usize>(), placed);
+        assert!(usage.keys().all(|id| *id < TETRAS.len()));
+    }
+
+    #[test]
+    fn leading_kinds_sum_up_to_results() {
+        let conf = Configuration::from_board_str("x----\n-----\n-----")
+            .unwrap()
+            .with_mode(SearchMode::Exhaustive);
+        let results = conf.run(&mut StatsDummy);
+        assert!(!results.is_empty());
+
+        let counts = count_by_leading_kind(&results, &conf);
+
+        assert_eq!(counts.values().sum::<usize>(), results.len());
+        // the top left corner is blocked, so the search starts from the cell to the right of it
+        let expected_o = results
+            .iter()
+            .filter(|result| {
+                result
+                    .placement
+                    .iter()
+                    .any(|placed| placed.tetra == &TETRAS[0] && placed.position == Pos::new(0, 1))
+            })
+            .count();
+        assert_eq!(
+            counts.get(&Some(PieceKind::O)).copied().unwrap_or(0),
+            expected_o
+        );
+    }
+
+    #[test]
+    fn invalid_board_literal() {
+        assert!(matches!(
+            Configuration::from_board_str("--\n-#"),
+            Err(ParseError::UnexpectedCharacter { char_busy: 'x', .. })
+        ));
+        assert!(matches!(
+            Configuration::from_board_str(""),
+            Err(ParseError::EmptyInput)
+        ));
+    }
+
+    #[test]
+    fn first_fit_is_the_first_empty_candidate() {
+        let conf = Configuration::new(Size::new(4, 4), HashSet::new())
+            .with_prefilled(Placed::new(crate::tetra::I_HORIZONTAL, Pos::new(0, 0)))
+            .unwrap();
+        let mut stats = StatsDummy;
+        let mut state = RecursionState::with_configuration(&conf, &mut stats);
+
+        let square = PlacedBoundariesChecked::in_boundaries(
+            Placed::new(&TETRAS[0], Pos::new(1, 0)),
+            conf.size,
+        );
+        assert_eq!(state.find_any_fit_for(0), square);
+        for id in 0..TETRAS.len() {
+            let expected = state.candidates.per_tetra[id]
+                .iter()
+                .find(|candidate| candidate.iter_relative_to_place().all(|pos| pos.row > 0))
+                .cloned();
+            assert_eq!(state.find_any_fit_for(id), expected);
+        }
+    }
+
+    #[test]
+    fn fit_policies_pick_among_places() {
+        let square = |row, col| {
+            PlacedBoundariesChecked::in_boundaries(
+                Placed::new(&TETRAS[0], Pos::new(row, col)),
+                Size::new(3, 3),
+            )
+            .unwrap()
+        };
+        // a square fits into each corner of the 3x3
+        let picks = |policy, count| {
+            let conf = Configuration::new(Size::new(3, 3), HashSet::new()).with_fit_policy(policy);
+            let mut stats = StatsDummy;
+            let mut state = RecursionState::with_configuration(&conf, &mut stats);
+            (0..count)
+                .map(|_| state.find_any_fit_for(0).unwrap())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(picks(FitPolicy::First, 1), [square(0, 0)]);
+        assert_eq!(picks(FitPolicy::Last, 1), [square(1, 1)]);
+        assert_eq!(picks(FitPolicy::MostConstrainedCell, 1), [square(0, 0)]);
+
+        let seeded = picks(FitPolicy::Random(Some(3)), 8);
+        assert_eq!(picks(FitPolicy::Random(Some(3)), 8), seeded);
+        assert_eq!(
+            seeded[..4],
+            [square(0, 0), square(0, 1), square(0, 1), square(1, 0)]
+        );
+    }
+
+    #[test]
+    fn region_sizes_rank_big_region_above_small_one() {
+        let conf = Configuration::from_board_str("--x-\n--x-\n--x-\n--x-").unwrap();
+
+        let sizes = empty_region_sizes(&conf.field_grid());
+
+        assert_eq!(sizes[0][0], 8);
+        assert_eq!(sizes[3][3], 4);
+        assert_eq!(sizes[0][2], 0);
+
+        // the last place of a vertical I is in the small area, unless the largest is preferred
+        let last_fit = |heuristic| {
+            let conf = Configuration::from_board_str("--x-\n--x-\n--x-\n--x-")
+                .unwrap()
+                .with_fit_policy(FitPolicy::Last)
+                .with_heuristic(heuristic);
+            let mut stats = StatsDummy;
+            let mut state = RecursionState::with_configuration(&conf, &mut stats);
+            state.find_any_fit_for(2).unwrap().position
+        };
+        assert_eq!(last_fit(Heuristic::None), Pos::new(0, 3));
+        assert_eq!(last_fit(Heuristic::FillLargest), Pos::new(0, 1));
+    }
+
+    #[test]
+    fn occupancy_rows_cross_words() {
+        let size = Size::new(3, 70);
+        let square =
+            PlacedBoundariesChecked::in_boundaries(Placed::new(&TETRAS[0], Pos::new(1, 63)), size)
+                .unwrap();
+        let mask = RowMask::new(square.tetra);
+        let mut occupancy = Occupancy::new(size);
+
+        occupancy.fill(&mask, &square);
+        assert_eq!(occupancy.words, [0, 0, 1 << 63, 1, 1 << 63, 1]);
+        assert!(!occupancy.is_free(&mask, &square));
+        occupancy.clear(&mask, &square);
+        assert!(occupancy.words.iter().all(|word| *word == 0));
+    }
+
+    #[test]
+    fn gravity_rejects_floating_tetras() {
+        let conf = Configuration::new(Size::new(4, 4), [Pos::new(3, 3)].into_iter().collect())
+            .with_gravity(true);
+        let mut stats = StatsDummy;
+        let mut state = RecursionState::with_configuration(&conf, &mut stats);
+        let square = |row, col| {
+            PlacedBoundariesChecked::in_boundaries(
+                Placed::new(&TETRAS[0], Pos::new(row, col)),
+                conf.size,
+            )
+            .unwrap()
+        };
+
+        let fits = |placed| state.fits(&placed, &RowMask::new(&TETRAS[0]));
+
+        assert!(!fits(square(0, 0)));
+        assert!(fits(square(2, 0)));
+        assert!(fits(square(1, 2)));
+        assert_eq!(state.find_any_fit_for(0), Some(square(1, 2)));
+    }
+
+    #[test]
+    fn placement_options_of_corner_and_center() {
+        let conf = Configuration::new(Size::new(4, 4), HashSet::new());
+
+        assert!(conf.placement_options(Pos::new(0, 0)) < conf.placement_options(Pos::new(1, 1)));
+        assert_eq!(
+            conf.placement_options(Pos::new(0, 0)),
+            conf.placement_options(Pos::new(3, 3))
+        );
+
+        let blocked = Configuration::from_board_str("x---\n----\n----\n----").unwrap();
+        assert_eq!(blocked.placement_options(Pos::new(0, 0)), 0);
+    }
+
+    #[test]
+    fn most_constrained_first_keeps_exhaustive_results() {
+        let cfg = Configuration::new(Size::new(4, 4), HashSet::new())
+            .with_mode(SearchMode::Exhaustive)
+            .with_scan_order(ScanOrder::MostConstrainedFirst);
+
+        let results = cfg.run(&mut StatsDummy);
+
+        assert_eq!(results.len(), 117);
+    }
+
+    #[test]
+    fn column_scan_order() {
+        let cfg =
+            Configuration::new(Size::new(2, 3), HashSet::new()).with_scan_order(ScanOrder::Column);
+
+        assert_eq!(
+            Candidates::new(&cfg).positions,
+            vec![
+                Pos::new(0, 0),
+                Pos::new(1, 0),
+                Pos::new(0, 1),
+                Pos::new(1, 1),
+                Pos::new(0, 2),
+                Pos::new(1, 2),
+            ]
+        );
+    }
+
+    #[test]
+    fn spiral_scan_order() {
+        let cells = ScanOrder::Spiral.cells(Size::new(3, 4), &mut StdRng::seed_from_u64(0));
+
+        let expected = [
+            (0, 0),
+            (0, 1),
+            (0, 2),
+            (0, 3),
+            (1, 3),
+            (2, 3),
+            (2, 2),
+            (2, 1),
+            (2, 0),
+            (1, 0),
+            (1, 1),
+            (1, 2),
+        ]
+        .map(|(row, col)| Pos::new(row, col));
+        assert_eq!(cells, expected);
+    }
+
+    #[test]
+    fn toggle_keeps_scan_order() {
+        let fresh = |unavailable: &[Pos]| {
+            Configuration::new(Size::new(4, 5), unavailable.iter().copied().collect())
+                .with_scan_order(ScanOrder::Spiral)
+        };
+        let mut solver = Solver::new(fresh(&[]));
+
+        solver.toggle_cell(Pos::new(1, 1)).unwrap();
+        assert_eq!(
+            solver.candidates,
+            Candidates::new(&fresh(&[Pos::new(1, 1)]))
+        );
+        solver.toggle_cell(Pos::new(1, 1)).unwrap();
+        assert_eq!(solver.candidates, Candidates::new(&fresh(&[])));
+    }
+
+    #[test]
+    fn results_budget_caps_boards_in_total() {
+        let limit = NonZeroUsize::new;
+        let solve_all = |per_board: usize, total: usize| {
+            let mut budget = ResultsBudget::new(limit(total));
+            let mut found = Vec::new();
+            for _ in 0..2 {
+                if budget.is_spent() {
+                    break;
+                }
+                let mut conf = Configuration::new(Size::new(4, 4), HashSet::new())
+                    .with_mode(SearchMode::Exhaustive)
+                    .with_results_limit(limit(per_board).unwrap());
+                conf.results_limit = budget.limit(conf.results_limit);
+                let results = conf.run(&mut StatsDummy);
+                budget.spend(results.len());
+                found.push(results.len());
+            }
+            found
+        };
+
+        assert_eq!(solve_all(3, 100), [3, 3]);
+        assert_eq!(solve_all(3, 5), [3, 2]);
+        assert_eq!(solve_all(10, 4), [4]);
+    }
+
+    #[test]
+    fn heatmap_counts_covering_placements() {
+        let conf = Configuration::new(Size::new(2, 4), [Pos::new(0, 0)].into())
+            .with_mode(SearchMode::Exhaustive);
+        let results = conf.run(&mut StatsDummy);
+
+        let heatmap = coverage_heatmap(&results, &conf);
+
+        for row in 0..2 {
+            for col in 0..4 {
+                let covering = results
+                    .iter()
+                    .filter(|result| {
+                        result.placement.iter().any(|placed| {
+                            placed
+                                .iter_relative_to_place()
+                                .any(|pos| pos == Pos::new(row, col))
+                        })
+                    })
+                    .count();
+                assert_eq!(heatmap[row][col], covering, "cell {row}:{col}");
+            }
+        }
+        assert_eq!(heatmap[0][0], 0);
+        assert!(heatmap.iter().any(|count| *count == results.len()));
+    }
+
+    #[test]
+    fn applies_placements_without_searching() {
+        let square = |row, col, size| {
+            PlacedBoundariesChecked::in_boundaries(
+                Placed::new(&TETRAS[0], Pos::new(row, col)),
+                size,
+            )
+            .unwrap()
+        };
+        let size = Size::new(2, 5);
+        let conf = Configuration::new(size, [Pos::new(0, 4)].into())
+            .with_prefilled(square(0, 0, size).into())
+            .unwrap();
+
+        let grid = conf
+            .apply(&[square(0, 0, size), square(0, 2, size)])
+            .unwrap();
+
+        assert_eq!(
+            grid.iter().filter(|cell| **cell == Cell::Occupied).count(),
+            8
+        );
+        assert_eq!(grid[0][4], Cell::Unavailable);
+        assert_eq!(grid[1][4], Cell::Empty);
+        assert_eq!(
+            conf.apply(&[square(0, 1, size)]),
+            Err(PlacementError::Overlap(Pos::new(0, 1)))
+        );
+        assert_eq!(
+            conf.apply(&[square(2, 2, Size::new(4, 4))]),
+            Err(PlacementError::OutOfBoundaries(Pos::new(2, 2)))
+        );
+    }
+
+    #[test]
+    fn every_result_contains_seed_piece() {
+        let seed = Placed::new(crate::tetra::PieceKind::T.oriented(1), Pos::new(1, 2));
+        let cfg = Configuration::new(Size::new(4, 4), HashSet::new())
+            .with_mode(SearchMode::Exhaustive)
+            .with_prefilled(seed.clone())
+            .unwrap();
+        let seed = PlacedBoundariesChecked::in_boundaries(seed, cfg.size).unwrap();
+
+        let results = cfg.run(&mut StatsDummy);
+
+        assert!(!results.is_empty());
+        assert!(results
+            .iter()
+            .all(|result| result.placement.contains(&seed)));
+    }
+
+    mod no_solution {
+        use super::*;
+
+        /// 9 cells can't be covered by tetras completely
+        fn unsolvable(policy: NoSolutionPolicy) -> Configuration {
+            Configuration::new(Size::new(3, 3), HashSet::new())
+                .with_mode(SearchMode::Exhaustive)
+                .with_complete(true)
+                .on_no_solution(policy)
+        }
+
+        #[test]
+        fn empty() {
+            let cfg = unsolvable(NoSolutionPolicy::Empty);
+
+            assert_eq!(cfg.try_run(&mut StatsDummy), Ok(BTreeSet::new()));
+        }
+
+        #[test]
+        fn best_partial() {
+            let cfg = unsolvable(NoSolutionPolicy::BestPartial);
+
+            let results = cfg.try_run(&mut StatsDummy).unwrap();
+
+            assert_eq!(results.len(), 1);
+            let best = results.first().unwrap();
+            assert_eq!(best.free, 1);
+            assert_eq!(best.placement.len(), 2);
+        }
+
+        #[test]
+        fn error() {
+            let cfg = unsolvable(NoSolutionPolicy::Error);
+
+            assert_eq!(cfg.try_run(&mut StatsDummy), Err(NoSolutionError));
+            assert!(cfg.run(&mut StatsDummy).is_empty());
+        }
+    }
+
+    #[test]
+    fn results_equal_regardless_of_placement_order() {
+        let size = Size::new(4, 4);
+        let placed = |row| {
+            PlacedBoundariesChecked::in_boundaries(Placed::new(&TETRAS[1], Pos::new(row, 0)), size)
+                .unwrap()
+        };
+        let first = PlacementResult {
+            placement: [0, 1, 2, 3].into_iter().map(placed).collect(),
+            free: 0,
+            order: None,
+        };
+        let second = PlacementResult {
+            placement: [3, 1, 0, 2].into_iter().map(placed).collect(),
+            free: 0,
+            order: None,
+        };
+
+        assert_eq!(first, second);
+        assert_eq!(fingerprint(&first), fingerprint(&second));
+    }
+
+    #[test]
+    fn narration_has_line_per_placed_tetra_and_summary() {
+        let conf = Configuration::from_board_str("----\n----\n----")
+            .unwrap()
+            .with_mode(SearchMode::Exhaustive)
+            .with_complete(true)
+            .with_placement_order(true)
+            .with_prefilled(Placed::new(&TETRAS[0], Pos::new(0, 0)))
+            .unwrap();
+        let result = conf.solve_one().unwrap();
+
+        let lines = result.narrate(&conf);
+
+        // the prefilled tetra is left out
+        let placed = result.placement.len() - 1;
+        assert_eq!(lines.len(), placed + 1);
+        assert!(lines[0].ends_with(", 4 cells remain"));
+        assert!(lines[1].ends_with(", 0 cells remain"));
+        assert_eq!(lines[2], "Covered all 8 cells with 2 pieces");
+
+        let conf = Configuration::from_board_str("----\n----")
+            .unwrap()
+            .with_optional(HashSet::from([Pos::new(1, 2), Pos::new(1, 3)]))
+            .with_mode(SearchMode::Exhaustive)
+            .with_complete(true);
+        let result = conf.solve_one().unwrap();
+
+        let lines = result.narrate(&conf);
+        assert!(lines[lines.len() - 2].ends_with(", 0 cells remain"));
+        assert!(lines
+            .last()
+            .unwrap()
+            .starts_with("Covered all 6 cells with "));
+    }
+
+    #[test]
+    fn fill_percent_is_share_of_covered_cells() {
+        let complete = Configuration::new(Size::new(4, 4), HashSet::new())
+            .with_mode(SearchMode::Exhaustive)
+            .with_complete(true);
+        let results = complete.run(&mut StatsDummy);
+        assert!(!results.is_empty());
+        assert!(results
+            .iter()
+            .all(|result| result.fill_percent(&complete) == 100.0));
+
+        let conf =
+            Configuration::new(Size::new(3, 3), HashSet::new()).with_mode(SearchMode::Exhaustive);
+        let one_free: Vec<_> = conf
+            .run(&mut StatsDummy)
+            .into_iter()
+            .filter(|result| result.free == 1)
+            .collect();
+        assert!(!one_free.is_empty());
+        for result in &one_free {
+            assert!((result.fill_percent(&conf) - 800.0 / 9.0).abs() < 1e-9);
+        }
+
+        // 8 required cells and an optional corner
+        let with_optional = conf.with_optional(HashSet::from([Pos::new(2, 2)]));
+        let one_free: Vec<_> = with_optional
+            .run(&mut StatsDummy)
+            .into_iter()
+            .filter(|result| result.free == 1)
+            .collect();
+        assert!(!one_free.is_empty());
+        for result in &one_free {
+            assert_eq!(result.fill_percent(&with_optional), 87.5);
+        }
+    }
+
+    #[test]
+    fn signature_depends_only_on_placement() {
+        let size = Size::new(4, 4);
+        let placed = |tetra, row| {
+            PlacedBoundariesChecked::in_boundaries(
+                Placed::new(&TETRAS[tetra], Pos::new(row, 0)),
+                size,
+            )
+            .unwrap()
+        };
+        let result = |placement: &[(usize, usize)]| PlacementResult {
+            placement: placement
+                .iter()
+                .map(|(tetra, row)| placed(*tetra, *row))
+                .collect(),
+            free: 16 - placement.len() * 4,
+            order: None,
+        };
+
+        let first = result(&[(1, 0), (1, 1), (1, 3)]);
+        let second = result(&[(1, 3), (1, 0), (1, 1)]);
+        let other = result(&[(1, 0), (1, 1), (1, 2)]);
+
+        assert_eq!(first.signature(), second.signature());
+        assert_ne!(first.signature(), other.signature());
+        assert_eq!(first.signature().len(), 16);
+    }
+
+    #[test]
+    fn grid_of_occupying_tetras() {
+        let conf = Configuration::from_board_str("----\n----\n---x").unwrap();
+        let result = PlacementResult {
+            placement: [
+                Placed::new(&TETRAS[1], Pos::new(0, 0)),
+                Placed::new(&TETRAS[7], Pos::new(1, 0)),
+            ]
+            .into_iter()
+            .map(|placed| PlacedBoundariesChecked::in_boundaries(placed, conf.size).unwrap())
+            .collect(),
+            free: 3,
+            order: None,
+        };
+
+        let grid = result.to_grid(&conf);
+
+        let l_idx = result
+            .placement
+            .iter()
+            .position(|placed| placed.tetra == &TETRAS[7])
+            .unwrap();
+        assert_eq!(grid[2][0], Some(l_idx));
+        assert_eq!(grid[0][3], Some(1 - l_idx));
+        assert_eq!(grid[2][1], None);
+        assert_eq!(grid[2][3], None);
+        assert_eq!(grid.iter().flatten().count(), 8);
+    }
+
+    #[test]
+    fn estimate_for_empty_4x4() {
+        let cfg = Configuration::new(Size::new(4, 4), HashSet::new());
+
+        let estimate = cfg.estimate();
+
+        // O: 3 * 3, two I-s: 4 each, other 16 tetras are 2x3 or 3x2: 6 each
+        assert_eq!(estimate.candidates, 9 + 4 + 4 + 16 * 6);
+        assert_eq!(estimate.free_cells, 16);
+        assert!(estimate.divisible);
+        assert_eq!(estimate.branching, 19);
+        assert_eq!(estimate.depth, 4);
+        assert!(estimate.to_string().contains("candidate placements: 113"));
+    }
+
+    #[test]
+    fn bigger_emptier_field_is_more_difficult() {
+        let tiny = Configuration::from_board_str("--\n-x").unwrap();
+        let big = Configuration::new(Size::new(8, 8), HashSet::new());
+
+        // 3 * (1 + 1/4) / (1 + 0) and 64 / (1 + 8)
+        assert_eq!(tiny.difficulty_estimate(), 3.75);
+        assert!((big.difficulty_estimate() - 64.0 / 9.0).abs() < 1e-9);
+        assert!(big.difficulty_estimate() > tiny.difficulty_estimate());
+    }
+
+    #[test]
+    fn tiles_l_shaped_field() {
+        // 4x4 with the top right 2x2 corner cut off
+        let outside: HashSet<_> = [(0, 2), (0, 3), (1, 2), (1, 3)]
+            .into_iter()
+            .map(Pos::from)
+            .collect();
+        let cfg = Configuration::new(Size::new(4, 4), HashSet::new())
+            .with_outside(outside.clone())
+            .with_mode(SearchMode::Exhaustive);
+
+        assert_eq!(cfg.cell_kind(&Pos::new(0, 3)), CellKind::Outside);
+        assert_eq!(cfg.cell_kind(&Pos::new(3, 3)), CellKind::Playable);
+        assert_eq!(cfg.estimate().free_cells, 12);
+
+        let results = cfg.run(&mut StatsDummy);
+
+        assert!(!results.is_empty());
+        for result in results {
+            assert_eq!(result.free, 0);
+            assert!(result
+                .placement
+                .iter()
+                .flat_map(|placed| placed.iter_relative_to_place())
+                .all(|pos| !outside.contains(&pos)));
+        }
+    }
+
+    struct CheckpointsCollector(Vec<Checkpoint>);
+
+    impl CollectStats for CheckpointsCollector {
+        fn recursions_inc(&mut self) {}
+
+        fn results_inc(&mut self) {}
+
+        fn checkpoint_interval(&self) -> Option<NonZeroUsize> {
+            NonZeroUsize::new(500)
+        }
+
+        fn checkpoint(&mut self, checkpoint: &Checkpoint) {
+            self.0.push(checkpoint.clone());
+        }
+    }
+
+    #[test]
+    fn resumed_search_yields_same_results() {
+        let cfg = || {
+            Configuration::new(Size::new(4, 4), HashSet::new()).with_mode(SearchMode::Exhaustive)
+        };
+
+        let mut collector = CheckpointsCollector(Vec::new());
+        let uninterrupted = cfg().run(&mut collector);
+        let checkpoint = collector.0[collector.0.len() / 2].clone();
+        assert!(checkpoint.results.len() < uninterrupted.len());
+
+        let json = serde_json::to_string(&checkpoint).unwrap();
+        let restored: Checkpoint = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored, checkpoint);
+
+        let resumed = cfg().with_resume(restored).unwrap().run(&mut StatsDummy);
+        assert_eq!(resumed, uninterrupted);
+    }
+
+    #[test]
+    fn resume_rejects_checkpoint_for_another_field() {
+        let mut collector = CheckpointsCollector(Vec::new());
+        Configuration::new(Size::new(4, 4), HashSet::new())
+            .with_mode(SearchMode::Exhaustive)
+            .run(&mut collector);
+        let checkpoint = collector.0.pop().unwrap();
+
+        let result = Configuration::new(Size::new(2, 2), HashSet::new()).with_resume(checkpoint);
+
+        assert!(matches!(result, Err(ResumeError::Placement(_))));
+    }
+
+    #[test]
+    fn results_count_for_empty_4x4() {
+        let cfg =
+            Configuration::new(Size::new(4, 4), HashSet::new()).with_mode(SearchMode::Exhaustive);
 
         let results = cfg.run(&mut StatsDummy);
 
-        assert_eq!(results.len(), 267);
+        // the number of tetromino tilings of a 4x4 square
+        assert_eq!(results.len(), 117);
     }
 
     #[test]
+    #[ignore = "the exhaustive search of this field takes minutes even in release builds"]
     fn results_count_for_non_empty_6x6() {
         let unavailable = {
             let mut set = HashSet::new();
@@ -311,10 +3897,11 @@ mod tests {
             }
             set
         };
-        let cfg = Configuration::new(Size::new(6, 6), unavailable);
+        let cfg =
+            Configuration::new(Size::new(6, 6), unavailable).with_mode(SearchMode::Exhaustive);
 
         let results = cfg.run(&mut StatsDummy);
 
-        assert_eq!(results.len(), 44);
+        assert_eq!(results.len(), 3_414_605);
     }
 }
@@ -1,6 +1,8 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::HashSet;
 use std::fmt::Write;
-use std::io::stdout;
+use std::io::{self, stdout};
+use std::num::NonZeroUsize;
+use std::ops::Range;
 
 use crossterm::style::{
     Attribute, Color, ResetColor, SetAttribute, SetBackgroundColor, SetForegroundColor,
@@ -14,19 +16,154 @@ use crossterm::{
     Command, ExecutableCommand, Result,
 };
 use grid::Grid;
+use miette::Diagnostic;
+use thiserror::Error;
 
-use crate::algorithm::{Configuration, PlacementResult};
-use crate::tetra::PlacedBoundariesChecked;
+use crate::algorithm::{CellKind, Configuration, PlacementResult};
+use crate::tetra::TETRAS;
 use crate::util::{Pos, Size};
 
 pub const CHAR_EMPTY: char = '·';
 pub const CHAR_UNAVAILABLE: char = '×';
+pub const CHAR_OUTSIDE: char = ' ';
+
+/// How to render placements
+#[derive(Debug, Clone)]
+pub struct RenderConfig {
+    /// Labels of placed tetras, in placement order. Cycled if there are more tetras than labels.
+    labels: Vec<char>,
+    /// Style tetras by their id rather than by placement order, so that the same tetra looks the
+    /// same in every placement
+    color_by_type: bool,
+    /// Give each tetra its own RGB color instead of cycling through a small palette
+    truecolor: bool,
+    /// How many terminal columns a cell takes
+    cell_width: usize,
+    /// Assign labels and styles in [`PlacementResult::fill_order`] rather than in placement order
+    fill_order: bool,
+    /// Empty lines between rows of a placement
+    row_spacing: usize,
+    /// Empty lines after each placement, or between rows of placements laid out in columns
+    placement_spacing: usize,
+}
+
+#[derive(Debug, Error, PartialEq, Diagnostic)]
+pub enum RenderConfigError {
+    #[error("At least one label should be provided")]
+    EmptyLabels,
+}
+
+impl Default for RenderConfig {
+    fn default() -> Self {
+        Self {
+            labels: ('A'..='Z').chain('a'..='z').collect(),
+            color_by_type: false,
+            truecolor: false,
+            cell_width: 1,
+            fill_order: false,
+            row_spacing: 0,
+            placement_spacing: 1,
+        }
+    }
+}
+
+impl RenderConfig {
+    pub fn with_labels(
+        mut self,
+        labels: Vec<char>,
+    ) -> std::result::Result<Self, RenderConfigError> {
+        if labels.is_empty() {
+            return Err(RenderConfigError::EmptyLabels);
+        }
+        self.labels = labels;
+        Ok(self)
+    }
+
+    pub fn color_by_type(mut self, value: bool) -> Self {
+        self.color_by_type = value;
+        self
+    }
+
+    /// Pads cells with spaces to take the given number of columns, at least 1. With 2 cells look
+    /// square-ish with most fonts
+    pub fn cell_width(mut self, width: usize) -> Self {
+        self.cell_width = width.max(1);
+        self
+    }
+
+    /// Should be enabled only if the terminal supports 24-bit colors, see [`supports_truecolor`]
+    pub fn truecolor(mut self, value: bool) -> Self {
+        self.truecolor = value;
+        self
+    }
+
+    /// Labels tetras in the order they fill the field, e.g. with [`FILL_ORDER_LABELS`] to number
+    /// the steps
+    pub fn fill_order(mut self, value: bool) -> Self {
+        self.fill_order = value;
+        self
+    }
+
+    pub fn row_spacing(mut self, lines: usize) -> Self {
+        self.row_spacing = lines;
+        self
+    }
+
+    pub fn placement_spacing(mut self, lines: usize) -> Self {
+        self.placement_spacing = lines;
+        self
+    }
+
+    /// What ends a placement: a line break for each empty line of [`Self::placement_spacing`]
+    pub fn placement_gap(&self) -> String {
+        "\n".repeat(self.placement_spacing)
+    }
+}
+
+/// Labels which number tetras: digits, then lowercase and uppercase letters
+pub const FILL_ORDER_LABELS: &str =
+    "0123456789abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ";
+
+/// Whether the terminal announces 24-bit colors support
+pub fn supports_truecolor() -> bool {
+    std::env::var("COLORTERM").is_ok_and(|value| value == "truecolor" || value == "24bit")
+}
+
+/// Color of the tetra with the given index in truecolor mode.
+///
+/// Hues are rotated by the golden angle, so that neighbour indices get far apart colors and no
+/// color repeats.
+fn truecolor_for(idx: usize) -> Color {
+    const GOLDEN_RATIO_CONJUGATE: f64 = 0.618_033_988_749_895;
+    const SATURATION: f64 = 0.65;
+    const VALUE: f64 = 0.95;
+
+    let hue = (idx as f64 * GOLDEN_RATIO_CONJUGATE).fract() * 6.0;
+    let chroma = VALUE * SATURATION;
+    let x = chroma * (1.0 - (hue % 2.0 - 1.0).abs());
+    let (r, g, b) = match hue as usize {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+    let m = VALUE - chroma;
+    let channel = |value: f64| ((value + m) * 255.0).round() as u8;
+
+    Color::Rgb {
+        r: channel(r),
+        g: channel(g),
+        b: channel(b),
+    }
+}
 
 pub mod live_configuration {
     use super::{
-        cursor, event, print_field_setup, stdout, terminal, Clear, ClearType, Configuration,
-        EnterAlternateScreen, Event, ExecutableCommand, HashSet, LeaveAlternateScreen, Pos, Print,
-        RawMode, Result, Size,
+        cursor, event, print_field_setup, ruler_width, stdout, terminal, CellKind, Clear,
+        ClearType, Configuration, EnterAlternateScreen, Event, ExecutableCommand, HashSet,
+        LeaveAlternateScreen, Pos, Print, Range, RawMode, Result, Size,
     };
     use crossterm::style::{Attribute, Color, SetAttribute, SetForegroundColor};
 
@@ -46,11 +183,42 @@ pub mod live_configuration {
         }
     }
 
+    /// Lines taken by the controls help above the field
+    const HEADER_ROWS: usize = 8;
+    /// Indentation of the field
+    const MARGIN_COLS: usize = 2;
+
+    /// Where the editor takes events from
+    trait EventSource {
+        fn read(&mut self) -> Result<Event>;
+    }
+
+    struct TerminalEvents;
+
+    impl EventSource for TerminalEvents {
+        fn read(&mut self) -> Result<Event> {
+            event::read()
+        }
+    }
+
+    enum LoopResult {
+        Terminate,
+        Proceed,
+    }
+
     pub struct State {
         rows: Bounded<1, { usize::MAX }>,
         cols: Bounded<1, { usize::MAX }>,
         cursor: (Bounded<0, { usize::MAX }>, Bounded<0, { usize::MAX }>),
         unavailable: HashSet<Pos>,
+        /// Cells excluded from the field, only kept from the configuration being edited
+        outside: HashSet<Pos>,
+        /// Size of the terminal, if known
+        terminal: Option<Size>,
+        /// How many terminal columns a cell takes
+        cell_width: usize,
+        /// Whether to label rows and columns with their indices
+        rulers: bool,
     }
 
     impl State {
@@ -60,25 +228,81 @@ pub mod live_configuration {
                 cols: Bounded(cols),
                 cursor: (Bounded(0), Bounded(0)),
                 unavailable: HashSet::new(),
+                outside: HashSet::new(),
+                terminal: None,
+                cell_width: 1,
+                rulers: false,
             }
         }
 
-        pub fn live(mut self) -> Result<Self> {
-            enum LoopResult {
-                Terminate,
-                Proceed,
+        /// Starts editing the field of an existing configuration, e.g. to change it between
+        /// searches
+        pub fn from_configuration(conf: &Configuration) -> Self {
+            let mut state = Self::new(conf.size.rows, conf.size.cols);
+            state.unavailable = conf.unavailable.clone();
+            state.outside = conf.outside.clone();
+            state
+        }
+
+        /// See [`super::RenderConfig::cell_width`]
+        pub fn with_cell_width(mut self, width: usize) -> Self {
+            self.cell_width = width.max(1);
+            self
+        }
+
+        /// Labels columns with their indices above the field and rows to the left of it
+        pub fn with_rulers(mut self, value: bool) -> Self {
+            self.rulers = value;
+            self
+        }
+
+        /// Terminal columns taken by the row indices, if rulers are shown
+        fn ruler_width(&self) -> usize {
+            if self.rulers {
+                ruler_width(self.rows.0)
+            } else {
+                0
             }
+        }
 
+        pub fn live(mut self) -> Result<Self> {
             stdout().execute(EnterAlternateScreen)?;
             terminal::enable_raw_mode()?;
 
-            self.print()?;
+            let (cols, rows) = terminal::size()?;
+            self.terminal = Some(Size::new(rows.into(), cols.into()));
+
+            let loop_result = self.run(&mut TerminalEvents, |state| {
+                stdout().execute(Clear(ClearType::All))?;
+                state.print()
+            })?;
+
+            terminal::disable_raw_mode()?;
+            stdout().execute(LeaveAlternateScreen)?;
+
+            match loop_result {
+                LoopResult::Terminate => {
+                    std::process::exit(1);
+                }
+                LoopResult::Proceed => Ok(self),
+            }
+        }
+
+        /// Handles events until the editing is finished, redrawing after each change
+        fn run(
+            &mut self,
+            events: &mut impl EventSource,
+            mut redraw: impl FnMut(&Self) -> Result<()>,
+        ) -> Result<LoopResult> {
+            redraw(self)?;
 
-            let loop_result = loop {
-                if let Event::Key(event::KeyEvent { code, .. }) = event::read()? {
-                    match code {
-                        event::KeyCode::Esc => break LoopResult::Terminate,
-                        event::KeyCode::Enter => break LoopResult::Proceed,
+            loop {
+                match events.read()? {
+                    Event::Key(event::KeyEvent {
+                        code, modifiers, ..
+                    }) => match code {
+                        event::KeyCode::Esc => return Ok(LoopResult::Terminate),
+                        event::KeyCode::Enter => return Ok(LoopResult::Proceed),
                         event::KeyCode::Char('w') => self.rows.dec(),
                         event::KeyCode::Char('s') => self.rows.inc(),
                         event::KeyCode::Char('a') => self.cols.dec(),
@@ -87,30 +311,74 @@ pub mod live_configuration {
                         event::KeyCode::Right => self.cursor.1.inc(),
                         event::KeyCode::Up => self.cursor.0.dec(),
                         event::KeyCode::Down => self.cursor.0.inc(),
+                        // few terminals tell Shift+Space apart from Space, hence `r`
+                        event::KeyCode::Char(' ')
+                            if modifiers.contains(event::KeyModifiers::SHIFT) =>
+                        {
+                            self.toggle_row()
+                        }
+                        event::KeyCode::Char('r') => self.toggle_row(),
+                        event::KeyCode::Char('c') => self.toggle_column(),
                         event::KeyCode::Char(' ') => self.toggle_under_cursor(),
                         _ => {}
+                    },
+                    Event::Resize(cols, rows) => {
+                        self.terminal = Some(Size::new(rows.into(), cols.into()));
                     }
+                    _ => continue,
                 }
 
-                stdout().execute(Clear(ClearType::All))?;
-
                 self.align_cursor();
-                self.print()?;
-            };
-
-            terminal::disable_raw_mode()?;
-            stdout().execute(LeaveAlternateScreen)?;
+                redraw(self)?;
+            }
+        }
 
-            match loop_result {
-                LoopResult::Terminate => {
-                    std::process::exit(1);
+        /// Rows and columns of the field which fit into the terminal. If the field doesn't fit,
+        /// the visible part follows the cursor.
+        fn visible_window(&self) -> (Range<usize>, Range<usize>) {
+            fn visible(len: usize, cursor: usize, available: usize) -> Range<usize> {
+                let available = available.max(1);
+                if len <= available {
+                    return 0..len;
                 }
-                LoopResult::Proceed => Ok(self),
+                let start = cursor.saturating_sub(available / 2).min(len - available);
+                start..start + available
+            }
+
+            let size = self.as_size();
+            let cursor = self.cursor_as_pos();
+            let ruler_rows = usize::from(self.rulers);
+            match self.terminal {
+                None => (0..size.rows, 0..size.cols),
+                Some(terminal) => (
+                    visible(
+                        size.rows,
+                        cursor.row,
+                        terminal.rows.saturating_sub(HEADER_ROWS + ruler_rows),
+                    ),
+                    visible(
+                        size.cols,
+                        cursor.col,
+                        terminal
+                            .cols
+                            .saturating_sub(MARGIN_COLS + self.ruler_width())
+                            / self.cell_width,
+                    ),
+                ),
             }
         }
 
         pub fn into_configuration(self) -> Configuration {
-            Configuration::new((self.rows.0, self.cols.0).into(), self.unavailable)
+            // the field could have been shrunk after the cells were marked
+            let size = self.as_size();
+            let in_field = |cells: HashSet<Pos>| {
+                cells
+                    .into_iter()
+                    .filter(|pos| pos.in_bounds(&size))
+                    .collect()
+            };
+            Configuration::new(size, in_field(self.unavailable))
+                .with_outside(in_field(self.outside))
         }
 
         fn cursor_as_pos(&self) -> Pos {
@@ -167,6 +435,22 @@ pub mod live_configuration {
                 .execute(Print(" - toggle the cell"))?
                 .execute(cursor::MoveToNextLine(1))?
                 .execute(cursor::MoveRight(2))?
+                .execute(SetForegroundColor(Color::Blue))?
+                .execute(Print("Shift+Space"))?
+                .execute(SetForegroundColor(Color::Grey))?
+                .execute(SetAttribute(Attribute::Dim))?
+                .execute(Print(" / "))?
+                .execute(SetAttribute(Attribute::Reset))?
+                .execute(SetForegroundColor(Color::Blue))?
+                .execute(Print("R"))?
+                .execute(SetForegroundColor(Color::Reset))?
+                .execute(Print(" - toggle the row, "))?
+                .execute(SetForegroundColor(Color::Blue))?
+                .execute(Print("C"))?
+                .execute(SetForegroundColor(Color::Reset))?
+                .execute(Print(" - the column"))?
+                .execute(cursor::MoveToNextLine(1))?
+                .execute(cursor::MoveRight(2))?
                 .execute(SetForegroundColor(Color::Yellow))?
                 .execute(Print("Esc"))?
                 .execute(SetForegroundColor(Color::Reset))?
@@ -192,9 +476,20 @@ pub mod live_configuration {
             //     .execute(cursor::MoveToNextLine(2))?;
 
             print_field_setup(
-                self.as_size(),
-                &self.unavailable,
+                &mut stdout(),
+                self.visible_window(),
+                |pos| {
+                    if self.outside.contains(pos) {
+                        CellKind::Outside
+                    } else if self.unavailable.contains(pos) {
+                        CellKind::Blocked
+                    } else {
+                        CellKind::Playable
+                    }
+                },
                 Some(self.cursor_as_pos()),
+                self.cell_width,
+                self.ruler_width(),
                 &RawMode::Enabled,
             )?;
 
@@ -208,20 +503,296 @@ pub mod live_configuration {
 
         fn toggle_under_cursor(&mut self) {
             let entry = self.cursor_as_pos();
+            if self.outside.remove(&entry) {
+                return;
+            }
             if self.unavailable.contains(&entry) {
                 self.unavailable.remove(&entry);
             } else {
                 self.unavailable.insert(entry);
             };
         }
+
+        /// Blocks the cells of the cursor's row, or frees them if all of them are blocked
+        fn toggle_row(&mut self) {
+            let row = self.cursor.0 .0;
+            self.toggle_line((0..self.cols.0).map(|col| Pos::new(row, col)));
+        }
+
+        /// Like [`Self::toggle_row`], but for the cursor's column
+        fn toggle_column(&mut self) {
+            let col = self.cursor.1 .0;
+            self.toggle_line((0..self.rows.0).map(|row| Pos::new(row, col)));
+        }
+
+        /// Cells outside of the field stay outside
+        fn toggle_line(&mut self, line: impl Iterator<Item = Pos>) {
+            let cells: Vec<_> = line.filter(|pos| !self.outside.contains(pos)).collect();
+            if cells.iter().all(|pos| self.unavailable.contains(pos)) {
+                for pos in &cells {
+                    self.unavailable.remove(pos);
+                }
+            } else {
+                self.unavailable.extend(cells);
+            }
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+        use std::collections::VecDeque;
+
+        struct Scripted(VecDeque<Event>);
+
+        impl EventSource for Scripted {
+            fn read(&mut self) -> Result<Event> {
+                Ok(self.0.pop_front().expect("script is over"))
+            }
+        }
+
+        fn key(code: KeyCode) -> Event {
+            Event::Key(KeyEvent::new(code, KeyModifiers::NONE))
+        }
+
+        #[test]
+        fn redraws_on_resize() {
+            let mut state = State::new(6, 20);
+            let mut events = Scripted(
+                [
+                    Event::FocusGained,
+                    Event::Resize(10, HEADER_ROWS as u16 + 2),
+                    key(KeyCode::Enter),
+                ]
+                .into(),
+            );
+            let mut windows = Vec::new();
+
+            let result = state
+                .run(&mut events, |state| {
+                    windows.push(state.visible_window());
+                    Ok(())
+                })
+                .unwrap();
+
+            assert!(matches!(result, LoopResult::Proceed));
+            // the initial draw and the one after resize
+            assert_eq!(windows, [(0..6, 0..20), (0..2, 0..8)]);
+        }
+
+        #[test]
+        fn toggles_whole_row_and_column() {
+            let mut state = State::new(3, 4);
+            state.cursor = (Bounded(1), Bounded(2));
+            state.unavailable.insert(Pos::new(1, 0));
+
+            state.toggle_row();
+            let row: HashSet<_> = (0..4).map(|col| Pos::new(1, col)).collect();
+            assert_eq!(state.unavailable, row);
+
+            state.toggle_row();
+            assert!(state.unavailable.is_empty());
+
+            let mut events = Scripted([key(KeyCode::Char('c')), key(KeyCode::Enter)].into());
+            state.run(&mut events, |_| Ok(())).unwrap();
+            let column: HashSet<_> = (0..3).map(|row| Pos::new(row, 2)).collect();
+            assert_eq!(state.unavailable, column);
+        }
+
+        #[test]
+        fn ruler_fits_the_last_row_index() {
+            assert_eq!(ruler_width(1), 2);
+            assert_eq!(ruler_width(10), 2);
+            assert_eq!(ruler_width(11), 3);
+            assert_eq!(ruler_width(101), 4);
+
+            let state = State::new(12, 4);
+            assert_eq!(state.ruler_width(), 0);
+            assert_eq!(state.with_rulers(true).ruler_width(), 3);
+        }
+
+        #[test]
+        fn rulers_narrow_visible_window() {
+            let mut state = State::new(12, 20).with_rulers(true);
+            state.terminal = Some(Size::new(HEADER_ROWS + 3, 13));
+
+            // a row of column indices above, `11 ` to the left of rows
+            assert_eq!(state.visible_window(), (0..2, 0..8));
+        }
+
+        #[test]
+        fn visible_window_follows_cursor() {
+            let mut state = State::new(6, 20);
+            state.terminal = Some(Size::new(HEADER_ROWS + 2, 10));
+            state.cursor = (Bounded(5), Bounded(19));
+
+            assert_eq!(state.visible_window(), (4..6, 12..20));
+
+            state.cell_width = 2;
+            assert_eq!(state.visible_window(), (4..6, 16..20));
+        }
+    }
+}
+
+/// What to do once the results of an interactive run are shown
+pub mod post_solve {
+    use std::io::{self, BufRead, Write};
+
+    use super::Configuration;
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum Action {
+        /// Search the same field again, with tetras shuffled anew
+        NextSeed,
+        /// Search again, recording placements which leave one more cell free
+        Loosen,
+        /// Change the field in the editor and search it
+        Edit,
+        Quit,
+    }
+
+    const PROMPT: &str = "  [n]ext seed, [l]oosen the threshold, [e]dit the field, [q]uit: ";
+
+    impl Action {
+        fn parse(answer: &str) -> Option<Self> {
+            match answer.trim().to_lowercase().as_str() {
+                "n" | "next" => Some(Self::NextSeed),
+                "l" | "loosen" => Some(Self::Loosen),
+                "e" | "edit" => Some(Self::Edit),
+                "q" | "quit" => Some(Self::Quit),
+                _ => None,
+            }
+        }
+    }
+
+    /// Prompts until a valid answer is given. The end of the input means quitting
+    pub fn ask(input: &mut impl BufRead, out: &mut impl Write) -> io::Result<Action> {
+        loop {
+            write!(out, "\n{PROMPT}")?;
+            out.flush()?;
+
+            let mut answer = String::new();
+            if input.read_line(&mut answer)? == 0 {
+                return Ok(Action::Quit);
+            }
+            if let Some(action) = Action::parse(&answer) {
+                return Ok(action);
+            }
+        }
+    }
+
+    /// Prepares the configuration for the next search, or returns `None` if there should be
+    /// none. Editing is left to the caller, since it needs the terminal
+    pub fn dispatch<E>(
+        action: Action,
+        mut conf: Configuration,
+        edit: impl FnOnce(Configuration) -> Result<Configuration, E>,
+    ) -> Result<Option<Configuration>, E> {
+        // the checkpoint belongs to the first search only
+        conf.resume = None;
+
+        Ok(match action {
+            Action::NextSeed => Some(conf),
+            Action::Loosen => {
+                let slack = conf.free_cells_slack + 1;
+                Some(conf.with_free_cells_slack(slack))
+            }
+            Action::Edit => Some(edit(conf)?),
+            Action::Quit => None,
+        })
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::util::{Pos, Size};
+        use std::collections::HashSet;
+
+        fn conf() -> Configuration {
+            Configuration::new(Size::new(2, 4), HashSet::new())
+        }
+
+        fn no_edit(_: Configuration) -> Result<Configuration, ()> {
+            panic!("only the edit action should open the editor")
+        }
+
+        #[test]
+        fn asks_until_answered() {
+            let mut out = Vec::new();
+
+            let action = ask(&mut "what\n\n L \n".as_bytes(), &mut out).unwrap();
+
+            assert_eq!(action, Action::Loosen);
+            assert_eq!(String::from_utf8(out).unwrap().matches(PROMPT).count(), 3);
+            assert_eq!(
+                ask(&mut "".as_bytes(), &mut Vec::new()).unwrap(),
+                Action::Quit
+            );
+        }
+
+        #[test]
+        fn next_seed_searches_the_same_field() {
+            let next = dispatch(Action::NextSeed, conf(), no_edit)
+                .unwrap()
+                .unwrap();
+
+            assert_eq!(next.size, Size::new(2, 4));
+            assert_eq!(next.free_cells_slack, 0);
+            assert!(next.resume.is_none());
+        }
+
+        #[test]
+        fn loosen_accumulates() {
+            let once = dispatch(Action::Loosen, conf(), no_edit).unwrap().unwrap();
+            let twice = dispatch(Action::Loosen, once, no_edit).unwrap().unwrap();
+
+            assert_eq!(twice.free_cells_slack, 2);
+        }
+
+        #[test]
+        fn edit_searches_the_edited_field() {
+            let next = dispatch(Action::Edit, conf(), |conf| {
+                Ok::<_, ()>(Configuration::new(
+                    conf.size,
+                    [Pos::new(0, 0)].into_iter().collect(),
+                ))
+            })
+            .unwrap()
+            .unwrap();
+
+            assert!(next.unavailable.contains(&Pos::new(0, 0)));
+            assert_eq!(
+                dispatch(Action::Edit, conf(), |_| Err("cancelled")).err(),
+                Some("cancelled")
+            );
+        }
+
+        #[test]
+        fn quit_stops() {
+            assert!(dispatch(Action::Quit, conf(), no_edit).unwrap().is_none());
+        }
     }
 }
 
 impl Configuration {
-    pub fn print_field(&self) -> Result<()> {
-        stdout().execute(Print("Field:\n\n"))?;
-        print_field_setup(self.size, &self.unavailable, None, &RawMode::Disabled)?;
-        stdout().execute(Print("\n"))?;
+    pub fn print_field(&self, render: &RenderConfig) -> Result<()> {
+        self.write_field(&mut stdout(), render)
+    }
+
+    /// Like [`Configuration::print_field`], but into any writer, e.g. to capture the output
+    pub fn write_field(&self, out: &mut impl io::Write, render: &RenderConfig) -> Result<()> {
+        out.execute(Print("Field:\n\n"))?;
+        print_field_setup(
+            out,
+            (0..self.size.rows, 0..self.size.cols),
+            |pos| self.cell_kind(pos),
+            None,
+            render.cell_width,
+            0,
+            &RawMode::Disabled,
+        )?;
+        out.execute(Print("\n"))?;
         Ok(())
     }
 }
@@ -231,24 +802,72 @@ enum RawMode {
     Disabled,
 }
 
-fn print_field_setup(
-    size: Size,
-    unavailable: &HashSet<Pos>,
+/// Terminal columns taken by indices of `rows` rows, including the space after them
+fn ruler_width(rows: usize) -> usize {
+    rows.saturating_sub(1).to_string().len() + 1
+}
+
+/// Prints only the given rows and columns of the field. With a non-zero `ruler_width`, rows and
+/// columns are labelled with their indices
+fn print_field_setup<W: io::Write>(
+    out: &mut W,
+    (rows, cols): (Range<usize>, Range<usize>),
+    cell_kind: impl Fn(&Pos) -> CellKind,
     cursor: Option<Pos>,
+    cell_width: usize,
+    ruler_width: usize,
     raw_mode: &RawMode,
 ) -> Result<()> {
-    for row in 0..size.rows {
+    let margin = |out: &mut W, ruler: String| {
         match raw_mode {
-            RawMode::Enabled => execute!(stdout(), cursor::MoveRight(2))?,
-            RawMode::Disabled => execute!(stdout(), Print("  "))?,
+            RawMode::Enabled => execute!(out, cursor::MoveRight(2))?,
+            RawMode::Disabled => execute!(out, Print("  "))?,
+        }
+        if ruler.is_empty() {
+            return Ok(());
         }
+        execute!(
+            out,
+            SetForegroundColor(Color::DarkGrey),
+            Print(ruler),
+            ResetColor
+        )
+    };
+    let new_line = |out: &mut W| match raw_mode {
+        RawMode::Enabled => execute!(out, cursor::MoveToNextLine(1)),
+        RawMode::Disabled => execute!(out, Print("\n")),
+    };
 
-        for col in 0..size.cols {
-            let under_cursor = cursor.map_or(false, |pos| (row, col) == (pos.row, pos.col));
+    if ruler_width > 0 {
+        margin(out, " ".repeat(ruler_width))?;
+        for col in cols.clone() {
+            // only the lowest digits of wide indices fit into a cell
+            let index = col.to_string();
+            let index = &index[index.len().saturating_sub(cell_width)..];
+            execute!(
+                out,
+                SetForegroundColor(Color::DarkGrey),
+                Print(format!("{index:<cell_width$}")),
+                ResetColor
+            )?;
+        }
+        new_line(out)?;
+    }
 
-            if unavailable.contains(&Pos::new(row, col)) {
-                execute!(
-                    stdout(),
+    for row in rows {
+        if ruler_width > 0 {
+            let width = ruler_width - 1;
+            margin(out, format!("{row:>width$} "))?;
+        } else {
+            margin(out, String::new())?;
+        }
+
+        for col in cols.clone() {
+            let under_cursor = cursor.is_some_and(|pos| (row, col) == (pos.row, pos.col));
+
+            match cell_kind(&Pos::new(row, col)) {
+                CellKind::Blocked => execute!(
+                    out,
                     SetBackgroundColor(if under_cursor {
                         Color::DarkRed
                     } else {
@@ -256,37 +875,39 @@ fn print_field_setup(
                     }),
                     SetAttribute(Attribute::Bold),
                     SetForegroundColor(Color::DarkRed),
-                    Print(CHAR_UNAVAILABLE),
+                    Print(pad_cell(CHAR_UNAVAILABLE, cell_width)),
                     ResetColor
-                )?;
-            } else {
-                execute!(
-                    stdout(),
+                )?,
+                CellKind::Playable => execute!(
+                    out,
                     SetBackgroundColor(if under_cursor {
                         Color::DarkGrey
                     } else {
                         Color::Reset
                     }),
-                    Print(CHAR_EMPTY),
+                    Print(pad_cell(CHAR_EMPTY, cell_width)),
                     ResetColor
-                )?;
-            };
+                )?,
+                CellKind::Outside => execute!(out, Print(pad_cell(CHAR_OUTSIDE, cell_width)))?,
+            }
         }
 
-        match raw_mode {
-            RawMode::Enabled => execute!(stdout(), cursor::MoveToNextLine(1))?,
-            RawMode::Disabled => execute!(stdout(), Print("\n"))?,
-        }
+        new_line(out)?;
     }
 
     Ok(())
 }
 
+fn pad_cell(char: char, width: usize) -> String {
+    format!("{char:<width$}")
+}
+
 #[derive(Clone)]
 enum CellView {
     Tetra(TetraView),
     Empty,
     Unavailable,
+    Outside,
 }
 
 #[derive(Clone)]
@@ -308,7 +929,9 @@ impl Command for OptionAttribute {
     }
 }
 
-fn compose_tetra_views(result: &PlacementResult) -> HashMap<&PlacedBoundariesChecked, TetraView> {
+/// Views of the tetras in the order of [`PlacementResult::placement`], i.e. by the indices of
+/// [`PlacementResult::to_grid`], so that equal results always look the same
+fn compose_tetra_views(result: &PlacementResult, render: &RenderConfig) -> Vec<TetraView> {
     const COLORS: [Color; 5] = [
         Color::Green,
         Color::Cyan,
@@ -320,80 +943,788 @@ fn compose_tetra_views(result: &PlacementResult) -> HashMap<&PlacedBoundariesChe
     const ATTRIBUTES: [Option<Attribute>; 3] =
         [None, Some(Attribute::Bold), Some(Attribute::Italic)];
 
-    let map: HashMap<_, _> = result
+    let fill_order = render.fill_order.then(|| result.fill_order());
+    result
         .placement
         .iter()
         .enumerate()
         .map(|(idx, tetra)| {
-            let sym = char::from_u32(('A' as usize + idx) as u32).unwrap();
-
-            let relative = idx % (COLORS.len() * ATTRIBUTES.len());
-            let idx_color = relative % COLORS.len();
-            let idx_attr = relative / COLORS.len();
+            let idx = match &fill_order {
+                Some(order) => order
+                    .iter()
+                    .position(|x| *x == tetra)
+                    .expect("All tetras are in the fill order"),
+                None => idx,
+            };
+            let sym = render.labels[idx % render.labels.len()];
 
-            let view = TetraView {
-                char: sym,
-                color: COLORS[idx_color],
-                attr: OptionAttribute(ATTRIBUTES[idx_attr]),
+            let style_idx = if render.color_by_type {
+                TETRAS
+                    .iter()
+                    .position(|x| x == tetra.tetra)
+                    .expect("All tetras are from the list")
+            } else {
+                idx
             };
+            if render.truecolor {
+                TetraView {
+                    char: sym,
+                    color: truecolor_for(style_idx),
+                    attr: OptionAttribute(None),
+                }
+            } else {
+                let relative = style_idx % (COLORS.len() * ATTRIBUTES.len());
+                let idx_color = relative % COLORS.len();
+                let idx_attr = relative / COLORS.len();
 
-            (tetra, view)
+                TetraView {
+                    char: sym,
+                    color: COLORS[idx_color],
+                    attr: OptionAttribute(ATTRIBUTES[idx_attr]),
+                }
+            }
         })
-        .collect();
-
-    map
+        .collect()
 }
 
-fn grid_view(result: &PlacementResult, conf: &Configuration) -> Grid<CellView> {
+fn grid_view(
+    result: &PlacementResult,
+    conf: &Configuration,
+    render: &RenderConfig,
+) -> Grid<CellView> {
     let mut grid = Grid::init(conf.size.rows, conf.size.cols, CellView::Empty);
 
     for Pos { row, col } in &conf.unavailable {
         grid[*row][*col] = CellView::Unavailable;
     }
+    for Pos { row, col } in &conf.outside {
+        grid[*row][*col] = CellView::Outside;
+    }
 
-    for (tetra, view) in compose_tetra_views(result) {
-        for Pos { row, col } in tetra.iter_relative_to_place() {
-            grid[row][col] = CellView::Tetra(view.clone());
+    let views = compose_tetra_views(result, render);
+    let occupied = result.to_grid(conf);
+    for (view, idx) in grid.iter_mut().zip(occupied.iter()) {
+        if let Some(idx) = idx {
+            *view = CellView::Tetra(views[*idx].clone());
         }
     }
 
     grid
 }
 
-pub fn report_placement(result: &PlacementResult, conf: &Configuration) -> Result<()> {
-    let grid = grid_view(result, conf);
+/// Edges between cells of different tetras, and between tetras and the rest of the field
+#[derive(Debug, PartialEq)]
+struct Outline {
+    /// `rows + 1` lines of `cols` edges: above each row, and below the last one
+    horizontal: Grid<bool>,
+    /// `rows` lines of `cols + 1` edges: left of each column, and right of the last one
+    vertical: Grid<bool>,
+}
+
+impl Outline {
+    /// Takes which tetra occupies each cell, see [`PlacementResult::to_grid`]
+    fn new(owners: &Grid<Option<usize>>) -> Self {
+        let (rows, cols) = (owners.rows(), owners.cols());
+        let owner = |row: usize, col: usize| owners.get(row, col).copied().flatten();
+
+        let mut horizontal = Grid::init(rows + 1, cols, false);
+        for row in 0..=rows {
+            for col in 0..cols {
+                let above = row.checked_sub(1).and_then(|row| owner(row, col));
+                horizontal[row][col] = above != owner(row, col);
+            }
+        }
+        let mut vertical = Grid::init(rows, cols + 1, false);
+        for row in 0..rows {
+            for col in 0..=cols {
+                let left = col.checked_sub(1).and_then(|col| owner(row, col));
+                vertical[row][col] = left != owner(row, col);
+            }
+        }
+
+        Self {
+            horizontal,
+            vertical,
+        }
+    }
+
+    /// Box-drawing character joining the edges which meet at the top left corner of the cell
+    fn corner(&self, row: usize, col: usize) -> char {
+        let up = row > 0 && self.vertical[row - 1][col];
+        let down = row < self.vertical.rows() && self.vertical[row][col];
+        let left = col > 0 && self.horizontal[row][col - 1];
+        let right = col < self.horizontal.cols() && self.horizontal[row][col];
+
+        match (up, down, left, right) {
+            (false, false, false, false) => ' ',
+            (true, true, false, false) => '│',
+            (false, false, true, true) => '─',
+            (false, true, false, true) => '┌',
+            (false, true, true, false) => '┐',
+            (true, false, false, true) => '└',
+            (true, false, true, false) => '┘',
+            (true, true, false, true) => '├',
+            (true, true, true, false) => '┤',
+            (false, true, true, true) => '┬',
+            (true, false, true, true) => '┴',
+            (true, true, true, true) => '┼',
+            (true, false, false, false) => '╵',
+            (false, true, false, false) => '╷',
+            (false, false, true, false) => '╴',
+            (false, false, false, true) => '╶',
+        }
+    }
+}
+
+impl PlacementResult {
+    /// Draws the borders of tetras with box-drawing characters, like pieces of a jigsaw. Takes
+    /// two lines per row of the field: one of horizontal edges, one of cells
+    pub fn to_outline(&self, conf: &Configuration, render: &RenderConfig) -> String {
+        let owners = self.to_grid(conf);
+        let outline = Outline::new(&owners);
+        let width = render.cell_width;
+
+        let mut output = String::new();
+        for row in 0..=conf.size.rows {
+            for col in 0..=conf.size.cols {
+                output.push(outline.corner(row, col));
+                if col < conf.size.cols {
+                    let edge = if outline.horizontal[row][col] {
+                        '─'
+                    } else {
+                        ' '
+                    };
+                    output.extend(std::iter::repeat_n(edge, width));
+                }
+            }
+            output.push('\n');
+
+            if row == conf.size.rows {
+                break;
+            }
+            for col in 0..=conf.size.cols {
+                output.push(if outline.vertical[row][col] {
+                    '│'
+                } else {
+                    ' '
+                });
+                if col < conf.size.cols {
+                    let char = match conf.cell_kind(&Pos::new(row, col)) {
+                        _ if owners[row][col].is_some() => ' ',
+                        CellKind::Playable => CHAR_EMPTY,
+                        CellKind::Blocked => CHAR_UNAVAILABLE,
+                        CellKind::Outside => CHAR_OUTSIDE,
+                    };
+                    output += &pad_cell(char, width);
+                }
+            }
+            output.push('\n');
+        }
+        output
+    }
+
+    /// Plain text version of [`report_placement`] with default labels: one line per row, `.` for
+    /// empty cells, `x` for unavailable ones and space for cells outside of the field
+    pub fn to_labeled_grid(&self, conf: &Configuration) -> String {
+        plain_text(&grid_view(self, conf, &RenderConfig::default()))
+    }
+
+    /// Like [`PlacementResult::to_labeled_grid`], but each tetra is labelled with its index in
+    /// [`PlacementResult::fill_order`], see [`FILL_ORDER_LABELS`]
+    pub fn to_fill_order_grid(&self, conf: &Configuration) -> String {
+        let render = RenderConfig::default()
+            .with_labels(FILL_ORDER_LABELS.chars().collect())
+            .expect("Labels are not empty")
+            .fill_order(true);
+        plain_text(&grid_view(self, conf, &render))
+    }
 
+    /// Shows the placement as [`PlacementResult::to_labeled_grid`] does, e.g. to format it into
+    /// a log message or an assertion. No terminal is involved, and nothing has to be imported
+    /// from this module.
+    ///
+    /// ```
+    /// use brutal_tetris_hacker::algorithm::{Configuration, NoopStats, SearchMode};
+    ///
+    /// let conf = Configuration::from_board_str("----\n----\nxxxx")
+    ///     .unwrap()
+    ///     .with_mode(SearchMode::Exhaustive);
+    /// let results = conf.run(&mut NoopStats);
+    ///
+    /// let shown: Vec<_> = results.iter().map(|result| result.display(&conf).to_string()).collect();
+    /// assert!(shown.contains(&"AAAA\nBBBB\nxxxx\n".to_owned()));
+    /// ```
+    pub fn display<'a>(&'a self, conf: &'a Configuration) -> DisplayPlacement<'a> {
+        DisplayPlacement { result: self, conf }
+    }
+}
+
+/// See [`PlacementResult::display`]
+pub struct DisplayPlacement<'a> {
+    result: &'a PlacementResult,
+    conf: &'a Configuration,
+}
+
+impl std::fmt::Display for DisplayPlacement<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.result.to_labeled_grid(self.conf))
+    }
+}
+
+/// One line per row, `.` for empty cells, `x` for unavailable ones and space for cells outside
+/// of the field
+fn plain_text(grid: &Grid<CellView>) -> String {
+    let mut output = String::with_capacity(grid.rows() * (grid.cols() + 1));
+    for row in 0..grid.rows() {
+        output.extend(grid.iter_row(row).map(|view| match view {
+            CellView::Tetra(TetraView { char, .. }) => *char,
+            CellView::Empty => '.',
+            CellView::Unavailable => 'x',
+            CellView::Outside => ' ',
+        }));
+        output.push('\n');
+    }
+    output
+}
+
+pub fn report_placement(
+    result: &PlacementResult,
+    conf: &Configuration,
+    render: &RenderConfig,
+) -> Result<()> {
+    write_placement(&mut stdout(), result, conf, render)
+}
+
+/// Like [`report_placement`], but into any writer, e.g. to capture the output
+pub fn write_placement(
+    out: &mut impl std::io::Write,
+    result: &PlacementResult,
+    conf: &Configuration,
+    render: &RenderConfig,
+) -> Result<()> {
+    for line in placement_lines(result, conf, render)? {
+        if line.is_empty() {
+            out.execute(Print("\n"))?;
+        } else {
+            out.execute(Print(format!("  {line}\n")))?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Rows written by [`write_placement`], without the indentation. Each row takes
+/// [`RenderConfig::cell_width`] columns of the terminal per cell, not counting the escape codes.
+/// Rows are separated by [`RenderConfig::row_spacing`] empty lines
+pub fn placement_lines(
+    result: &PlacementResult,
+    conf: &Configuration,
+    render: &RenderConfig,
+) -> Result<Vec<String>> {
+    let grid = grid_view(result, conf, render);
+    let cell = |char: char| pad_cell(char, render.cell_width);
+
+    let mut lines = Vec::with_capacity(placement_height(conf, render));
     for row in 0..grid.rows() {
-        stdout().execute(Print("  "))?;
+        if row > 0 {
+            lines.extend(std::iter::repeat_n(String::new(), render.row_spacing));
+        }
+        let mut out = Vec::new();
         for view in grid.iter_row(row) {
             match view {
                 CellView::Empty => execute!(
-                    stdout(),
+                    out,
                     SetForegroundColor(Color::Grey),
                     SetAttribute(Attribute::Dim),
-                    Print(CHAR_EMPTY),
+                    Print(cell(CHAR_EMPTY)),
                     ResetColor,
                 )?,
                 CellView::Unavailable => execute!(
-                    stdout(),
+                    out,
                     SetForegroundColor(Color::DarkRed),
                     // SetBackgroundColor(Color::DarkRed),
-                    Print(CHAR_UNAVAILABLE),
+                    Print(cell(CHAR_UNAVAILABLE)),
                     ResetColor
                 )?,
+                CellView::Outside => execute!(out, Print(cell(CHAR_OUTSIDE)))?,
                 CellView::Tetra(TetraView { char, color, attr }) => {
                     execute!(
-                        stdout(),
+                        out,
                         SetForegroundColor(*color),
                         attr,
-                        Print(char),
+                        Print(cell(*char)),
                         ResetColor
                     )?;
                 }
             }
         }
-        stdout().execute(Print("\n"))?;
+        lines.push(String::from_utf8(out).expect("Cells and escape codes are UTF-8"));
+    }
+
+    Ok(lines)
+}
+
+/// Columns of the terminal a line of [`placement_lines`] takes
+pub fn placement_width(conf: &Configuration, render: &RenderConfig) -> usize {
+    conf.size.cols * render.cell_width
+}
+
+/// How many lines [`placement_lines`] gives
+pub fn placement_height(conf: &Configuration, render: &RenderConfig) -> usize {
+    conf.size.rows + conf.size.rows.saturating_sub(1) * render.row_spacing
+}
+
+/// Lays rendered placements out side by side, `columns` of them across, each under its label.
+/// Lines of a block should take `width` columns of the terminal, like the ones of
+/// [`placement_lines`] do, since escape codes can't be measured. Rows of blocks are separated by
+/// [`RenderConfig::placement_spacing`] empty lines
+pub fn arrange_in_columns(
+    blocks: &[(String, Vec<String>)],
+    width: usize,
+    columns: NonZeroUsize,
+    render: &RenderConfig,
+) -> Vec<String> {
+    const GAP: &str = "   ";
+
+    let mut lines = Vec::new();
+    for (chunk_idx, chunk) in blocks.chunks(columns.get()).enumerate() {
+        if chunk_idx > 0 {
+            lines.extend(std::iter::repeat_n(String::new(), render.placement_spacing));
+        }
+        let column_width = chunk
+            .iter()
+            .map(|(label, _)| label.chars().count())
+            .fold(width, usize::max);
+        let line = |cells: Vec<(&str, usize)>| {
+            let last = cells.len() - 1;
+            let mut line = String::new();
+            for (idx, (text, text_width)) in cells.into_iter().enumerate() {
+                line.push_str(text);
+                if idx < last {
+                    line.extend(std::iter::repeat_n(' ', column_width - text_width));
+                    line.push_str(GAP);
+                }
+            }
+            line
+        };
+
+        lines.push(line(
+            chunk
+                .iter()
+                .map(|(label, _)| (label.as_str(), label.chars().count()))
+                .collect(),
+        ));
+        let height = chunk
+            .iter()
+            .map(|(_, block)| block.len())
+            .max()
+            .unwrap_or(0);
+        for row in 0..height {
+            lines.push(line(
+                chunk
+                    .iter()
+                    .map(|(_, block)| match block.get(row) {
+                        Some(text) => (text.as_str(), width),
+                        None => ("", 0),
+                    })
+                    .collect(),
+            ));
+        }
+    }
+    lines
+}
+
+/// Writes the rows of a field which failed to parse, see
+/// [`crate::parse_field::SourcedParseError::bad_cell`], with the bad cell highlighted
+pub fn write_bad_cell(out: &mut impl std::io::Write, rows: &[&str], bad: Pos) -> Result<()> {
+    for (row, line) in rows.iter().enumerate() {
+        out.execute(Print("  "))?;
+        for (col, char) in line.chars().enumerate() {
+            // a tab would take several columns
+            let char = if char == '\t' { ' ' } else { char };
+            if Pos::new(row, col) == bad {
+                execute!(
+                    out,
+                    SetAttribute(Attribute::Bold),
+                    SetBackgroundColor(Color::DarkRed),
+                    Print(char),
+                    ResetColor,
+                    SetAttribute(Attribute::Reset)
+                )?;
+            } else {
+                out.execute(Print(char))?;
+            }
+        }
+        out.execute(Print("\n"))?;
     }
 
     Ok(())
 }
+
+/// Prints the [`crate::algorithm::coverage_heatmap`] of `placements` results. Each playable cell
+/// shows how many tenths of them cover it, rounded up to at most 9, so `0` is only for cells
+/// which are never covered
+pub fn report_heatmap(
+    heatmap: &Grid<usize>,
+    placements: usize,
+    conf: &Configuration,
+    render: &RenderConfig,
+) -> Result<()> {
+    write_heatmap(&mut stdout(), heatmap, placements, conf, render)
+}
+
+/// Like [`report_heatmap`], but into any writer
+pub fn write_heatmap(
+    out: &mut impl std::io::Write,
+    heatmap: &Grid<usize>,
+    placements: usize,
+    conf: &Configuration,
+    render: &RenderConfig,
+) -> Result<()> {
+    // from the rarely covered cells to the always covered ones
+    const COLORS: [Color; 5] = [
+        Color::Red,
+        Color::Yellow,
+        Color::Green,
+        Color::Cyan,
+        Color::Blue,
+    ];
+
+    let cell = |char: char| pad_cell(char, render.cell_width);
+    for row in 0..conf.size.rows {
+        out.execute(Print("  "))?;
+        for col in 0..conf.size.cols {
+            let pos = Pos::new(row, col);
+            if conf.outside.contains(&pos) {
+                execute!(out, Print(cell(CHAR_OUTSIDE)))?;
+            } else if conf.unavailable.contains(&pos) {
+                execute!(
+                    out,
+                    SetForegroundColor(Color::DarkRed),
+                    Print(cell(CHAR_UNAVAILABLE)),
+                    ResetColor
+                )?;
+            } else {
+                let tenths = match placements {
+                    0 => 0,
+                    _ => (heatmap[row][col] * 10).div_ceil(placements).min(9),
+                };
+                let digit = char::from_digit(tenths as u32, 10).expect("Tenths are digits");
+                execute!(
+                    out,
+                    SetForegroundColor(COLORS[tenths * COLORS.len() / 10]),
+                    Print(cell(digit)),
+                    ResetColor
+                )?;
+            }
+        }
+        out.execute(Print("\n"))?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tetra::{Placed, PlacedBoundariesChecked, I_HORIZONTAL};
+
+    fn rows_of_i(rows: usize) -> PlacementResult {
+        PlacementResult {
+            placement: (0..rows)
+                .map(|row| {
+                    PlacedBoundariesChecked::in_boundaries(
+                        Placed::new(I_HORIZONTAL, Pos::new(row, 0)),
+                        Size::new(rows, 4),
+                    )
+                    .unwrap()
+                })
+                .collect(),
+            free: 0,
+            order: None,
+        }
+    }
+
+    /// The captured output without styling escape sequences
+    fn visible_text(out: Vec<u8>) -> String {
+        let mut text = String::new();
+        let mut in_escape = false;
+        for char in String::from_utf8(out).unwrap().chars() {
+            match char {
+                '\x1b' => in_escape = true,
+                'm' if in_escape => in_escape = false,
+                _ if in_escape => {}
+                _ => text.push(char),
+            }
+        }
+        text
+    }
+
+    #[test]
+    fn wide_cells_double_line_length() {
+        fn visible_line_lengths(render: &RenderConfig) -> Vec<usize> {
+            let result = rows_of_i(2);
+            let conf = Configuration::new(Size::new(3, 4), HashSet::new());
+            let mut out = Vec::new();
+            write_placement(&mut out, &result, &conf, render).unwrap();
+
+            visible_text(out)
+                .lines()
+                .map(|line| line.chars().count())
+                .collect()
+        }
+
+        let margin = 2;
+        let narrow = visible_line_lengths(&RenderConfig::default());
+        let wide = visible_line_lengths(&RenderConfig::default().cell_width(2));
+
+        assert_eq!(narrow, [margin + 4; 3]);
+        assert_eq!(wide, [margin + 8; 3]);
+    }
+
+    #[test]
+    fn captured_field_and_placement() {
+        let conf = Configuration::new(Size::new(3, 4), [Pos::new(2, 0)].into_iter().collect());
+        let render = RenderConfig::default();
+
+        let mut field = Vec::new();
+        conf.write_field(&mut field, &render).unwrap();
+        let mut placement = Vec::new();
+        write_placement(&mut placement, &rows_of_i(2), &conf, &render).unwrap();
+
+        assert_eq!(visible_text(field), "Field:\n\n  ····\n  ····\n  ×···\n\n");
+        assert_eq!(visible_text(placement), "  AAAA\n  BBBB\n  ×···\n");
+    }
+
+    #[test]
+    fn bad_cell_is_highlighted() {
+        let mut highlight = String::new();
+        SetBackgroundColor(Color::DarkRed)
+            .write_ansi(&mut highlight)
+            .unwrap();
+        let mut out = Vec::new();
+
+        write_bad_cell(&mut out, &["---", "-#-"], Pos::new(1, 1)).unwrap();
+
+        let raw = String::from_utf8(out.clone()).unwrap();
+        let highlighted: Vec<_> = raw.split(&highlight).skip(1).collect();
+        assert_eq!(highlighted.len(), 1);
+        assert!(highlighted[0].starts_with("#\x1b"));
+        assert_eq!(visible_text(out), "  ---\n  -#-\n");
+    }
+
+    #[test]
+    fn heatmap_shows_tenths() {
+        let conf = Configuration::new(Size::new(2, 4), [Pos::new(1, 3)].into());
+        let mut heatmap = Grid::init(2, 4, 0);
+        for (col, count) in [4, 3, 1, 0].into_iter().enumerate() {
+            heatmap[0][col] = count;
+        }
+        let mut out = Vec::new();
+
+        write_heatmap(&mut out, &heatmap, 4, &conf, &RenderConfig::default()).unwrap();
+
+        assert_eq!(visible_text(out), "  9830\n  000×\n");
+    }
+
+    #[test]
+    fn custom_labels_cycle() {
+        let result = rows_of_i(5);
+        let render = RenderConfig::default().with_labels(vec!['x', 'y']).unwrap();
+
+        let views = compose_tetra_views(&result, &render);
+
+        let labels: Vec<_> = views.iter().map(|view| view.char).collect();
+        assert_eq!(labels, ['x', 'y', 'x', 'y', 'x']);
+    }
+
+    #[test]
+    fn color_by_type_is_the_same_across_placements() {
+        let (first, second) = (rows_of_i(1), rows_of_i(3));
+        let color_of_last = |result: &PlacementResult, render: &RenderConfig| {
+            compose_tetra_views(result, render).last().unwrap().color
+        };
+
+        let by_type = RenderConfig::default().color_by_type(true);
+        assert_eq!(
+            color_of_last(&first, &by_type),
+            color_of_last(&second, &by_type)
+        );
+
+        let by_order = RenderConfig::default();
+        assert_ne!(
+            color_of_last(&first, &by_order),
+            color_of_last(&second, &by_order)
+        );
+    }
+
+    #[test]
+    fn equal_results_render_identically() {
+        let conf = Configuration::new(Size::new(4, 4), HashSet::new());
+        let render = |result: &PlacementResult| {
+            let mut out = Vec::new();
+            let render = RenderConfig::default().fill_order(true).truecolor(true);
+            write_placement(&mut out, result, &conf, &render).unwrap();
+            out
+        };
+
+        let first = render(&rows_of_i(4));
+        assert_eq!(render(&rows_of_i(4)), first);
+        assert_eq!(render(&rows_of_i(4)), first);
+    }
+
+    #[test]
+    fn truecolors_are_distinct() {
+        let colors: HashSet<_> = (0..20)
+            .map(|idx| match truecolor_for(idx) {
+                Color::Rgb { r, g, b } => (r, g, b),
+                other => panic!("expected RGB color, got {other:?}"),
+            })
+            .collect();
+
+        assert_eq!(colors.len(), 20);
+    }
+
+    #[test]
+    fn labeled_grid_snapshot() {
+        let conf = Configuration::new(Size::new(4, 4), [Pos::new(3, 0)].into_iter().collect());
+
+        assert_eq!(
+            rows_of_i(3).to_labeled_grid(&conf),
+            "AAAA\nBBBB\nCCCC\nx...\n"
+        );
+    }
+
+    #[test]
+    fn display_is_the_labeled_grid() {
+        let conf = Configuration::new(Size::new(3, 4), [Pos::new(2, 3)].into_iter().collect());
+
+        assert_eq!(
+            format!("{}", rows_of_i(2).display(&conf)),
+            "AAAA\nBBBB\n...x\n"
+        );
+    }
+
+    #[test]
+    fn outline_of_a_single_square() {
+        let conf = Configuration::new(Size::new(2, 2), HashSet::new());
+        let square = PlacementResult {
+            placement: [PlacedBoundariesChecked::in_boundaries(
+                Placed::new(&TETRAS[0], Pos::new(0, 0)),
+                conf.size,
+            )
+            .unwrap()]
+            .into(),
+            free: 0,
+            order: None,
+        };
+
+        let outline = Outline::new(&square.to_grid(&conf));
+
+        assert_eq!(
+            outline.horizontal,
+            Grid::from_vec(vec![true, true, false, false, true, true], 2)
+        );
+        assert_eq!(
+            outline.vertical,
+            Grid::from_vec(vec![true, false, true, true, false, true], 3)
+        );
+        assert_eq!(
+            square.to_outline(&conf, &RenderConfig::default()),
+            "┌───┐\n│   │\n│   │\n│   │\n└───┘\n"
+        );
+    }
+
+    #[test]
+    fn outline_separates_tetras() {
+        let conf = Configuration::new(Size::new(3, 4), [Pos::new(2, 0)].into_iter().collect());
+
+        assert_eq!(
+            rows_of_i(2).to_outline(&conf, &RenderConfig::default()),
+            [
+                "┌───────┐",
+                "│       │",
+                "├───────┤",
+                "│       │",
+                "└───────┘",
+                " × · · · ",
+                "         ",
+                "",
+            ]
+            .join("\n")
+        );
+    }
+
+    #[test]
+    fn fill_order_numbers_tetras_from_the_top() {
+        let conf = Configuration::new(Size::new(4, 4), HashSet::new());
+        let placed = |tetra, row, col| {
+            PlacedBoundariesChecked::in_boundaries(
+                Placed::new(&TETRAS[tetra], Pos::new(row, col)),
+                conf.size,
+            )
+            .unwrap()
+        };
+        // an O below an I, and an I at the bottom: in the set, Os go before Is
+        let result = PlacementResult {
+            placement: [placed(1, 0, 0), placed(0, 1, 1), placed(1, 3, 0)].into(),
+            free: 0,
+            order: None,
+        };
+
+        assert_eq!(result.to_fill_order_grid(&conf), "0000\n.11.\n.11.\n2222\n");
+        assert_ne!(
+            result.fill_order(),
+            result.placement.iter().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn empty_labels_are_rejected() {
+        assert_eq!(
+            RenderConfig::default().with_labels(vec![]).unwrap_err(),
+            RenderConfigError::EmptyLabels
+        );
+    }
+
+    #[test]
+    fn placements_are_arranged_in_columns() {
+        let conf = Configuration::new(Size::new(2, 4), HashSet::new());
+        let render = RenderConfig::default();
+        let block = placement_lines(&rows_of_i(2), &conf, &render).unwrap();
+        let blocks: Vec<_> = (1..=3)
+            .map(|number| (format!("#{number}"), block.clone()))
+            .collect();
+        let columns = NonZeroUsize::new(2).unwrap();
+
+        let lines = arrange_in_columns(&blocks, placement_width(&conf, &render), columns, &render);
+
+        // a label and 2 rows for each row of blocks, with an empty line between them
+        assert_eq!(lines.len(), 2 * 3 + 1);
+        assert_eq!(lines[0], "#1     #2");
+        assert_eq!(lines[1], format!("{}   {}", block[0], block[0]));
+        assert_eq!(lines[3], "");
+        assert_eq!(lines[4], "#3");
+        assert_eq!(lines[6], block[1]);
+    }
+
+    #[test]
+    fn spacing_adds_empty_lines() {
+        let conf = Configuration::new(Size::new(3, 4), HashSet::new());
+        let render = RenderConfig::default().row_spacing(2).placement_spacing(3);
+        let empty_lines = |lines: &[String]| lines.iter().filter(|line| line.is_empty()).count();
+
+        let block = placement_lines(&rows_of_i(3), &conf, &render).unwrap();
+        assert_eq!(block.len(), placement_height(&conf, &render));
+        assert_eq!(empty_lines(&block), 2 * 2);
+        assert!(block[0].contains('A') && block[3].contains('B') && block[6].contains('C'));
+
+        let blocks = vec![("#1".to_owned(), block.clone()), ("#2".to_owned(), block)];
+        let columns = NonZeroUsize::new(1).unwrap();
+        let lines = arrange_in_columns(&blocks, placement_width(&conf, &render), columns, &render);
+        assert_eq!(empty_lines(&lines), 2 * 2 * 2 + 3);
+
+        assert_eq!(render.placement_gap(), "\n\n\n");
+        assert_eq!(RenderConfig::default().placement_gap(), "\n");
+    }
+}
@@ -0,0 +1,208 @@
+//! Compact binary encoding of placements.
+//!
+//! The header is the magic bytes, then the field rows and columns as little-endian `u32`-s, then
+//! the tetras dictionary: the number of tetras, and for each one its 4 cells as `(row, col)` byte
+//! pairs, in the id order. Each placement follows as a bitmap with a bit per cell of the field, in
+//! row-major order starting from the lowest bit, where set bits are the top left cells of placed
+//! tetras, then the ids of those tetras, a byte each, in the same order, then the free cells count
+//! as a little-endian `u32`.
+
+use std::collections::BTreeSet;
+use std::io::{self, Write};
+
+use miette::Diagnostic;
+use thiserror::Error;
+
+use crate::algorithm::{Configuration, PlacementResult};
+use crate::tetra::{Placed, PlacedBoundariesChecked, Tetra, TETRAS};
+use crate::util::{Pos, Size};
+
+const MAGIC: &[u8; 4] = b"BTH1";
+
+#[derive(Debug, Error, PartialEq, Diagnostic)]
+pub enum DecodeError {
+    #[error("Not a placements bitmap")]
+    BadMagic,
+    #[error("Input ends in the middle of {0}")]
+    Truncated(&'static str),
+    #[error("Tetra #{0} of the dictionary is unknown")]
+    UnknownTetra(usize),
+    #[error("Tetra #{id} at {pos} doesn't fit into the field")]
+    OutOfField { id: usize, pos: Pos },
+}
+
+/// Placements read with [`decode`]
+#[derive(Debug, PartialEq)]
+pub struct Decoded {
+    pub size: Size,
+    pub results: BTreeSet<PlacementResult>,
+}
+
+/// Writes the header on creation, then placements one by one
+pub struct BitmapWriter<W> {
+    out: W,
+    size: Size,
+}
+
+impl<W: Write> BitmapWriter<W> {
+    pub fn new(mut out: W, conf: &Configuration) -> io::Result<Self> {
+        out.write_all(MAGIC)?;
+        for number in [conf.size.rows, conf.size.cols] {
+            out.write_all(&(number as u32).to_le_bytes())?;
+        }
+        out.write_all(&[TETRAS.len() as u8])?;
+        for tetra in &TETRAS {
+            for pos in tetra.iter() {
+                out.write_all(&[pos.row as u8, pos.col as u8])?;
+            }
+        }
+
+        Ok(Self {
+            out,
+            size: conf.size,
+        })
+    }
+
+    pub fn write(&mut self, result: &PlacementResult) -> io::Result<()> {
+        let cols = self.size.cols;
+        let mut bitmap = vec![0u8; bitmap_len(self.size)];
+        let mut anchored: Vec<_> = result
+            .placement
+            .iter()
+            .map(|placed| {
+                let id = TETRAS.iter().position(|x| x == placed.tetra).unwrap();
+                (placed.position, id)
+            })
+            .collect();
+        anchored.sort_unstable();
+
+        for (pos, _) in &anchored {
+            let bit = pos.row * cols + pos.col;
+            bitmap[bit / 8] |= 1 << (bit % 8);
+        }
+        self.out.write_all(&bitmap)?;
+        let ids: Vec<_> = anchored.iter().map(|(_, id)| *id as u8).collect();
+        self.out.write_all(&ids)?;
+        self.out.write_all(&(result.free as u32).to_le_bytes())
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+}
+
+fn bitmap_len(size: Size) -> usize {
+    (size.rows * size.cols).div_ceil(8)
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize, what: &'static str) -> Result<&'a [u8], DecodeError> {
+        if self.0.len() < len {
+            return Err(DecodeError::Truncated(what));
+        }
+        let (taken, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Ok(taken)
+    }
+
+    fn u32(&mut self, what: &'static str) -> Result<usize, DecodeError> {
+        let bytes = self.take(4, what)?;
+        Ok(u32::from_le_bytes(bytes.try_into().unwrap()) as usize)
+    }
+}
+
+/// Reads placements written with [`BitmapWriter`]
+pub fn decode(input: &[u8]) -> Result<Decoded, DecodeError> {
+    let mut reader = Reader(input);
+    if reader.take(MAGIC.len(), "the header")? != MAGIC {
+        return Err(DecodeError::BadMagic);
+    }
+    let size = Size::new(reader.u32("the header")?, reader.u32("the header")?);
+
+    let count = reader.take(1, "the dictionary")?[0] as usize;
+    let dictionary = (0..count)
+        .map(|id| {
+            let cells = reader.take(8, "the dictionary")?;
+            let positions: Vec<_> = cells
+                .chunks(2)
+                .map(|pair| Pos::new(pair[0].into(), pair[1].into()))
+                .collect();
+            TETRAS
+                .iter()
+                .find(|tetra| tetra.iter().eq(positions.iter()))
+                .ok_or(DecodeError::UnknownTetra(id))
+        })
+        .collect::<Result<Vec<&'static Tetra>, _>>()?;
+
+    let mut results = BTreeSet::new();
+    while !reader.0.is_empty() {
+        let bitmap = reader.take(bitmap_len(size), "a placement")?;
+        let anchors: Vec<_> = (0..size.rows * size.cols)
+            .filter(|bit| bitmap[bit / 8] & (1 << (bit % 8)) != 0)
+            .map(|bit| Pos::new(bit / size.cols, bit % size.cols))
+            .collect();
+        let ids = reader.take(anchors.len(), "a placement")?;
+
+        let placement = anchors
+            .into_iter()
+            .zip(ids)
+            .map(|(pos, &id)| {
+                let id = id as usize;
+                let tetra = dictionary.get(id).ok_or(DecodeError::UnknownTetra(id))?;
+                PlacedBoundariesChecked::in_boundaries(Placed::new(tetra, pos), size)
+                    .ok_or(DecodeError::OutOfField { id, pos })
+            })
+            .collect::<Result<BTreeSet<_>, _>>()?;
+        results.insert(PlacementResult {
+            placement,
+            free: reader.u32("a placement")?,
+            order: None,
+        });
+    }
+
+    Ok(Decoded { size, results })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    use crate::algorithm::{NoopStats, SearchMode};
+
+    #[test]
+    fn round_trip() {
+        let blocked = Configuration::from_board_str("-----\n-----\n--x--").unwrap();
+        let optional = Configuration::from_board_str("----\n----\n----")
+            .unwrap()
+            .with_optional(HashSet::from(
+                [(2, 0), (2, 1), (2, 2), (2, 3)].map(Pos::from),
+            ));
+        for conf in [blocked, optional] {
+            let conf = conf.with_mode(SearchMode::Exhaustive);
+            let results = conf.run(&mut NoopStats);
+            assert!(!results.is_empty());
+
+            let mut writer = BitmapWriter::new(Vec::new(), &conf).unwrap();
+            for result in &results {
+                writer.write(result).unwrap();
+            }
+
+            let decoded = decode(&writer.out).unwrap();
+
+            assert_eq!(decoded.size, conf.size);
+            assert_eq!(decoded.results, results);
+        }
+    }
+
+    #[test]
+    fn rejects_other_input() {
+        assert_eq!(decode(b"{}\n\n\n\n"), Err(DecodeError::BadMagic));
+        assert_eq!(
+            decode(b"BTH1\x02"),
+            Err(DecodeError::Truncated("the header"))
+        );
+    }
+}
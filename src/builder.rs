@@ -0,0 +1,426 @@
+use std::collections::{HashMap, HashSet};
+use std::num::NonZeroUsize;
+use std::time::Duration;
+
+use miette::Diagnostic;
+use thiserror::Error;
+
+use crate::algorithm::{
+    Checkpoint, Configuration, Constraint, FitPolicy, Heuristic, NoSolutionPolicy, PieceCount,
+    PlacementError, ResumeError, ScanOrder, SearchMode,
+};
+use crate::score::ScoreMode;
+use crate::tetra::{Placed, Tetra};
+use crate::util::{Pos, Size};
+
+/// Collects all the options of a [`Configuration`] and validates them at once
+#[derive(Default)]
+pub struct ConfigurationBuilder {
+    size: Option<Size>,
+    unavailable: HashSet<Pos>,
+    outside: HashSet<Pos>,
+    optional: HashSet<Pos>,
+    results_limit: Option<NonZeroUsize>,
+    mode: SearchMode,
+    prefilled: Vec<Placed>,
+    constraints: Vec<Box<dyn Constraint>>,
+    resume: Option<Checkpoint>,
+    pieces: Option<Vec<&'static Tetra>>,
+    required_pieces: HashSet<&'static Tetra>,
+    min_piece_counts: HashMap<&'static Tetra, usize>,
+    enclose_blocked: bool,
+    gravity: bool,
+    complete: bool,
+    parity_prune: bool,
+    no_solution: NoSolutionPolicy,
+    scan_order: ScanOrder,
+    fit_policy: FitPolicy,
+    heuristic: Heuristic,
+    piece_count: Option<PieceCount>,
+    max_depth: Option<usize>,
+    free_cells_slack: usize,
+    record_all_maximal: bool,
+    placement_order: bool,
+    symmetry_breaking: bool,
+    score: Option<ScoreMode>,
+    max_recursions: Option<usize>,
+    time_limit: Option<Duration>,
+    seed: Option<u64>,
+}
+
+#[derive(Debug, Error, PartialEq, Diagnostic)]
+pub enum ConfigurationError {
+    #[error("Field size is not set")]
+    MissingSize,
+    #[error("Field should have at least one row and one column")]
+    EmptyField,
+    #[error("Unavailable cell {0} is out of the field")]
+    UnavailableOutOfBoundaries(Pos),
+    #[error("Outside cell {0} is out of the field bounding box")]
+    OutsideOutOfBoundaries(Pos),
+    #[error("Optional cell {0} is out of the field")]
+    OptionalOutOfBoundaries(Pos),
+    #[error("Options `{0}` and `{1}` can't be used together")]
+    ConflictingOptions(&'static str, &'static str),
+    #[error(transparent)]
+    Placement(#[from] PlacementError),
+    #[error(transparent)]
+    Resume(#[from] ResumeError),
+}
+
+impl Configuration {
+    pub fn builder() -> ConfigurationBuilder {
+        ConfigurationBuilder::default()
+    }
+}
+
+impl ConfigurationBuilder {
+    pub fn size(mut self, size: Size) -> Self {
+        self.size = Some(size);
+        self
+    }
+
+    pub fn unavailable(mut self, unavailable: HashSet<Pos>) -> Self {
+        self.unavailable = unavailable;
+        self
+    }
+
+    /// Cells which are not part of the field, for non-rectangular fields
+    pub fn outside(mut self, outside: HashSet<Pos>) -> Self {
+        self.outside = outside;
+        self
+    }
+
+    /// Cells which tetras might cover, but don't have to
+    pub fn optional(mut self, optional: HashSet<Pos>) -> Self {
+        self.optional = optional;
+        self
+    }
+
+    pub fn results_limit(mut self, limit: NonZeroUsize) -> Self {
+        self.results_limit = Some(limit);
+        self
+    }
+
+    pub fn mode(mut self, mode: SearchMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    pub fn scan_order(mut self, order: ScanOrder) -> Self {
+        self.scan_order = order;
+        self
+    }
+
+    pub fn fit_policy(mut self, policy: FitPolicy) -> Self {
+        self.fit_policy = policy;
+        self
+    }
+
+    pub fn heuristic(mut self, heuristic: Heuristic) -> Self {
+        self.heuristic = heuristic;
+        self
+    }
+
+    pub fn exact_piece_count(mut self, count: usize) -> Self {
+        self.piece_count = Some(PieceCount::Exact(count));
+        self
+    }
+
+    pub fn max_piece_count(mut self, count: usize) -> Self {
+        self.piece_count = Some(PieceCount::Max(count));
+        self
+    }
+
+    pub fn max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = Some(depth);
+        self
+    }
+
+    pub fn max_recursions(mut self, recursions: usize) -> Self {
+        self.max_recursions = Some(recursions);
+        self
+    }
+
+    pub fn time_limit(mut self, limit: Duration) -> Self {
+        self.time_limit = Some(limit);
+        self
+    }
+
+    /// Conflicts with a seeded [`FitPolicy::Random`], see [`Configuration::with_seed`]
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    pub fn free_cells_slack(mut self, slack: usize) -> Self {
+        self.free_cells_slack = slack;
+        self
+    }
+
+    pub fn record_all_maximal(mut self, value: bool) -> Self {
+        self.record_all_maximal = value;
+        self
+    }
+
+    pub fn placement_order(mut self, value: bool) -> Self {
+        self.placement_order = value;
+        self
+    }
+
+    pub fn symmetry_breaking(mut self, value: bool) -> Self {
+        self.symmetry_breaking = value;
+        self
+    }
+
+    pub fn score(mut self, score: ScoreMode) -> Self {
+        self.score = Some(score);
+        self
+    }
+
+    pub fn prefilled(mut self, placed: Placed) -> Self {
+        self.prefilled.push(placed);
+        self
+    }
+
+    /// Can be called multiple times
+    pub fn constraint(mut self, constraint: impl Constraint + 'static) -> Self {
+        self.constraints.push(Box::new(constraint));
+        self
+    }
+
+    pub fn pieces(mut self, pieces: Vec<&'static Tetra>) -> Self {
+        self.pieces = Some(pieces);
+        self
+    }
+
+    pub fn required_pieces(mut self, pieces: HashSet<&'static Tetra>) -> Self {
+        self.required_pieces = pieces;
+        self
+    }
+
+    pub fn min_piece_counts(mut self, counts: HashMap<&'static Tetra, usize>) -> Self {
+        self.min_piece_counts = counts;
+        self
+    }
+
+    pub fn complete(mut self, value: bool) -> Self {
+        self.complete = value;
+        self
+    }
+
+    pub fn parity_prune(mut self, value: bool) -> Self {
+        self.parity_prune = value;
+        self
+    }
+
+    pub fn on_no_solution(mut self, policy: NoSolutionPolicy) -> Self {
+        self.no_solution = policy;
+        self
+    }
+
+    pub fn enclose_blocked(mut self, value: bool) -> Self {
+        self.enclose_blocked = value;
+        self
+    }
+
+    pub fn gravity(mut self, value: bool) -> Self {
+        self.gravity = value;
+        self
+    }
+
+    pub fn resume(mut self, checkpoint: Checkpoint) -> Self {
+        self.resume = Some(checkpoint);
+        self
+    }
+
+    pub fn build(self) -> Result<Configuration, ConfigurationError> {
+        let size = self.size.ok_or(ConfigurationError::MissingSize)?;
+        if size.rows == 0 || size.cols == 0 {
+            return Err(ConfigurationError::EmptyField);
+        }
+        if let Some(pos) = self.unavailable.iter().find(|pos| !pos.in_bounds(&size)) {
+            return Err(ConfigurationError::UnavailableOutOfBoundaries(*pos));
+        }
+        if let Some(pos) = self.outside.iter().find(|pos| !pos.in_bounds(&size)) {
+            return Err(ConfigurationError::OutsideOutOfBoundaries(*pos));
+        }
+        if let Some(pos) = self.optional.iter().find(|pos| !pos.in_bounds(&size)) {
+            return Err(ConfigurationError::OptionalOutOfBoundaries(*pos));
+        }
+        // the most constrained cell is covered in every way, so there is no place to pick
+        if self.scan_order == ScanOrder::MostConstrainedFirst && self.fit_policy != FitPolicy::First
+        {
+            return Err(ConfigurationError::ConflictingOptions(
+                "scan_order",
+                "fit_policy",
+            ));
+        }
+        if self.seed.is_some() && matches!(self.fit_policy, FitPolicy::Random(Some(_))) {
+            return Err(ConfigurationError::ConflictingOptions("seed", "fit_policy"));
+        }
+
+        let mut conf = Configuration::new(size, self.unavailable)
+            .with_outside(self.outside)
+            .with_optional(self.optional)
+            .with_required_pieces(self.required_pieces)
+            .with_min_piece_counts(self.min_piece_counts)
+            .with_enclose_blocked(self.enclose_blocked)
+            .with_gravity(self.gravity)
+            .with_complete(self.complete)
+            .with_parity_prune(self.parity_prune)
+            .on_no_solution(self.no_solution)
+            .with_scan_order(self.scan_order)
+            .with_fit_policy(self.fit_policy)
+            .with_heuristic(self.heuristic)
+            .with_mode(self.mode);
+        if let Some(pieces) = self.pieces {
+            conf = conf.with_pieces(pieces);
+        }
+        conf.piece_count = self.piece_count;
+        conf.max_depth = self.max_depth;
+        conf.max_recursions = self.max_recursions;
+        conf.time_limit = self.time_limit;
+        conf.seed = self.seed;
+        conf.free_cells_slack = self.free_cells_slack;
+        conf = conf
+            .record_all_maximal(self.record_all_maximal)
+            .with_placement_order(self.placement_order)
+            .with_symmetry_breaking(self.symmetry_breaking);
+        if let Some(limit) = self.results_limit {
+            conf = conf.with_results_limit(limit);
+        }
+        if let Some(score) = self.score {
+            conf = conf.with_score(score);
+        }
+        conf.constraints = self.constraints;
+        for placed in self.prefilled {
+            conf = conf.with_prefilled(placed)?;
+        }
+        if let Some(checkpoint) = self.resume {
+            conf = conf.with_resume(checkpoint)?;
+        }
+
+        Ok(conf)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tetra::TETRAS;
+
+    #[derive(Debug)]
+    struct AllowAll;
+
+    impl Constraint for AllowAll {
+        fn allows(
+            &self,
+            _grid: &grid::Grid<crate::algorithm::Cell>,
+            _candidate: &crate::tetra::PlacedBoundariesChecked,
+        ) -> bool {
+            true
+        }
+    }
+
+    #[test]
+    fn builds_minimal() {
+        let conf = Configuration::builder()
+            .size(Size::new(4, 4))
+            .build()
+            .unwrap();
+
+        assert_eq!(conf.size, Size::new(4, 4));
+        assert!(conf.unavailable.is_empty());
+        assert_eq!(conf.results_limit, None);
+        assert_eq!(conf.mode, SearchMode::Random);
+        assert!(conf.prefilled.is_empty());
+        assert!(conf.constraints.is_empty());
+    }
+
+    #[test]
+    fn builds_fully_specified() {
+        let conf = Configuration::builder()
+            .size(Size::new(4, 4))
+            .unavailable([Pos::new(3, 3)].into_iter().collect())
+            .results_limit(NonZeroUsize::new(5).unwrap())
+            .mode(SearchMode::Exhaustive)
+            .seed(7)
+            .time_limit(Duration::from_secs(1))
+            .prefilled(Placed::new(&TETRAS[0], Pos::new(0, 0)))
+            .constraint(AllowAll)
+            .build()
+            .unwrap();
+
+        assert!(conf.unavailable.contains(&Pos::new(3, 3)));
+        assert_eq!(conf.results_limit, NonZeroUsize::new(5));
+        assert_eq!(conf.mode, SearchMode::Exhaustive);
+        assert_eq!(conf.seed, Some(7));
+        assert_eq!(conf.time_limit, Some(Duration::from_secs(1)));
+        assert_eq!(conf.prefilled.len(), 1);
+        assert_eq!(conf.constraints.len(), 1);
+    }
+
+    #[test]
+    fn requires_size() {
+        assert!(matches!(
+            Configuration::builder().build(),
+            Err(ConfigurationError::MissingSize)
+        ));
+    }
+
+    #[test]
+    fn rejects_unavailable_out_of_field() {
+        let result = Configuration::builder()
+            .size(Size::new(2, 2))
+            .unavailable([Pos::new(2, 0)].into_iter().collect())
+            .build();
+
+        assert!(matches!(
+            result,
+            Err(ConfigurationError::UnavailableOutOfBoundaries(pos)) if pos == Pos::new(2, 0)
+        ));
+    }
+
+    #[test]
+    fn rejects_conflicting_options() {
+        let builder = || Configuration::builder().size(Size::new(4, 4));
+
+        assert!(matches!(
+            builder()
+                .scan_order(ScanOrder::MostConstrainedFirst)
+                .fit_policy(FitPolicy::Last)
+                .build(),
+            Err(ConfigurationError::ConflictingOptions(
+                "scan_order",
+                "fit_policy"
+            ))
+        ));
+        assert!(matches!(
+            builder()
+                .seed(1)
+                .fit_policy(FitPolicy::Random(Some(2)))
+                .build(),
+            Err(ConfigurationError::ConflictingOptions("seed", "fit_policy"))
+        ));
+        assert!(builder()
+            .seed(1)
+            .fit_policy(FitPolicy::Random(None))
+            .build()
+            .is_ok());
+    }
+
+    #[test]
+    fn rejects_prefilled_over_unavailable() {
+        let result = Configuration::builder()
+            .size(Size::new(4, 4))
+            .unavailable([Pos::new(1, 1)].into_iter().collect())
+            .prefilled(Placed::new(&TETRAS[0], Pos::new(0, 0)))
+            .build();
+
+        assert!(matches!(
+            result,
+            Err(ConfigurationError::Placement(PlacementError::Overlap(_)))
+        ));
+    }
+}
@@ -0,0 +1,99 @@
+//! Placements as a terminal recording in the asciinema cast format, version 2.
+//!
+//! The first line is the header with the size of the terminal, then each placement is an output
+//! event: clearing the screen and drawing the placement as [`crate::app_terminal::write_placement`]
+//! does, [`FRAME_SECONDS`] after the previous one.
+
+use std::io::{self, Write};
+
+use serde_json::json;
+
+use crate::algorithm::{Configuration, PlacementResult};
+use crate::app_terminal::{self, RenderConfig};
+
+/// How long each placement is shown
+pub const FRAME_SECONDS: f64 = 1.0;
+
+/// Moves the cursor home and clears the screen
+const CLEAR: &str = "\x1b[H\x1b[2J";
+
+/// Writes the header on creation, then placements one by one
+pub struct CastWriter<'a, W> {
+    out: W,
+    conf: &'a Configuration,
+    render: &'a RenderConfig,
+    frames: usize,
+}
+
+impl<'a, W: Write> CastWriter<'a, W> {
+    pub fn new(mut out: W, conf: &'a Configuration, render: &'a RenderConfig) -> io::Result<Self> {
+        // placements are indented by 2 columns
+        let header = json!({
+            "version": 2,
+            "width": app_terminal::placement_width(conf, render) + 2,
+            "height": app_terminal::placement_height(conf, render),
+        });
+        serde_json::to_writer(&mut out, &header)?;
+        writeln!(out)?;
+
+        Ok(Self {
+            out,
+            conf,
+            render,
+            frames: 0,
+        })
+    }
+
+    pub fn write(&mut self, result: &PlacementResult) -> io::Result<()> {
+        let mut frame = Vec::new();
+        app_terminal::write_placement(&mut frame, result, self.conf, self.render)?;
+        let frame = String::from_utf8(frame).expect("Placements are drawn in UTF-8");
+        // the terminal is in raw mode while recording, so lines are ended explicitly
+        let text = format!("{CLEAR}{}", frame.trim_end().replace('\n', "\r\n"));
+
+        let time = self.frames as f64 * FRAME_SECONDS;
+        serde_json::to_writer(&mut self.out, &json!([time, "o", text]))?;
+        writeln!(self.out)?;
+        self.frames += 1;
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algorithm::{NoopStats, SearchMode};
+
+    #[test]
+    fn header_and_event_per_placement() {
+        let conf = Configuration::from_board_str("----\n----")
+            .unwrap()
+            .with_mode(SearchMode::Exhaustive);
+        let results = conf.run(&mut NoopStats);
+        assert!(results.len() > 1);
+        let render = RenderConfig::default();
+
+        let mut writer = CastWriter::new(Vec::new(), &conf, &render).unwrap();
+        for result in &results {
+            writer.write(result).unwrap();
+        }
+
+        let cast = String::from_utf8(writer.out).unwrap();
+        let mut lines = cast.lines();
+        let header: serde_json::Value = serde_json::from_str(lines.next().unwrap()).unwrap();
+        let events: Vec<serde_json::Value> = lines
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+
+        assert_eq!(header, json!({"version": 2, "width": 6, "height": 2}));
+        assert_eq!(events.len(), results.len());
+        assert_eq!(events[1][0], FRAME_SECONDS);
+        assert!(events
+            .iter()
+            .all(|event| event[1] == "o" && event[2].as_str().unwrap().starts_with(CLEAR)));
+    }
+}
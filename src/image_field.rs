@@ -0,0 +1,119 @@
+use std::collections::HashSet;
+
+use image::imageops::FilterType;
+use image::DynamicImage;
+use miette::Diagnostic;
+use thiserror::Error;
+
+use crate::parse_field::ParsedField;
+use crate::util::{Pos, Size};
+
+/// Pixels darker than this are treated as unavailable cells
+const LUMA_THRESHOLD: u8 = 128;
+
+#[derive(Debug, Error, Diagnostic)]
+pub enum ImageFieldError {
+    #[error("Failed to decode image")]
+    Decode(#[source] image::ImageError),
+    #[error("Image is empty")]
+    Empty,
+}
+
+/// Reads a field from an image: dark pixels become unavailable cells, light ones become empty
+/// cells, and transparent ones are left outside of the field.
+///
+/// If `size` is given, the image is downsampled to it first.
+pub fn parse_image(bytes: &[u8], size: Option<Size>) -> Result<ParsedField, ImageFieldError> {
+    let image = image::load_from_memory(bytes).map_err(ImageFieldError::Decode)?;
+    let image = match size {
+        Some(Size { rows, cols }) => {
+            image.resize_exact(cols as u32, rows as u32, FilterType::Triangle)
+        }
+        None => image,
+    };
+    if image.width() == 0 || image.height() == 0 {
+        return Err(ImageFieldError::Empty);
+    }
+
+    Ok(threshold(&image))
+}
+
+fn threshold(image: &DynamicImage) -> ParsedField {
+    let image = image.to_luma_alpha8();
+
+    let mut unavailable = HashSet::new();
+    let mut outside = HashSet::new();
+    for (x, y, pixel) in image.enumerate_pixels() {
+        let pos = Pos::new(y as usize, x as usize);
+        let [luma, alpha] = pixel.0;
+        if alpha < LUMA_THRESHOLD {
+            outside.insert(pos);
+        } else if luma < LUMA_THRESHOLD {
+            unavailable.insert(pos);
+        }
+    }
+
+    ParsedField {
+        size: Size::new(image.height() as usize, image.width() as usize),
+        unavailable,
+        outside,
+        optional: HashSet::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{ImageBuffer, ImageOutputFormat, Luma};
+    use std::io::Cursor;
+
+    fn png(width: u32, height: u32, black: &[(u32, u32)]) -> Vec<u8> {
+        let image = ImageBuffer::from_fn(width, height, |x, y| {
+            if black.contains(&(x, y)) {
+                Luma([0u8])
+            } else {
+                Luma([255u8])
+            }
+        });
+        let mut bytes = Vec::new();
+        DynamicImage::ImageLuma8(image)
+            .write_to(&mut Cursor::new(&mut bytes), ImageOutputFormat::Png)
+            .unwrap();
+        bytes
+    }
+
+    #[test]
+    fn decodes_black_pixels_as_unavailable() {
+        let bytes = png(3, 2, &[(0, 0), (2, 1)]);
+
+        let field = parse_image(&bytes, None).unwrap();
+
+        assert_eq!(
+            field,
+            ParsedField {
+                size: Size::new(2, 3),
+                unavailable: [Pos::new(0, 0), Pos::new(1, 2)].into_iter().collect(),
+                outside: HashSet::new(),
+                optional: HashSet::new(),
+            }
+        );
+    }
+
+    #[test]
+    fn downsamples_to_size() {
+        let bytes = png(4, 4, &[(0, 0), (1, 0), (0, 1), (1, 1)]);
+
+        let field = parse_image(&bytes, Some(Size::new(2, 2))).unwrap();
+
+        assert_eq!(field.size, Size::new(2, 2));
+        assert_eq!(field.unavailable, [Pos::new(0, 0)].into_iter().collect());
+    }
+
+    #[test]
+    fn rejects_garbage() {
+        assert!(matches!(
+            parse_image(b"not an image", None),
+            Err(ImageFieldError::Decode(_))
+        ));
+    }
+}
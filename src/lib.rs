@@ -0,0 +1,17 @@
+pub mod algorithm;
+pub mod app_terminal;
+pub mod bitmap_output;
+pub mod builder;
+pub mod cast_output;
+#[cfg(feature = "image")]
+pub mod image_field;
+pub mod parse_field;
+pub mod piece_profile;
+pub mod pieces;
+pub mod profile;
+pub mod score;
+pub mod structured_output;
+pub mod symmetry;
+pub mod tetra;
+pub mod trace;
+pub mod util;
@@ -1,69 +1,496 @@
-mod algorithm;
-mod app_terminal;
-mod parse_field;
-mod structured_output;
-mod tetra;
-mod util;
-
-use std::io::{stderr, stdout};
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::fs::File;
+use std::io::{stderr, stdout, BufReader, BufWriter, IsTerminal, Write};
 use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::{Duration, Instant};
 
+use clap::builder::PossibleValuesParser;
 use clap::{Parser, ValueEnum};
 use crossterm::style::Print;
 use crossterm::{cursor, terminal, ExecutableCommand};
-use miette::{miette, Result, WrapErr};
+use grid::Grid;
+use miette::{miette, Report, Result, WrapErr};
 
-use algorithm::CollectStats;
+use brutal_tetris_hacker::algorithm::{
+    self, Checkpoint, CollectStats, PlacementResult, ResultsBudget,
+};
+use brutal_tetris_hacker::app_terminal::post_solve;
+use brutal_tetris_hacker::bitmap_output::BitmapWriter;
+use brutal_tetris_hacker::cast_output::CastWriter;
+use brutal_tetris_hacker::piece_profile::{self, PieceProfile, PieceSet};
+use brutal_tetris_hacker::pieces::{Duplicate, PieceLoader};
+use brutal_tetris_hacker::profile::{Phase, Profile};
+use brutal_tetris_hacker::score::{Score, ScoreMode};
+use brutal_tetris_hacker::structured_output::{JsonLinesWriter, Provenance};
+use brutal_tetris_hacker::tetra::{self, PieceKind, Placed, PlacedBoundariesChecked, TETRAS};
+use brutal_tetris_hacker::trace::DotTrace;
+use brutal_tetris_hacker::util::{group_thousands, Pos, Size};
+use brutal_tetris_hacker::{app_terminal, parse_field, structured_output, symmetry};
 
 #[derive(Parser)]
 struct Args {
-    /// The limit of the generated results.
+    /// The limit of the generated results, in total for all the fields read at once.
     #[arg(long)]
     results_limit: Option<NonZeroUsize>,
+    /// The limit of the generated results for each of the fields read at once, see `--stdin`
+    #[arg(long)]
+    max_solutions_per_board: Option<NonZeroUsize>,
     /// Read the field from STDIN.
     ///
-    /// Use `--stdin-char-empty` and `--stdin-char-busy` to configure characters recognition. Any
-    /// other characters are not allowed. The length of each line should be fixed.
+    /// Use `--stdin-char-empty`, `--stdin-char-busy` and `--stdin-char-outside` to configure
+    /// characters recognition. Any other characters are not allowed. The length of each line
+    /// should be fixed.
+    ///
+    /// Several fields separated by empty lines are solved one after another, and the `json`
+    /// output becomes an array with an object per field.
     #[arg(long)]
     stdin: bool,
+    /// Read the field from the argument, with `/` between rows, e.g. `----/-x--/----/----`.
+    ///
+    /// Characters are the same as with `--stdin`
+    #[arg(long, conflicts_with = "stdin", allow_hyphen_values = true)]
+    field: Option<String>,
+    /// Read the fields from the file, the same way as with `--stdin`. The `json` output tells
+    /// the file name
+    #[arg(long, conflicts_with_all = ["stdin", "field"])]
+    field_file: Option<PathBuf>,
     /// In case of reading the field from STDIN, which character treat as an empty cell
     #[arg(long, default_value_t = '-')]
     stdin_char_empty: char,
     /// In case of reading the field from STDIN, which character treat as an unavailable cell
     #[arg(long, default_value_t = 'x')]
     stdin_char_busy: char,
+    /// In case of reading the field from STDIN, which character treat as a cell outside of the
+    /// field, for non-rectangular fields
+    #[arg(long, default_value_t = ' ')]
+    stdin_char_outside: char,
+    /// In case of reading the field from STDIN, which character treat as an optional cell, which
+    /// tetras might cover but don't have to, e.g. with `--complete`
+    #[arg(long)]
+    stdin_char_optional: Option<char>,
+    /// In case of reading the field from STDIN, how it is written: as lines of characters, or as
+    /// `<ROWS>x<COLS>` followed by a line of base64 of a bit per cell, set for unavailable ones
+    #[arg(long, value_enum, default_value_t)]
+    field_format: FieldFormat,
     #[arg(long, value_enum, default_value_t)]
     output_format: OutputFormat,
+    /// Write the `bitmap` or `term-replay` output into the file instead of STDOUT
+    #[arg(long)]
+    output_file: Option<PathBuf>,
+    /// With `json` output, add the `status` field, and write `null` placements rather than an
+    /// empty list if nothing is found
+    #[arg(long)]
+    include_empty_solution: bool,
+    /// With `json` output, add `placement_order` to each placement: its tetras in the order they
+    /// were placed, e.g. to replay the search
+    #[arg(long)]
+    json_order: bool,
+    /// Put a tetra into the field before the search, in format `<TETRA>@<ROW>,<COL>`.
+    ///
+    /// `TETRA` is the tetra id, the same as in the JSON output. Can be repeated.
+    #[arg(long)]
+    prefill: Vec<Prefill>,
+    /// Put a tetra of the given kind into the field before the search, in format
+    /// `<KIND>@<ROW>,<COL>@<ROTATION>`.
+    ///
+    /// `KIND` is one of `O`, `I`, `T`, `L`, `J`, `S`, `Z`, `ROTATION` is clockwise, in degrees:
+    /// 0, 90, 180 or 270. The position is the same as for `--prefill`. Can be repeated.
+    #[arg(long)]
+    seed_piece: Vec<SeedPiece>,
+    /// Keep only placements where tetras fully enclose unavailable cells, i.e. no unavailable cell
+    /// is connected to the field border through empty cells
+    #[arg(long)]
+    enclose_blocked: bool,
+    /// Place tetras only where they rest on the field floor, on an unavailable cell or on another
+    /// tetra, like in Tetris
+    #[arg(long)]
+    gravity: bool,
+    /// Search with a named set of pieces and the options going along with it: `classic-tetris`,
+    /// the 7 one-sided tetrominoes in all rotations with `--gravity`, or `no-skew`, all of them
+    /// but the S and Z pieces
+    #[arg(long, value_parser = PossibleValuesParser::new(piece_profile::NAMES))]
+    piece_profile: Option<String>,
+    /// Search with the 7 tetrominoes of Tetris in all their rotations, which are the 19 tetras
+    /// the search knows, see `--list-pieces`. This is the default, spelled out. Pieces are never
+    /// reflected: the reflections of S and L are the Z and J pieces
+    #[arg(long, conflicts_with = "piece_profile")]
+    standard_tetris: bool,
+    /// Keep only placements which cover the whole field
+    #[arg(long)]
+    complete: bool,
+    /// Before searching, color the field like a checkerboard and find nothing if the tetras
+    /// can't cover as many cells of one color as there are. Tetras other than T cover two cells
+    /// of each color
+    #[arg(long, requires = "complete")]
+    parity_prune: bool,
+    /// What to do if no placement is found: print nothing, print the placement covering the most
+    /// cells instead, or fail
+    #[arg(long, value_enum, default_value_t)]
+    on_no_solution: NoSolution,
+    /// In which order cells are tried when looking for a place for a tetra. Affects which
+    /// placements are found first
+    #[arg(long, value_enum, default_value_t)]
+    scan_order: ScanOrder,
+    /// Which of the places where a tetra fits is taken on each step. Changes how different the
+    /// found placements are and how soon they are found. Not used with `most-constrained-first`
+    /// scan order
+    #[arg(long, value_enum, default_value_t)]
+    fit_policy: FitPolicy,
+    /// Which places where a tetra fits are preferred before the fit policy picks one.
+    /// `fill-largest` keeps to the largest area of empty cells, so that the field doesn't break
+    /// up into areas too small for tetras
+    #[arg(long, value_enum, default_value_t)]
+    heuristic: Heuristic,
+    /// Stop placing tetras once there are this many of them in the field, including prefilled
+    /// ones. Such placements are still recorded only if they cover enough cells
+    #[arg(long)]
+    max_depth: Option<usize>,
+    /// Stop the search after this many steps, keeping what is found by then. Unlike a time
+    /// limit, gives the same results on every run
+    #[arg(long)]
+    max_recursions: Option<usize>,
+    /// Periodically save the search state into the file, so that it could be continued later
+    /// with `--resume`
+    #[arg(long)]
+    checkpoint: Option<PathBuf>,
+    /// Continue the search from the state saved with `--checkpoint`.
+    ///
+    /// The field and the other options should be the same as in the interrupted run.
+    #[arg(long)]
+    resume: Option<PathBuf>,
+    /// Write results into the file in JSON Lines format once there are too many of them to keep
+    /// in memory. The output is produced from the file afterwards
+    #[arg(long)]
+    spill_to: Option<PathBuf>,
+    /// Check whether two fields are the same up to rotation and reflection, and exit.
+    ///
+    /// Fields are read in the same format as with `--stdin`
+    #[arg(long, num_args = 2, value_names = ["A", "B"])]
+    compare_boards: Option<Vec<PathBuf>>,
+    /// Print a hash of the pieces the search places, with `--standard-tetris` and
+    /// `--piece-profile` applied, and exit. Builds with the same hash share the same pieces
+    #[arg(long)]
+    pieces_hash: bool,
+    /// Print the id, kind, size and shape of each tetra the search places, with
+    /// `--standard-tetris` and `--piece-profile` applied, and exit
+    #[arg(long)]
+    list_pieces: bool,
+    /// Validate a file of custom pieces, drawn with `#` and `.` and separated by empty lines,
+    /// print how many distinct pieces it has and exit
+    #[arg(long)]
+    check_pieces: Option<PathBuf>,
+    /// Print how long each phase of the run took into STDERR
+    #[arg(long)]
+    profile: bool,
+    /// Write the search tree into the file in Graphviz DOT format. Only for small fields
+    #[arg(long)]
+    trace_dot: Option<PathBuf>,
+    /// Print an estimate of how hard the search is and exit without searching
+    #[arg(long)]
+    dry_run: bool,
+    /// Print the rotations and reflections which map the field onto itself, and exit without
+    /// searching. Placements which are such images of each other are found separately
+    #[arg(long)]
+    report_symmetry: bool,
+    /// Find a single placement and tell step by step how the search filled the field. With
+    /// `json` output, print the steps as a list of lines
+    #[arg(long, conflicts_with_all = ["dry_run", "report_symmetry"])]
+    explain: bool,
+    /// Print a number to sort fields by how hard they are and exit without searching, see
+    /// `Configuration::difficulty_estimate` of the library
+    #[arg(long, conflicts_with_all = ["dry_run", "report_symmetry", "explain"])]
+    difficulty: bool,
+    /// Search, but print only the fewest free cells a placement leaves and exit. Every maximal
+    /// placement counts, and the search stops once none could leave fewer
+    #[arg(
+        long,
+        conflicts_with_all = ["dry_run", "report_symmetry", "explain", "difficulty"]
+    )]
+    tail_free: bool,
+    /// Characters to label placed tetras with, e.g. `ABC`. Cycled if there are more tetras.
+    #[arg(long)]
+    labels: Option<String>,
+    /// Number tetras in the order they fill the field, row by row, to show the path to a
+    /// placement. Takes `--labels` if given
+    #[arg(long)]
+    fill_order: bool,
+    /// Color tetras by their type rather than by placement order, so that the same tetra has the
+    /// same color in every placement
+    #[arg(long)]
+    color_by_type: bool,
+    /// How many terminal columns a cell takes. With 2 cells look square-ish with most fonts
+    #[arg(long, default_value_t = 1)]
+    cell_width: usize,
+    /// Empty lines between rows of a placement in the `default` output format
+    #[arg(long, default_value_t = 0)]
+    row_spacing: usize,
+    /// Empty lines after each placement in the `default` and `outline` output formats
+    #[arg(long, default_value_t = 1)]
+    placement_spacing: usize,
+    /// Print placements side by side, this many across, each under its number. Only for the
+    /// `default` and `outline` output formats
+    #[arg(long)]
+    columns: Option<NonZeroUsize>,
+    /// Label rows and columns of the field editor with their indices
+    #[arg(long)]
+    editor_rulers: bool,
+    /// Print how many times each tetra is used across all the placements. With `json` output,
+    /// add it as the `usage` field
+    #[arg(long)]
+    usage_summary: bool,
+    /// Print how many placements cover each cell, in tenths of them, to find the cells which are
+    /// hard to cover. With `json` output, add the counts as the `heatmap` field, row by row
+    #[arg(long)]
+    heatmap: bool,
+    /// Print how many placements have each kind of tetra in the top left playable cell
+    #[arg(long)]
+    count_by_kind: bool,
+    /// Sort the placements by an expression, the highest first, e.g.
+    /// `coverage*1.0 - pieces*0.1`.
+    ///
+    /// Known variables are `coverage`, the covered share of the playable cells from 0 to 1,
+    /// `pieces`, the number of tetras, `free`, the number of free cells, and `compactness`, the
+    /// covered share of the bounding box of the covered cells. The score is written into the JSON
+    /// output.
+    #[arg(long, conflicts_with = "spill_to", allow_hyphen_values = true)]
+    score: Option<String>,
+    /// Keep only this many placements with the best `--score`
+    #[arg(long, requires = "score")]
+    score_top: Option<NonZeroUsize>,
+    /// Read the field from a PNG image: dark pixels are unavailable cells, light ones are empty
+    #[cfg(feature = "image")]
+    #[arg(long, conflicts_with_all = ["stdin", "field", "field_file"])]
+    image_file: Option<PathBuf>,
+    /// Downsample the image to the given size, in format `<ROWS>x<COLS>`
+    #[cfg(feature = "image")]
+    #[arg(long, requires = "image_file")]
+    image_size: Option<brutal_tetris_hacker::util::Size>,
+}
+
+#[derive(Clone, Debug)]
+struct Prefill {
+    tetra: usize,
+    pos: Pos,
+}
+
+impl FromStr for Prefill {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (tetra, pos) = s
+            .split_once('@')
+            .ok_or("expected format `<TETRA>@<ROW>,<COL>`")?;
+
+        let tetra: usize = tetra
+            .parse()
+            .map_err(|err| format!("bad tetra id: {err}"))?;
+        if tetra >= TETRAS.len() {
+            return Err(format!(
+                "tetra id should be less than {}, got {tetra}",
+                TETRAS.len()
+            ));
+        }
+
+        Ok(Self {
+            tetra,
+            pos: parse_pos(pos)?,
+        })
+    }
+}
+
+fn parse_pos(s: &str) -> Result<Pos, String> {
+    let (row, col) = s
+        .split_once(',')
+        .ok_or("expected position in format `<ROW>,<COL>`")?;
+    let row = row.parse().map_err(|err| format!("bad row: {err}"))?;
+    let col = col.parse().map_err(|err| format!("bad column: {err}"))?;
+
+    Ok(Pos::new(row, col))
+}
+
+#[derive(Clone, Debug)]
+struct SeedPiece {
+    tetra: &'static tetra::Tetra,
+    pos: Pos,
+}
+
+impl FromStr for SeedPiece {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (kind, rest) = s
+            .split_once('@')
+            .ok_or("expected format `<KIND>@<ROW>,<COL>@<ROTATION>`")?;
+        let (pos, rotation) = rest
+            .split_once('@')
+            .ok_or("expected format `<KIND>@<ROW>,<COL>@<ROTATION>`")?;
+
+        let kind: tetra::PieceKind = kind.parse()?;
+        let pos = parse_pos(pos)?;
+        let quarter_turns = match rotation {
+            "0" => 0,
+            "90" => 1,
+            "180" => 2,
+            "270" => 3,
+            _ => {
+                return Err(format!(
+                    "rotation should be 0, 90, 180 or 270, got {rotation}"
+                ))
+            }
+        };
+
+        Ok(Self {
+            tetra: kind.oriented(quarter_turns),
+            pos,
+        })
+    }
+}
+
+#[derive(ValueEnum, Default, Debug, Clone)]
+enum ScanOrder {
+    #[default]
+    Row,
+    Col,
+    Spiral,
+    Random,
+    MostConstrainedFirst,
+}
+
+impl From<ScanOrder> for algorithm::ScanOrder {
+    fn from(value: ScanOrder) -> Self {
+        match value {
+            ScanOrder::Row => Self::Row,
+            ScanOrder::Col => Self::Column,
+            ScanOrder::Spiral => Self::Spiral,
+            ScanOrder::Random => Self::Random,
+            ScanOrder::MostConstrainedFirst => Self::MostConstrainedFirst,
+        }
+    }
+}
+
+#[derive(ValueEnum, Default, Debug, Clone)]
+enum FitPolicy {
+    #[default]
+    First,
+    Last,
+    /// The place covering the cell the fewest placements could cover
+    MostConstrainedCell,
+    Random,
+}
+
+impl From<FitPolicy> for algorithm::FitPolicy {
+    fn from(value: FitPolicy) -> Self {
+        match value {
+            FitPolicy::First => Self::First,
+            FitPolicy::Last => Self::Last,
+            FitPolicy::MostConstrainedCell => Self::MostConstrainedCell,
+            FitPolicy::Random => Self::Random(None),
+        }
+    }
+}
+
+#[derive(ValueEnum, Default, Debug, Clone)]
+enum Heuristic {
+    #[default]
+    None,
+    FillLargest,
+}
+
+impl From<Heuristic> for algorithm::Heuristic {
+    fn from(value: Heuristic) -> Self {
+        match value {
+            Heuristic::None => Self::None,
+            Heuristic::FillLargest => Self::FillLargest,
+        }
+    }
+}
+
+#[derive(ValueEnum, Default, Debug, Clone)]
+enum NoSolution {
+    #[default]
+    Empty,
+    BestPartial,
+    Error,
+}
+
+impl From<NoSolution> for algorithm::NoSolutionPolicy {
+    fn from(value: NoSolution) -> Self {
+        match value {
+            NoSolution::Empty => Self::Empty,
+            NoSolution::BestPartial => Self::BestPartial,
+            NoSolution::Error => Self::Error,
+        }
+    }
+}
+
+#[derive(ValueEnum, Default, Debug, Clone)]
+enum FieldFormat {
+    #[default]
+    Grid,
+    Bits,
 }
 
 #[derive(ValueEnum, Default, Debug, Clone)]
 enum OutputFormat {
     #[default]
     Default,
+    /// Like `default`, but draws borders of tetras instead of labelling their cells
+    Outline,
+    /// Errors are written into STDOUT as JSON too, with the exit code 1
     Json,
+    /// Compact binary encoding, see the `bitmap_output` module of the library
+    Bitmap,
+    /// Asciinema cast, version 2, showing a placement per second
+    TermReplay,
 }
 
-struct Stats {
-    start: std::time::Instant,
+struct Stats<'a> {
+    start: Instant,
     recursions: usize,
     results: usize,
+    checkpoint_file: Option<PathBuf>,
+    spill: Option<Spill<'a>>,
+    profile: Profile,
+    trace: Option<DotTrace>,
 }
 
-impl Stats {
-    fn new() -> Self {
+struct Spill<'a> {
+    conf: &'a algorithm::Configuration,
+    writer: JsonLinesWriter<BufWriter<File>>,
+    /// Whether anything was written, i.e. whether the results should be read from the file
+    used: bool,
+    /// The first failure, after which nothing is written anymore
+    error: Option<std::io::Error>,
+}
+
+impl<'a> Stats<'a> {
+    fn new(
+        checkpoint_file: Option<PathBuf>,
+        spill: Option<Spill<'a>>,
+        trace: Option<DotTrace>,
+    ) -> Self {
         Self {
-            start: std::time::Instant::now(),
+            start: Instant::now(),
             recursions: 0,
             results: 0,
+            checkpoint_file,
+            spill,
+            profile: Profile::default(),
+            trace,
         }
     }
 }
 
-impl CollectStats for Stats {
+const CHECKPOINT_INTERVAL: usize = 10_000_000;
+const SPILL_THRESHOLD: usize = 100_000;
+
+impl CollectStats for Stats<'_> {
     fn recursions_inc(&mut self) {
         self.recursions += 1;
 
-        if self.recursions % 100_000 == 0 {
+        if self.recursions.is_multiple_of(100_000) {
             stderr()
                 .execute(terminal::Clear(terminal::ClearType::CurrentLine))
                 .unwrap()
@@ -71,9 +498,9 @@ impl CollectStats for Stats {
                 .unwrap()
                 .execute(Print(format!(
                     "recursions: {}, time: {:.2?}, results: {}",
-                    self.recursions,
+                    group_thousands(self.recursions),
                     self.start.elapsed(),
-                    self.results
+                    group_thousands(self.results)
                 )))
                 .unwrap();
         }
@@ -82,6 +509,77 @@ impl CollectStats for Stats {
     fn results_inc(&mut self) {
         self.results += 1;
     }
+
+    fn checkpoint_interval(&self) -> Option<NonZeroUsize> {
+        self.checkpoint_file
+            .as_ref()
+            .and(NonZeroUsize::new(CHECKPOINT_INTERVAL))
+    }
+
+    fn checkpoint(&mut self, checkpoint: &Checkpoint) {
+        let Some(path) = &self.checkpoint_file else {
+            return;
+        };
+
+        // write into a temporary file first, so that a crash in the middle of writing
+        // doesn't spoil the previous checkpoint
+        let tmp = path.with_extension("tmp");
+        let json = serde_json::to_string(checkpoint).expect("Checkpoint is serialisable");
+        if let Err(err) = std::fs::write(&tmp, json).and_then(|()| std::fs::rename(&tmp, path)) {
+            eprintln!("\nFailed to save checkpoint into {}: {err}", path.display());
+        }
+    }
+
+    fn phase_finished(&mut self, phase: Phase, elapsed: Duration) {
+        self.profile.record(phase, elapsed);
+    }
+
+    fn tetra_placed(&mut self, placed: &PlacedBoundariesChecked) {
+        if let Some(trace) = &mut self.trace {
+            trace.tetra_placed(placed);
+        }
+    }
+
+    fn tetra_removed(&mut self) {
+        if let Some(trace) = &mut self.trace {
+            trace.tetra_removed();
+        }
+    }
+
+    fn leaf_reached(&mut self, accepted: bool) {
+        if let Some(trace) = &mut self.trace {
+            trace.leaf_reached(accepted);
+        }
+    }
+
+    fn search_truncated(&mut self) {
+        eprintln!(
+            "\nThe search is stopped after {} recursions, the results are incomplete",
+            group_thousands(self.recursions)
+        );
+    }
+
+    fn spill_threshold(&self) -> Option<NonZeroUsize> {
+        self.spill.as_ref().and(NonZeroUsize::new(SPILL_THRESHOLD))
+    }
+
+    fn spill(&mut self, results: BTreeSet<PlacementResult>) {
+        let Some(spill) = &mut self.spill else {
+            return;
+        };
+
+        spill.used = true;
+        if spill.error.is_some() {
+            return;
+        }
+        for result in &results {
+            let placement = structured_output::Placement::new(result, spill.conf);
+            if let Err(err) = spill.writer.write(&placement) {
+                spill.error = Some(err);
+                return;
+            }
+        }
+    }
 }
 
 fn io_err_into_diagnostic(err: std::io::Error) -> miette::Report {
@@ -90,67 +588,990 @@ fn io_err_into_diagnostic(err: std::io::Error) -> miette::Report {
 
 fn main() -> Result<()> {
     let args = Args::parse();
+    let json = matches!(args.output_format, OutputFormat::Json);
 
-    let conf = {
-        let mut conf = if args.stdin {
-            use std::io::{self, Read};
+    match run(args) {
+        // callers reading JSON get the error in JSON too, rather than a diagnostic for humans
+        Err(report) if json => {
+            print_json_error(&report)?;
+            std::process::exit(1);
+        }
+        Err(report) => {
+            // the same as returning the report would print, followed by the field
+            eprintln!("Error: {report:?}");
+            print_bad_cell(&report);
+            std::process::exit(1);
+        }
+        Ok(()) => Ok(()),
+    }
+}
 
-            let mut input = String::new();
-            io::stdin().read_to_string(&mut input).unwrap();
+fn print_json_error(report: &Report) -> Result<()> {
+    let output = serde_json::to_string_pretty(&structured_output::ErrorOutput::new(report))
+        .map_err(|err| miette!("{err}"))
+        .wrap_err("Failed to serialise error into JSON")?;
+    println!("{output}");
+    Ok(())
+}
+
+/// Shows the field which failed to parse with the bad cell highlighted, if there is one
+fn print_bad_cell(report: &Report) {
+    let Some((rows, pos)) = report
+        .downcast_ref::<parse_field::SourcedParseError>()
+        .and_then(parse_field::SourcedParseError::bad_cell)
+    else {
+        return;
+    };
+    eprintln!("  Cell {pos} of the field:\n");
+    // the error is already reported, so a failure to show it is not worth another one
+    let _ = app_terminal::write_bad_cell(&mut stderr(), &rows, pos);
+}
 
-            parse_field::Parser::new(args.stdin_char_empty, args.stdin_char_busy)
-                .parse(input)
-                .map(|parse_field::ParsedField { size, unavailable }| {
-                    algorithm::Configuration::new(size, unavailable)
+fn run(args: Args) -> Result<()> {
+    if args.pieces_hash {
+        stdout()
+            .execute(Print(format!(
+                "{:016x}\n",
+                tetra::pieces_fingerprint(configure_pieces(placeholder_conf(), &args).pieces)
+            )))
+            .map_err(io_err_into_diagnostic)?;
+        return Ok(());
+    }
+
+    if args.list_pieces {
+        stdout()
+            .execute(Print(tetra::describe_pieces(
+                configure_pieces(placeholder_conf(), &args).pieces,
+            )))
+            .map_err(io_err_into_diagnostic)?;
+        return Ok(());
+    }
+
+    if let Some(path) = &args.check_pieces {
+        let input = std::fs::read_to_string(path)
+            .map_err(io_err_into_diagnostic)
+            .wrap_err_with(|| format!("Failed to read pieces from {}", path.display()))?;
+        let loaded = PieceLoader::default()
+            .load(&input)
+            .map_err(|err| miette::Report::new(err).with_source_code(input.clone()))
+            .wrap_err_with(|| format!("Failed to read pieces from {}", path.display()))?;
+        for Duplicate { index, same_as } in &loaded.duplicates {
+            eprintln!("Piece #{index} is the same as piece #{same_as}, left out");
+        }
+        stdout()
+            .execute(Print(format!("Distinct pieces: {}\n", loaded.shapes.len())))
+            .map_err(io_err_into_diagnostic)?;
+        return Ok(());
+    }
+
+    if let Some(paths) = &args.compare_boards {
+        let parser = field_parser(&args);
+        let read = |path: &PathBuf| {
+            std::fs::read_to_string(path)
+                .map_err(io_err_into_diagnostic)
+                .and_then(|input| parser.parse(input))
+                .wrap_err_with(|| format!("Failed to read field from {}", path.display()))
+        };
+        let comparison = symmetry::compare(&read(&paths[0])?, &read(&paths[1])?);
+        stdout()
+            .execute(Print(format!("Fields are {comparison}\n")))
+            .map_err(io_err_into_diagnostic)?;
+        return Ok(());
+    }
+
+    let parse_start = Instant::now();
+
+    #[cfg(feature = "image")]
+    let from_image = args
+        .image_file
+        .as_ref()
+        .map(|path| {
+            let bytes = std::fs::read(path).map_err(io_err_into_diagnostic)?;
+            brutal_tetris_hacker::image_field::parse_image(&bytes, args.image_size)
+                .map(|field| {
+                    (
+                        Provenance::new(path.display().to_string()),
+                        algorithm::Configuration::from_parsed(field),
+                    )
                 })
-                .wrap_err("Failed to parse field from STDIN")?
+                .wrap_err_with(|| format!("Failed to read field from {}", path.display()))
+        })
+        .transpose()?;
+    #[cfg(not(feature = "image"))]
+    let from_image = None;
+
+    let (source, mut confs) = {
+        let (source, confs) = if let Some((source, conf)) = from_image {
+            (source, vec![conf])
+        } else if let Some(field) = &args.field {
+            let field = field_parser(&args)
+                .with_row_delimiter('/')
+                .parse(field)
+                .wrap_err("Failed to parse field from `--field`")?;
+            (
+                Provenance::new("--field"),
+                vec![algorithm::Configuration::from_parsed(field)],
+            )
+        } else if args.stdin || args.field_file.is_some() {
+            use std::io::{self, Read};
+
+            let (source, input, described) = match &args.field_file {
+                Some(path) => (
+                    Provenance::new(path.display().to_string()),
+                    std::fs::read_to_string(path)
+                        .map_err(io_err_into_diagnostic)
+                        .wrap_err_with(|| format!("Failed to read {}", path.display()))?,
+                    path.display().to_string(),
+                ),
+                None => {
+                    let mut input = String::new();
+                    io::stdin().read_to_string(&mut input).unwrap();
+                    (Provenance::new("stdin"), input, "STDIN".to_owned())
+                }
+            };
+
+            let confs = match args.field_format {
+                FieldFormat::Grid => field_parser(&args).parse_many(input),
+                FieldFormat::Bits => parse_field::parse_bits(input).map(|field| vec![field]),
+            }
+            .map(|fields| {
+                fields
+                    .into_iter()
+                    .map(algorithm::Configuration::from_parsed)
+                    .collect()
+            })
+            .wrap_err_with(|| format!("Failed to parse field from {described}"))?;
+            (source, confs)
         } else {
-            app_terminal::live_configuration::State::new(4, 4)
+            (
+                Provenance::new("editor"),
+                vec![app_terminal::live_configuration::State::new(4, 4)
+                    .with_cell_width(args.cell_width)
+                    .with_rulers(args.editor_rulers)
+                    .live()
+                    .map_err(io_err_into_diagnostic)?
+                    .into_configuration()],
+            )
+        };
+
+        let mut confs = confs
+            .into_iter()
+            .map(|conf| configure(conf, &args))
+            .collect::<Result<Vec<_>>>()?;
+        if confs.len() > 1 {
+            // these describe a single search
+            if args.checkpoint.is_some() || args.resume.is_some() || args.output_file.is_some() {
+                return Err(miette!(
+                    "`--checkpoint`, `--resume` and `--output-file` work with a single field, \
+                     found {}",
+                    confs.len()
+                ));
+            }
+        } else if let Some(path) = &args.resume {
+            let checkpoint: Checkpoint = std::fs::read_to_string(path)
+                .map_err(io_err_into_diagnostic)
+                .and_then(|json| serde_json::from_str(&json).map_err(|err| miette!("{err}")))
+                .wrap_err_with(|| format!("Failed to read checkpoint from {}", path.display()))?;
+            let conf = confs.pop().expect("a single field");
+            confs.push(
+                conf.with_resume(checkpoint)
+                    .wrap_err("Failed to resume the search")?,
+            );
+        }
+        (source, confs)
+    };
+    let mut parse_elapsed = parse_start.elapsed();
+
+    let labels = match &args.labels {
+        Some(labels) => Some(labels.as_str()),
+        None if args.fill_order => Some(app_terminal::FILL_ORDER_LABELS),
+        None => None,
+    };
+    let render = match labels {
+        Some(labels) => app_terminal::RenderConfig::default()
+            .with_labels(labels.chars().collect())
+            .wrap_err("Invalid labels")?,
+        None => app_terminal::RenderConfig::default(),
+    }
+    .fill_order(args.fill_order)
+    .color_by_type(args.color_by_type)
+    .cell_width(args.cell_width)
+    .row_spacing(args.row_spacing)
+    .placement_spacing(args.placement_spacing)
+    .truecolor(app_terminal::supports_truecolor());
+
+    if confs.len() > 1 {
+        return solve_each(confs, &args, &render, &source, parse_elapsed);
+    }
+    let mut conf = confs.pop().expect("at least one field");
+
+    // offer to search again only if there is someone to answer
+    let interactive = matches!(
+        args.output_format,
+        OutputFormat::Default | OutputFormat::Outline
+    ) && !args.stdin
+        && args.field.is_none()
+        && args.field_file.is_none()
+        && std::io::stdin().is_terminal()
+        && stdout().is_terminal();
+
+    loop {
+        if let OutputFormat::Default | OutputFormat::Outline = args.output_format {
+            conf.print_field(&render).map_err(io_err_into_diagnostic)?;
+        }
+
+        if args.dry_run {
+            return print_estimate(&conf, &args);
+        }
+        if args.report_symmetry {
+            return print_symmetry(&conf, &args);
+        }
+        if args.difficulty {
+            return print_difficulty(&conf);
+        }
+        if args.tail_free {
+            return print_tail_free(&conf, &args);
+        }
+        if args.explain {
+            return print_explanation(&conf, &args, &render);
+        }
+
+        solve(&conf, &args, &render, &source, parse_elapsed)?;
+        if !interactive {
+            return Ok(());
+        }
+
+        let action = post_solve::ask(&mut std::io::stdin().lock(), &mut stdout())
+            .map_err(io_err_into_diagnostic)?;
+        let edit = |conf: algorithm::Configuration| {
+            let edited = app_terminal::live_configuration::State::from_configuration(&conf)
+                .with_cell_width(args.cell_width)
+                .with_rulers(args.editor_rulers)
                 .live()
                 .map_err(io_err_into_diagnostic)?
-                .into_configuration()
+                .into_configuration();
+            configure(edited, &args)
+        };
+        match post_solve::dispatch(action, conf, edit)? {
+            Some(next) => conf = next,
+            None => return Ok(()),
+        }
+        parse_elapsed = Duration::ZERO;
+    }
+}
+
+/// Solves the fields one after another, stopping once `--results-limit` is reached in total
+fn solve_each(
+    confs: Vec<algorithm::Configuration>,
+    args: &Args,
+    render: &app_terminal::RenderConfig,
+    source: &Provenance,
+    parse_elapsed: Duration,
+) -> Result<()> {
+    let print = |text: String| {
+        stdout()
+            .execute(Print(text))
+            .map(|_| ())
+            .map_err(io_err_into_diagnostic)
+    };
+
+    let json = matches!(args.output_format, OutputFormat::Json);
+    if json {
+        print("[".to_owned())?;
+    }
+    let boards = confs.len();
+    let mut budget = ResultsBudget::new(args.results_limit);
+    for (idx, mut conf) in confs.into_iter().enumerate() {
+        if budget.is_spent() {
+            if !json {
+                print(format!(
+                    "\nResults limit reached, skipped {} of {boards} boards\n",
+                    boards - idx
+                ))?;
+            }
+            break;
+        }
+        match args.output_format {
+            OutputFormat::Default | OutputFormat::Outline => {
+                print(format!("\nBoard {} of {boards}\n", idx + 1))?;
+                conf.print_field(render).map_err(io_err_into_diagnostic)?;
+            }
+            OutputFormat::Json if idx > 0 => print(",".to_owned())?,
+            _ => {}
+        }
+
+        let board = if args.dry_run {
+            print_estimate(&conf, args)
+        } else if args.report_symmetry {
+            print_symmetry(&conf, args)
+        } else if args.difficulty {
+            print_difficulty(&conf)
+        } else if args.tail_free {
+            print_tail_free(&conf, args)
+        } else if args.explain {
+            print_explanation(&conf, args, render)
+        } else {
+            // parsing is shared, so it's accounted once
+            let parse_elapsed = if idx == 0 {
+                parse_elapsed
+            } else {
+                Duration::ZERO
+            };
+            conf.results_limit = budget.limit(conf.results_limit);
+            let source = source.clone().with_board(idx + 1);
+            solve(&conf, args, render, &source, parse_elapsed)
+                .map(|found| budget.spend(found))
+                .wrap_err_with(|| format!("Failed to solve board {}", idx + 1))
         };
+        match board {
+            // the error takes the place of the board and ends the list, so that the output is
+            // still a single JSON document
+            Err(report) if json => {
+                print_json_error(&report)?;
+                print("]\n".to_owned())?;
+                std::process::exit(1);
+            }
+            board => board?,
+        }
+    }
+    if json {
+        print("]\n".to_owned())?;
+    }
+    Ok(())
+}
 
-        if let Some(limit) = args.results_limit {
-            conf = conf.with_results_limit(limit);
+fn print_estimate(conf: &algorithm::Configuration, args: &Args) -> Result<()> {
+    let estimate = conf.estimate();
+    let output = match args.output_format {
+        OutputFormat::Default
+        | OutputFormat::Outline
+        | OutputFormat::Bitmap
+        | OutputFormat::TermReplay => {
+            format!("{estimate}\n")
         }
-        conf
+        OutputFormat::Json => serde_json::to_string_pretty(&estimate)
+            .map_err(|err| miette!("{err}"))
+            .wrap_err("Failed to serialise estimate into JSON")?,
+    };
+    stdout()
+        .execute(Print(output))
+        .map_err(io_err_into_diagnostic)?;
+    Ok(())
+}
+
+/// The number is the same in every output format, and is valid JSON
+fn print_difficulty(conf: &algorithm::Configuration) -> Result<()> {
+    stdout()
+        .execute(Print(format!("{:.3}\n", conf.difficulty_estimate())))
+        .map_err(io_err_into_diagnostic)?;
+    Ok(())
+}
+
+fn print_tail_free(conf: &algorithm::Configuration, args: &Args) -> Result<()> {
+    let min_free = conf.min_free();
+    let output = match (&args.output_format, min_free) {
+        (OutputFormat::Json, _) => serde_json::to_string(&min_free)
+            .map_err(|err| miette!("{err}"))
+            .wrap_err("Failed to serialise free cells into JSON")?,
+        (_, Some(free)) => free.to_string(),
+        (_, None) => "  Nothing is found".to_owned(),
     };
+    stdout()
+        .execute(Print(output + "\n"))
+        .map_err(io_err_into_diagnostic)?;
+    Ok(())
+}
 
-    if let OutputFormat::Default = args.output_format {
-        conf.print_field().map_err(io_err_into_diagnostic)?;
+fn print_symmetry(conf: &algorithm::Configuration, args: &Args) -> Result<()> {
+    let symmetries = symmetry::symmetries(&conf.to_parsed());
+    let output = match args.output_format {
+        OutputFormat::Default
+        | OutputFormat::Outline
+        | OutputFormat::Bitmap
+        | OutputFormat::TermReplay => {
+            let mut output = format!("Symmetries: {} of 8\n", symmetries.len());
+            for transform in &symmetries {
+                output.push_str(&format!("  {transform}\n"));
+            }
+            output
+        }
+        OutputFormat::Json => {
+            let names: Vec<_> = symmetries.iter().map(ToString::to_string).collect();
+            serde_json::to_string_pretty(&names)
+                .map_err(|err| miette!("{err}"))
+                .wrap_err("Failed to serialise symmetries into JSON")?
+        }
+    };
+    stdout()
+        .execute(Print(output))
+        .map_err(io_err_into_diagnostic)?;
+    Ok(())
+}
+
+fn print_explanation(
+    conf: &algorithm::Configuration,
+    args: &Args,
+    render: &app_terminal::RenderConfig,
+) -> Result<()> {
+    let result = conf.solve_one();
+    let steps = result
+        .as_ref()
+        .map(|result| result.narrate(conf))
+        .unwrap_or_default();
+    let output = match args.output_format {
+        OutputFormat::Default
+        | OutputFormat::Outline
+        | OutputFormat::Bitmap
+        | OutputFormat::TermReplay => {
+            let Some(result) = &result else {
+                return stdout()
+                    .execute(Print("  Nothing is found\n"))
+                    .map(|_| ())
+                    .map_err(io_err_into_diagnostic);
+            };
+            if !matches!(
+                args.output_format,
+                OutputFormat::Bitmap | OutputFormat::TermReplay
+            ) {
+                print_placement(result, conf, &args.output_format, render)?;
+                println!();
+            }
+            let mut output = String::new();
+            if let Some((summary, steps)) = steps.split_last() {
+                for (idx, step) in steps.iter().enumerate() {
+                    output.push_str(&format!("  {}. {step}\n", idx + 1));
+                }
+                output.push_str(&format!("\n  {summary}\n"));
+            }
+            output
+        }
+        OutputFormat::Json => serde_json::to_string_pretty(&steps)
+            .map_err(|err| miette!("{err}"))
+            .wrap_err("Failed to serialise steps into JSON")?,
+    };
+    stdout()
+        .execute(Print(output))
+        .map_err(io_err_into_diagnostic)?;
+    Ok(())
+}
+
+/// Reads fields in the grid format with the characters set by the arguments
+fn field_parser(args: &Args) -> parse_field::Parser {
+    let parser = parse_field::Parser::new(args.stdin_char_empty, args.stdin_char_busy)
+        .with_char_outside(args.stdin_char_outside);
+    match args.stdin_char_optional {
+        Some(char_optional) => parser.with_char_optional(char_optional),
+        None => parser,
     }
+}
 
-    let mut stats = Stats::new();
-    let placements = conf.run(&mut stats);
+/// Applies the search options to the field, either the initial one or an edited one
+fn configure(mut conf: algorithm::Configuration, args: &Args) -> Result<algorithm::Configuration> {
+    // with several fields the total limit is lowered further as they are solved
+    let limit = match (args.results_limit, args.max_solutions_per_board) {
+        (Some(total), Some(per_board)) => Some(total.min(per_board)),
+        (total, per_board) => total.or(per_board),
+    };
+    if let Some(limit) = limit {
+        conf = conf.with_results_limit(limit);
+    }
+    if let Some(depth) = args.max_depth {
+        conf = conf.with_max_depth(depth);
+    }
+    if let Some(recursions) = args.max_recursions {
+        conf = conf.with_max_recursions(recursions);
+    }
+    if let Some(score) = &args.score {
+        conf = conf.with_score(ScoreMode::Custom(Score::parse(score)?));
+    }
+    conf = conf
+        .with_enclose_blocked(args.enclose_blocked)
+        .with_gravity(args.gravity)
+        .with_placement_order(args.json_order || args.explain)
+        .with_complete(args.complete)
+        .with_parity_prune(args.parity_prune)
+        .on_no_solution(args.on_no_solution.clone().into())
+        .with_scan_order(args.scan_order.clone().into())
+        .with_fit_policy(args.fit_policy.clone().into())
+        .with_heuristic(args.heuristic.clone().into());
+    conf = configure_pieces(conf, args);
+    for Prefill { tetra, pos } in &args.prefill {
+        conf = conf
+            .with_prefilled(Placed::new(&TETRAS[*tetra], *pos))
+            .wrap_err("Failed to prefill the field")?;
+    }
+    for SeedPiece { tetra, pos } in &args.seed_piece {
+        conf = conf
+            .with_prefilled(Placed::new(tetra, *pos))
+            .wrap_err("Seed piece doesn't fit into the field")?;
+    }
+    Ok(conf)
+}
+
+/// Applies the options choosing which pieces the search places
+fn configure_pieces(mut conf: algorithm::Configuration, args: &Args) -> algorithm::Configuration {
+    if args.standard_tetris {
+        conf = conf.with_pieces(PieceSet::standard_tetris().pieces().to_vec());
+    }
+    if let Some(name) = &args.piece_profile {
+        let profile = PieceProfile::lookup(name).expect("Names are checked by the parser");
+        conf = profile.apply(conf);
+    }
+    conf
+}
+
+/// A configuration to apply the options to when there is no field, e.g. to tell the pieces
+fn placeholder_conf() -> algorithm::Configuration {
+    algorithm::Configuration::new(Size::new(1, 1), HashSet::new())
+}
+
+/// Searches the field and reports the results, returning how many were found
+fn solve(
+    conf: &algorithm::Configuration,
+    args: &Args,
+    render: &app_terminal::RenderConfig,
+    source: &Provenance,
+    parse_elapsed: Duration,
+) -> Result<usize> {
+    let spill = args
+        .spill_to
+        .as_ref()
+        .map(|path| {
+            File::create(path)
+                .map(|file| Spill {
+                    conf,
+                    writer: JsonLinesWriter::new(BufWriter::new(file)),
+                    used: false,
+                    error: None,
+                })
+                .map_err(io_err_into_diagnostic)
+                .wrap_err_with(|| format!("Failed to create spill file {}", path.display()))
+        })
+        .transpose()?;
+
+    let trace = args
+        .trace_dot
+        .as_ref()
+        .map(|_| DotTrace::new(conf))
+        .transpose()
+        .wrap_err("Failed to trace the search")?;
+
+    let mut stats = Stats::new(args.checkpoint.clone(), spill, trace);
+    let placements = conf.try_run(&mut stats)?;
     let elapsed = stats.start.elapsed();
+    let found = stats.results;
+
+    if let (Some(trace), Some(path)) = (stats.trace.take(), &args.trace_dot) {
+        std::fs::write(path, trace.to_string())
+            .map_err(io_err_into_diagnostic)
+            .wrap_err_with(|| format!("Failed to write the search tree into {}", path.display()))?;
+    }
+
+    let mut profile = std::mem::take(&mut stats.profile);
+    profile.record(Phase::Parse, parse_elapsed);
+
+    let mut spilled_to = None;
+    if let (Some(mut spill), Some(path)) = (stats.spill.take(), &args.spill_to) {
+        if spill.used {
+            spill
+                .error
+                .map_or_else(|| spill.writer.flush(), Err)
+                .map_err(io_err_into_diagnostic)
+                .wrap_err_with(|| format!("Failed to spill results into {}", path.display()))?;
+            spilled_to = Some(path);
+        }
+    }
 
-    match args.output_format {
-        OutputFormat::Default => {
-            for item in &placements {
-                app_terminal::report_placement(item, &conf).map_err(io_err_into_diagnostic)?;
-                stdout()
-                    .execute(Print("\n"))
-                    .map_err(io_err_into_diagnostic)?;
+    let render_start = Instant::now();
+    let output = Output {
+        source,
+        render,
+        columns: args.columns,
+        file: args.output_file.as_deref(),
+        usage_summary: args.usage_summary,
+        heatmap: args.heatmap,
+        count_by_kind: args.count_by_kind,
+        include_empty_solution: args.include_empty_solution,
+    };
+    let reported = match spilled_to {
+        Some(path) => report_spilled(path, conf, &args.output_format, &output, elapsed),
+        None => {
+            let placements = conf.ranked(&placements, args.score_top);
+            report(&placements, conf, &args.output_format, &output, elapsed)
+        }
+    };
+    profile.record(Phase::Render, render_start.elapsed());
+
+    if args.profile {
+        eprint!("\n{profile}");
+    }
+
+    reported.map(|()| found)
+}
+
+/// Where and how to write the results
+struct Output<'a> {
+    /// Written into the `json` output
+    source: &'a Provenance,
+    render: &'a app_terminal::RenderConfig,
+    columns: Option<NonZeroUsize>,
+    file: Option<&'a Path>,
+    usage_summary: bool,
+    heatmap: bool,
+    count_by_kind: bool,
+    include_empty_solution: bool,
+}
+
+impl<'a> Output<'a> {
+    fn placement_printer(
+        &self,
+        conf: &'a algorithm::Configuration,
+        output_format: &'a OutputFormat,
+    ) -> PlacementPrinter<'a> {
+        PlacementPrinter {
+            conf,
+            output_format,
+            render: self.render,
+            columns: self.columns,
+            row: Vec::new(),
+            printed: 0,
+        }
+    }
+
+    /// The file if it is given, or else STDOUT
+    fn stream(&self) -> Result<BufWriter<Box<dyn Write>>> {
+        let out: Box<dyn Write> = match self.file {
+            Some(path) => Box::new(
+                File::create(path)
+                    .map_err(io_err_into_diagnostic)
+                    .wrap_err_with(|| format!("Failed to create {}", path.display()))?,
+            ),
+            None => Box::new(stdout()),
+        };
+        Ok(BufWriter::new(out))
+    }
+
+    fn bitmap_writer(
+        &self,
+        conf: &algorithm::Configuration,
+    ) -> Result<BitmapWriter<BufWriter<Box<dyn Write>>>> {
+        BitmapWriter::new(self.stream()?, conf).map_err(io_err_into_diagnostic)
+    }
+
+    fn cast_writer(
+        &self,
+        conf: &'a algorithm::Configuration,
+    ) -> Result<CastWriter<'a, BufWriter<Box<dyn Write>>>> {
+        CastWriter::new(self.stream()?, conf, self.render).map_err(io_err_into_diagnostic)
+    }
+}
+
+fn report(
+    placements: &[&PlacementResult],
+    conf: &algorithm::Configuration,
+    output_format: &OutputFormat,
+    output: &Output,
+    elapsed: Duration,
+) -> Result<()> {
+    let render = output.render;
+    match output_format {
+        OutputFormat::Default | OutputFormat::Outline => {
+            let mut printer = output.placement_printer(conf, output_format);
+            for item in placements {
+                printer.print(item)?;
             }
+            printer.finish()?;
 
             stdout()
                 .execute(Print(format!(
                     "\n  Found placements: {} (time: {:.2?})\n",
-                    placements.len(),
+                    group_thousands(placements.len()),
                     elapsed
                 )))
                 .map_err(io_err_into_diagnostic)?;
+            if output.usage_summary {
+                print_usage(&algorithm::aggregate_piece_usage(
+                    placements.iter().copied(),
+                ))?;
+            }
+            if output.heatmap {
+                let heatmap = algorithm::coverage_heatmap(placements.iter().copied(), conf);
+                print_heatmap(&heatmap, placements.len(), conf, render)?;
+            }
+            if output.count_by_kind {
+                print_kinds(&algorithm::count_by_leading_kind(
+                    placements.iter().copied(),
+                    conf,
+                ))?;
+            }
         }
         OutputFormat::Json => {
-            let output = structured_output::Output::new(&placements);
-            let json = serde_json::to_string_pretty(&output)
+            let mut json_output = structured_output::Output::new(placements.iter().copied(), conf)
+                .with_source(output.source.clone());
+            if output.usage_summary {
+                json_output = json_output
+                    .with_usage(algorithm::aggregate_piece_usage(placements.iter().copied()));
+            }
+            if output.heatmap {
+                json_output = json_output.with_heatmap(&algorithm::coverage_heatmap(
+                    placements.iter().copied(),
+                    conf,
+                ));
+            }
+            if output.include_empty_solution {
+                json_output = json_output.with_status();
+            }
+            let json = serde_json::to_string_pretty(&json_output)
                 .map_err(|err| miette!("{err}"))
                 .wrap_err("Failed to serialise output into JSON")?;
             stdout()
                 .execute(Print(json))
                 .map_err(io_err_into_diagnostic)?;
         }
+        OutputFormat::Bitmap => {
+            let mut writer = output.bitmap_writer(conf)?;
+            placements
+                .iter()
+                .try_for_each(|result| writer.write(result))
+                .and_then(|()| writer.flush())
+                .map_err(io_err_into_diagnostic)
+                .wrap_err("Failed to write the bitmap")?;
+        }
+        OutputFormat::TermReplay => {
+            let mut writer = output.cast_writer(conf)?;
+            placements
+                .iter()
+                .try_for_each(|result| writer.write(result))
+                .and_then(|()| writer.flush())
+                .map_err(io_err_into_diagnostic)
+                .wrap_err("Failed to write the recording")?;
+        }
+    }
+
+    Ok(())
+}
+
+fn print_placement(
+    result: &PlacementResult,
+    conf: &algorithm::Configuration,
+    output_format: &OutputFormat,
+    render: &app_terminal::RenderConfig,
+) -> Result<()> {
+    match output_format {
+        OutputFormat::Outline => {
+            let outline = result.to_outline(conf, render);
+            let indented: String = outline.lines().map(|line| format!("  {line}\n")).collect();
+            stdout().execute(Print(indented)).map(|_| ())
+        }
+        _ => app_terminal::report_placement(result, conf, render),
+    }
+    .map_err(io_err_into_diagnostic)
+}
+
+/// Prints placements one under another, or side by side with `--columns`
+struct PlacementPrinter<'a> {
+    conf: &'a algorithm::Configuration,
+    output_format: &'a OutputFormat,
+    render: &'a app_terminal::RenderConfig,
+    columns: Option<NonZeroUsize>,
+    /// Labelled placements waiting for the row to fill up
+    row: Vec<(String, Vec<String>)>,
+    printed: usize,
+}
+
+impl PlacementPrinter<'_> {
+    fn print(&mut self, result: &PlacementResult) -> Result<()> {
+        let fill = format!("{:.1}%", result.fill_percent(self.conf));
+        let Some(columns) = self.columns else {
+            print_placement(result, self.conf, self.output_format, self.render)?;
+            return stdout()
+                .execute(Print(format!(
+                    "  Filled: {fill}\n{}",
+                    self.render.placement_gap()
+                )))
+                .map(|_| ())
+                .map_err(io_err_into_diagnostic);
+        };
+
+        let lines = match self.output_format {
+            OutputFormat::Outline => result
+                .to_outline(self.conf, self.render)
+                .lines()
+                .map(str::to_owned)
+                .collect(),
+            _ => app_terminal::placement_lines(result, self.conf, self.render)
+                .map_err(io_err_into_diagnostic)?,
+        };
+        let label = format!("#{} {fill}", self.printed + self.row.len() + 1);
+        self.row.push((label, lines));
+        if self.row.len() == columns.get() {
+            self.print_row(columns)?;
+        }
+        Ok(())
+    }
+
+    /// Prints the last row, which may be shorter than the others
+    fn finish(mut self) -> Result<()> {
+        match self.columns {
+            Some(columns) if !self.row.is_empty() => self.print_row(columns),
+            _ => Ok(()),
+        }
+    }
+
+    fn print_row(&mut self, columns: NonZeroUsize) -> Result<()> {
+        let width = match self.output_format {
+            // plain text, so it can be measured
+            OutputFormat::Outline => self.row[0].1[0].chars().count(),
+            _ => app_terminal::placement_width(self.conf, self.render),
+        };
+        let lines = app_terminal::arrange_in_columns(&self.row, width, columns, self.render);
+        let text: String = lines.iter().map(|line| format!("  {line}\n")).collect();
+        stdout()
+            .execute(Print(text + &self.render.placement_gap()))
+            .map_err(io_err_into_diagnostic)?;
+        self.printed += self.row.len();
+        self.row.clear();
+        Ok(())
+    }
+}
+
+fn print_usage(usage: &BTreeMap<usize, usize>) -> Result<()> {
+    let mut table = String::from("\n  Tetra  Used\n");
+    for (id, used) in usage {
+        table += &format!("  {id:>5}  {}\n", group_thousands(*used));
+    }
+    stdout()
+        .execute(Print(table))
+        .map_err(io_err_into_diagnostic)?;
+    Ok(())
+}
+
+fn print_heatmap(
+    heatmap: &Grid<usize>,
+    placements: usize,
+    conf: &algorithm::Configuration,
+    render: &app_terminal::RenderConfig,
+) -> Result<()> {
+    stdout()
+        .execute(Print("\n  Covered by tenths of placements:\n"))
+        .map_err(io_err_into_diagnostic)?;
+    app_terminal::report_heatmap(heatmap, placements, conf, render).map_err(io_err_into_diagnostic)
+}
+
+fn print_kinds(kinds: &BTreeMap<Option<PieceKind>, usize>) -> Result<()> {
+    let mut table = String::from("\n  Kind  Placements\n");
+    for (kind, count) in kinds {
+        // the top left cell is left free
+        let kind = kind.map_or("-".to_owned(), |kind| format!("{kind:?}"));
+        table += &format!("  {kind:>4}  {}\n", group_thousands(*count));
+    }
+    stdout()
+        .execute(Print(table))
+        .map_err(io_err_into_diagnostic)?;
+    Ok(())
+}
+
+/// Produces the output from the spill file, without reading it into memory all at once
+fn report_spilled(
+    path: &std::path::Path,
+    conf: &algorithm::Configuration,
+    output_format: &OutputFormat,
+    output: &Output,
+    elapsed: Duration,
+) -> Result<()> {
+    let placements = File::open(path)
+        .map(|file| structured_output::read_json_lines(BufReader::new(file)))
+        .map_err(io_err_into_diagnostic)
+        .wrap_err_with(|| format!("Failed to read spilled results from {}", path.display()))?;
+    let to_result = |placement: std::io::Result<structured_output::Placement>| {
+        placement
+            .map_err(io_err_into_diagnostic)
+            .and_then(|placement| {
+                placement
+                    .to_result(conf.size)
+                    .ok_or_else(|| miette!("Placement doesn't fit into the field"))
+            })
+            .wrap_err_with(|| format!("Failed to read spilled results from {}", path.display()))
+    };
+
+    match output_format {
+        OutputFormat::Default | OutputFormat::Outline => {
+            let mut count = 0;
+            let mut usage = BTreeMap::<usize, usize>::new();
+            let mut kinds = BTreeMap::<Option<PieceKind>, usize>::new();
+            let mut heatmap = Grid::init(conf.size.rows, conf.size.cols, 0);
+            let mut printer = output.placement_printer(conf, output_format);
+            for placement in placements {
+                let result = to_result(placement)?;
+                algorithm::add_coverage(&mut heatmap, &result);
+                for (id, used) in algorithm::aggregate_piece_usage([&result]) {
+                    *usage.entry(id).or_default() += used;
+                }
+                for (kind, count) in algorithm::count_by_leading_kind([&result], conf) {
+                    *kinds.entry(kind).or_default() += count;
+                }
+                printer.print(&result)?;
+                count += 1;
+            }
+            printer.finish()?;
+
+            stdout()
+                .execute(Print(format!(
+                    "\n  Found placements: {} (time: {elapsed:.2?})\n",
+                    group_thousands(count)
+                )))
+                .map_err(io_err_into_diagnostic)?;
+            if output.usage_summary {
+                print_usage(&usage)?;
+            }
+            if output.heatmap {
+                print_heatmap(&heatmap, count, conf, output.render)?;
+            }
+            if output.count_by_kind {
+                print_kinds(&kinds)?;
+            }
+        }
+        OutputFormat::Json => {
+            let mut out = BufWriter::new(stdout());
+            structured_output::write_streaming(
+                &mut out,
+                conf,
+                Some(output.source),
+                placements,
+                output.usage_summary,
+                output.heatmap,
+                output.include_empty_solution,
+            )
+            .and_then(|()| out.flush())
+            .map_err(io_err_into_diagnostic)
+            .wrap_err("Failed to write spilled results as JSON")?;
+        }
+        OutputFormat::Bitmap => {
+            let mut writer = output.bitmap_writer(conf)?;
+            for placement in placements {
+                writer
+                    .write(&to_result(placement)?)
+                    .map_err(io_err_into_diagnostic)
+                    .wrap_err("Failed to write the bitmap")?;
+            }
+            writer
+                .flush()
+                .map_err(io_err_into_diagnostic)
+                .wrap_err("Failed to write the bitmap")?;
+        }
+        OutputFormat::TermReplay => {
+            let mut writer = output.cast_writer(conf)?;
+            for placement in placements {
+                writer
+                    .write(&to_result(placement)?)
+                    .map_err(io_err_into_diagnostic)
+                    .wrap_err("Failed to write the recording")?;
+            }
+            writer
+                .flush()
+                .map_err(io_err_into_diagnostic)
+                .wrap_err("Failed to write the recording")?;
+        }
     }
 
     Ok(())
@@ -1,5 +1,7 @@
 use crate::util::{Pos, Size};
-use miette::{miette, Diagnostic, Report, SourceSpan};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use miette::{Diagnostic, LabeledSpan, Report, SourceCode, SourceSpan};
 use std::collections::HashSet;
 use thiserror::Error;
 
@@ -7,16 +9,38 @@ use thiserror::Error;
 pub struct ParsedField {
     pub size: Size,
     pub unavailable: HashSet<Pos>,
+    /// Cells which are not part of the field, for non-rectangular fields
+    pub outside: HashSet<Pos>,
+    /// Empty cells which tetras might cover, but don't have to, see
+    /// [`crate::algorithm::Configuration::with_optional`]
+    pub optional: HashSet<Pos>,
 }
 
 #[derive(Debug, Error, PartialEq, Diagnostic)]
 pub enum ParseError {
-    #[error("Unexpected character")]
+    #[error("Empty input")]
+    EmptyInput,
+    #[error("Unexpected character at {pos}")]
     UnexpectedCharacter {
-        #[label("Expected '{char_busy}' for busy or '{char_empty}' for empty")]
+        /// Row and column of the character in the field
+        pos: Pos,
+        #[label(
+            "Expected '{char_busy}' for busy, '{char_empty}' for empty or '{char_outside}' for outside"
+        )]
         loc: SourceSpan,
         char_busy: char,
         char_empty: char,
+        char_outside: char,
+    },
+    #[error("Tab character at {pos}")]
+    #[diagnostic(help(
+        "Tabs are shown with a varying width, so replace them with a character per cell"
+    ))]
+    TabCharacter {
+        /// Row and column of the tab in the field
+        pos: Pos,
+        #[label("this tab")]
+        loc: SourceSpan,
     },
     #[error("Fickle row length")]
     FickleRowLength {
@@ -37,11 +61,185 @@ pub enum ParseError {
         #[label("here")]
         short_row_span: SourceSpan,
     },
+    #[error("Field without a name")]
+    MissingBoardHeader {
+        #[label("Expected a `=== <NAME> ===` header before the field")]
+        loc: SourceSpan,
+    },
+    #[error("Board {name} is empty")]
+    EmptyBoard {
+        name: String,
+        #[label("No field after this header")]
+        header: SourceSpan,
+    },
+    #[error("Bad field size")]
+    BadBitsSize {
+        #[label("Expected `<ROWS>x<COLS>` with at least one row and one column")]
+        loc: SourceSpan,
+    },
+    #[error("Bad field bits")]
+    BadBits {
+        #[label("{reason}")]
+        loc: SourceSpan,
+        reason: String,
+    },
+}
+
+impl ParseError {
+    /// Row and column of the bad cell in the field, for the errors about a single cell
+    pub fn pos(&self) -> Option<Pos> {
+        match self {
+            ParseError::UnexpectedCharacter { pos, .. } | ParseError::TabCharacter { pos, .. } => {
+                Some(*pos)
+            }
+            _ => None,
+        }
+    }
+
+    /// Name of the variant, e.g. to tell errors apart in the JSON output
+    pub fn kind(&self) -> &'static str {
+        match self {
+            ParseError::EmptyInput => "EmptyInput",
+            ParseError::UnexpectedCharacter { .. } => "UnexpectedCharacter",
+            ParseError::TabCharacter { .. } => "TabCharacter",
+            ParseError::FickleRowLength { .. } => "FickleRowLength",
+            ParseError::NotEnoughRows { .. } => "NotEnoughRows",
+            ParseError::NotEnoughColumns { .. } => "NotEnoughColumns",
+            ParseError::MissingBoardHeader { .. } => "MissingBoardHeader",
+            ParseError::EmptyBoard { .. } => "EmptyBoard",
+            ParseError::BadBitsSize { .. } => "BadBitsSize",
+            ParseError::BadBits { .. } => "BadBits",
+        }
+    }
+
+    /// Moves the spans, for errors in a part of a larger source
+    fn offset_by(self, offset: usize) -> Self {
+        let shift = |span: SourceSpan| (span.offset() + offset, span.len()).into();
+        match self {
+            ParseError::EmptyInput => ParseError::EmptyInput,
+            ParseError::UnexpectedCharacter {
+                pos,
+                loc,
+                char_busy,
+                char_empty,
+                char_outside,
+            } => ParseError::UnexpectedCharacter {
+                pos,
+                loc: shift(loc),
+                char_busy,
+                char_empty,
+                char_outside,
+            },
+            ParseError::TabCharacter { pos, loc } => ParseError::TabCharacter {
+                pos,
+                loc: shift(loc),
+            },
+            ParseError::FickleRowLength {
+                reference_row,
+                bad_row,
+                len_reference,
+                len_actual,
+            } => ParseError::FickleRowLength {
+                reference_row: shift(reference_row),
+                bad_row: shift(bad_row),
+                len_reference,
+                len_actual,
+            },
+            ParseError::NotEnoughRows { all_rows_span } => ParseError::NotEnoughRows {
+                all_rows_span: shift(all_rows_span),
+            },
+            ParseError::NotEnoughColumns { short_row_span } => ParseError::NotEnoughColumns {
+                short_row_span: shift(short_row_span),
+            },
+            ParseError::MissingBoardHeader { loc } => {
+                ParseError::MissingBoardHeader { loc: shift(loc) }
+            }
+            ParseError::EmptyBoard { name, header } => ParseError::EmptyBoard {
+                name,
+                header: shift(header),
+            },
+            ParseError::BadBitsSize { loc } => ParseError::BadBitsSize { loc: shift(loc) },
+            ParseError::BadBits { loc, reason } => ParseError::BadBits {
+                loc: shift(loc),
+                reason,
+            },
+        }
+    }
+}
+
+/// [`ParseError`] with the input it is in, so that the report could point into the input and
+/// the error could still be found with [`Report::downcast_ref`], unlike with
+/// [`Report::with_source_code`]
+#[derive(Debug, Error)]
+#[error("{error}")]
+pub struct SourcedParseError {
+    pub error: ParseError,
+    source_code: String,
+    /// See [`Parser::with_row_delimiter`]
+    row_delimiter: char,
+}
+
+impl SourcedParseError {
+    pub fn new(error: ParseError, source_code: &str) -> Self {
+        Self {
+            error,
+            source_code: source_code.to_owned(),
+            row_delimiter: '\n',
+        }
+    }
+
+    fn with_row_delimiter(mut self, row_delimiter: char) -> Self {
+        self.row_delimiter = row_delimiter;
+        self
+    }
+
+    /// Rows of the field with the bad cell and the cell itself, see [`ParseError::pos`]. Only the
+    /// field with the cell is taken from an input of several ones: the rows end before an empty
+    /// one or one of another length, e.g. a header
+    pub fn bad_cell(&self) -> Option<(Vec<&str>, Pos)> {
+        let pos = self.error.pos()?;
+        let offset = self.error.labels()?.next()?.offset();
+        let rows: Vec<_> =
+            iter_str_offsets::rows_with_offsets(&self.source_code, self.row_delimiter).collect();
+        let bad_row = rows.iter().rposition(|row| row.offset <= offset)?;
+        let first_row = bad_row.checked_sub(pos.row)?;
+        let cols = rows[bad_row].line.chars().count();
+        let field = rows[first_row..]
+            .iter()
+            .map(|row| row.line)
+            .take_while(|line| !line.trim().is_empty() && line.chars().count() == cols)
+            .collect();
+        Some((field, pos))
+    }
+}
+
+impl Diagnostic for SourcedParseError {
+    fn help<'a>(&'a self) -> Option<Box<dyn std::fmt::Display + 'a>> {
+        self.error.help()
+    }
+
+    fn labels(&self) -> Option<Box<dyn Iterator<Item = LabeledSpan> + '_>> {
+        self.error.labels()
+    }
+
+    fn source_code(&self) -> Option<&dyn SourceCode> {
+        Some(&self.source_code)
+    }
 }
 
 pub struct Parser {
     char_empty: char,
     char_busy: char,
+    char_outside: char,
+    char_optional: Option<char>,
+    row_delimiter: char,
+}
+
+/// `-` for empty cells, `x` for busy ones, the same as the CLI defaults
+impl Default for Parser {
+    fn default() -> Self {
+        Self::new('-', 'x')
+    }
 }
 
 impl Parser {
@@ -49,53 +247,230 @@ impl Parser {
         Self {
             char_empty,
             char_busy,
+            char_outside: ' ',
+            char_optional: None,
+            row_delimiter: '\n',
         }
     }
 
+    /// Character of cells which are not part of the field, space by default
+    pub fn with_char_outside(mut self, char_outside: char) -> Self {
+        self.char_outside = char_outside;
+        self
+    }
+
+    /// Character of optional cells, none by default
+    pub fn with_char_optional(mut self, char_optional: char) -> Self {
+        self.char_optional = Some(char_optional);
+        self
+    }
+
+    /// Character which ends a row, a line break by default. Another one, e.g. `/`, allows to write
+    /// the field in a single line like `----/-x--/----`, and spans of errors point into that line.
+    /// Only [`Parser::parse`] and [`Parser::parse_without_source_code`] use it
+    pub fn with_row_delimiter(mut self, row_delimiter: char) -> Self {
+        self.row_delimiter = row_delimiter;
+        self
+    }
+
+    fn is_cell_char(&self, char: char) -> bool {
+        [self.char_empty, self.char_busy, self.char_outside].contains(&char)
+            || Some(char) == self.char_optional
+    }
+
     pub fn parse(&self, field: impl AsRef<str>) -> Result<ParsedField, Report> {
         let field_str = field.as_ref();
 
-        self.parse_without_source_code(field_str)
-            .map_err(|report| report.with_source_code(field_str.to_owned()))
+        self.parse_without_source_code(field_str).map_err(|err| {
+            Report::new(
+                SourcedParseError::new(err, field_str).with_row_delimiter(self.row_delimiter),
+            )
+        })
     }
 
-    fn parse_without_source_code(&self, field: impl AsRef<str>) -> Result<ParsedField, Report> {
+    /// Parses several fields, each preceded by a `=== <NAME> ===` header line. Empty lines
+    /// between fields are ignored.
+    pub fn parse_named(
+        &self,
+        input: impl AsRef<str>,
+    ) -> Result<Vec<(String, ParsedField)>, Report> {
+        let input = input.as_ref();
+
+        self.parse_named_without_source_code(input)
+            .map_err(|err| Report::new(SourcedParseError::new(err, input)))
+    }
+
+    fn parse_named_without_source_code(
+        &self,
+        input: &str,
+    ) -> Result<Vec<(String, ParsedField)>, ParseError> {
+        struct Board<'a> {
+            name: &'a str,
+            header: SourceSpan,
+            /// Non-empty lines of the field, with their offsets
+            lines: Vec<(usize, &'a str)>,
+        }
+
+        let mut boards: Vec<Board> = Vec::new();
+        for iter_str_offsets::LineOffset { line, offset } in
+            iter_str_offsets::lines_with_offsets(input)
+        {
+            let name = line
+                .strip_prefix("===")
+                .and_then(|rest| rest.strip_suffix("==="))
+                .map(str::trim);
+            if let Some(name) = name {
+                boards.push(Board {
+                    name,
+                    header: (offset, line.len()).into(),
+                    lines: Vec::new(),
+                });
+            } else if !line.is_empty() {
+                let Some(board) = boards.last_mut() else {
+                    return Err(ParseError::MissingBoardHeader {
+                        loc: (offset, line.len()).into(),
+                    });
+                };
+                board.lines.push((offset, line));
+            }
+        }
+
+        if boards.is_empty() {
+            return Err(ParseError::EmptyInput);
+        }
+
+        boards
+            .into_iter()
+            .map(|board| {
+                let (Some((start, _)), Some((last, last_line))) =
+                    (board.lines.first(), board.lines.last())
+                else {
+                    return Err(ParseError::EmptyBoard {
+                        name: board.name.to_owned(),
+                        header: board.header,
+                    });
+                };
+                let field = self
+                    .parse_without_source_code(&input[*start..last + last_line.len()])
+                    .map_err(|err| err.offset_by(*start))?;
+                Ok((board.name.to_owned(), field))
+            })
+            .collect()
+    }
+
+    /// Parses several independent fields separated by empty lines. A line like `---` couldn't
+    /// separate them, since it is a row of empty cells
+    pub fn parse_many(&self, input: impl AsRef<str>) -> Result<Vec<ParsedField>, Report> {
+        let input = input.as_ref();
+
+        self.parse_many_without_source_code(input)
+            .map_err(|err| Report::new(SourcedParseError::new(err, input)))
+    }
+
+    /// Like [`Parser::parse_many`], but the error isn't attached to the source code, so that it
+    /// could be matched
+    pub fn parse_many_without_source_code(
+        &self,
+        input: &str,
+    ) -> Result<Vec<ParsedField>, ParseError> {
+        // byte ranges of the fields, without the separating lines
+        let mut ranges: Vec<(usize, usize)> = Vec::new();
+        let mut in_field = false;
+        for iter_str_offsets::LineOffset { line, offset } in
+            iter_str_offsets::lines_with_offsets(input)
+        {
+            if line.is_empty() {
+                in_field = false;
+            } else if in_field {
+                if let Some((_, end)) = ranges.last_mut() {
+                    *end = offset + line.len();
+                }
+            } else {
+                in_field = true;
+                ranges.push((offset, offset + line.len()));
+            }
+        }
+
+        if ranges.is_empty() {
+            return Err(ParseError::EmptyInput);
+        }
+
+        ranges
+            .into_iter()
+            .map(|(start, end)| {
+                self.parse_without_source_code(&input[start..end])
+                    .map_err(|err| err.offset_by(start))
+            })
+            .collect()
+    }
+
+    /// Like [`Parser::parse`], but the error isn't attached to the source code, so that it could
+    /// be matched
+    pub fn parse_without_source_code(
+        &self,
+        field: impl AsRef<str>,
+    ) -> Result<ParsedField, ParseError> {
         let source_code = field.as_ref();
 
+        // counted in characters, while spans are in bytes, which differ for non-ASCII characters
         let mut cols = 0usize;
+        let mut reference_row: SourceSpan = (0, 0).into();
         let mut rows = 0;
         let mut unavailable = HashSet::new();
+        let mut outside = HashSet::new();
+        let mut optional = HashSet::new();
 
         for (row, iter_str_offsets::LineOffset { line, offset }) in
-            iter_str_offsets::lines_with_offsets(&source_code).enumerate()
+            iter_str_offsets::rows_with_offsets(source_code, self.row_delimiter).enumerate()
         {
-            let line_len = line.len();
+            let line_len = line.chars().count();
+
+            // a tab often stands for several cells, which would be reported as a wrong row length
+            if !self.is_cell_char('\t') {
+                if let Some((col, (char_offset, _))) = line
+                    .char_indices()
+                    .enumerate()
+                    .find(|(_, (_, char))| *char == '\t')
+                {
+                    return Err(ParseError::TabCharacter {
+                        pos: Pos::new(row, col),
+                        loc: (offset + char_offset, 1).into(),
+                    });
+                }
+            }
 
             if cols == 0 {
                 cols = line_len;
+                reference_row = (offset, line.len()).into();
                 if cols < 2 {
                     return Err(ParseError::NotEnoughColumns {
                         short_row_span: (offset, line.len()).into(),
-                    })?;
+                    });
                 }
             } else if line_len != cols {
                 return Err(ParseError::FickleRowLength {
-                    reference_row: (0, cols).into(),
-                    bad_row: (offset, line_len).into(),
+                    reference_row,
+                    bad_row: (offset, line.len()).into(),
                     len_reference: cols,
                     len_actual: line_len,
-                })?;
+                });
             }
 
-            for (col, char) in line.chars().enumerate() {
+            for (col, (char_offset, char)) in line.char_indices().enumerate() {
                 if char == self.char_busy {
                     unavailable.insert(Pos::new(row, col));
+                } else if char == self.char_outside {
+                    outside.insert(Pos::new(row, col));
+                } else if Some(char) == self.char_optional {
+                    optional.insert(Pos::new(row, col));
                 } else if char != self.char_empty {
                     return Err(ParseError::UnexpectedCharacter {
-                        loc: (offset + col, 1).into(),
+                        pos: Pos::new(row, col),
+                        loc: (offset + char_offset, char.len_utf8()).into(),
                         char_empty: self.char_empty,
                         char_busy: self.char_busy,
-                    })?;
+                        char_outside: self.char_outside,
+                    });
                 }
             }
 
@@ -103,39 +478,115 @@ impl Parser {
         }
 
         if rows == 0 {
-            return Err(miette!("Empty input"));
+            return Err(ParseError::EmptyInput);
         }
 
         if rows < 2 {
             return Err(ParseError::NotEnoughRows {
                 all_rows_span: (0, source_code.len()).into(),
-            })?;
+            });
         }
 
         Ok(ParsedField {
             size: Size::new(rows, cols),
             unavailable,
+            outside,
+            optional,
         })
     }
 }
 
-mod iter_str_offsets {
+/// Encodes unavailable cells compactly: the field size as `<ROWS>x<COLS>`, then a line of base64
+/// of a bit per cell, in row-major order starting from the lowest bit, where set bits are
+/// unavailable cells. Outside cells are not encoded.
+pub fn encode_bits(size: Size, unavailable: &HashSet<Pos>) -> String {
+    let mut bits = vec![0u8; (size.rows * size.cols).div_ceil(8)];
+    for pos in unavailable.iter().filter(|pos| pos.in_bounds(&size)) {
+        let bit = pos.row * size.cols + pos.col;
+        bits[bit / 8] |= 1 << (bit % 8);
+    }
+    format!("{}x{}\n{}", size.rows, size.cols, BASE64.encode(bits))
+}
+
+/// Reads the field written with [`encode_bits`]
+pub fn parse_bits(input: impl AsRef<str>) -> Result<ParsedField, Report> {
+    let input = input.as_ref();
+
+    parse_bits_without_source_code(input)
+        .map_err(|err| Report::new(SourcedParseError::new(err, input)))
+}
+
+/// Like [`parse_bits`], but the error isn't attached to the source code, so that it could be
+/// matched
+pub fn parse_bits_without_source_code(input: &str) -> Result<ParsedField, ParseError> {
+    let (size_str, rest) = input.split_once('\n').unwrap_or((input, ""));
+    if size_str.trim().is_empty() && rest.trim().is_empty() {
+        return Err(ParseError::EmptyInput);
+    }
+    let size = size_str
+        .trim()
+        .parse::<Size>()
+        .ok()
+        .filter(|size| size.rows > 0 && size.cols > 0)
+        .ok_or(ParseError::BadBitsSize {
+            loc: (0, size_str.len()).into(),
+        })?;
+
+    let bits_loc = (size_str.len() + 1, rest.trim_end().len()).into();
+    let bad_bits = |reason: String| ParseError::BadBits {
+        loc: bits_loc,
+        reason,
+    };
+    let bits = BASE64
+        .decode(rest.trim())
+        .map_err(|err| bad_bits(format!("Not a valid base64: {err}")))?;
+    let cells = size.rows * size.cols;
+    if bits.len() != cells.div_ceil(8) {
+        return Err(bad_bits(format!(
+            "Expected {} bytes for {cells} cells, found {}",
+            cells.div_ceil(8),
+            bits.len()
+        )));
+    }
+    if (cells..bits.len() * 8).any(|bit| bits[bit / 8] & (1 << (bit % 8)) != 0) {
+        return Err(bad_bits("Bits beyond the last cell are set".to_owned()));
+    }
+
+    let unavailable = (0..cells)
+        .filter(|bit| bits[bit / 8] & (1 << (bit % 8)) != 0)
+        .map(|bit| Pos::new(bit / size.cols, bit % size.cols))
+        .collect();
+    Ok(ParsedField {
+        size,
+        unavailable,
+        outside: HashSet::new(),
+        optional: HashSet::new(),
+    })
+}
+
+pub(crate) mod iter_str_offsets {
     /// Same as [`str::lines`], but also yields line offset
     pub fn lines_with_offsets(source: &str) -> impl Iterator<Item = LineOffset<'_>> {
+        rows_with_offsets(source, '\n')
+    }
+
+    /// Like [`lines_with_offsets`], but rows end with the delimiter. `\r` is stripped only
+    /// before a line break
+    pub fn rows_with_offsets(
+        source: &str,
+        delimiter: char,
+    ) -> impl Iterator<Item = LineOffset<'_>> {
         source
-            .split_inclusive("\n")
+            .split_inclusive(delimiter)
             .str_offsets()
-            .map(|(offset, line)| {
-                fn strip_n_r(line: &str) -> &str {
-                    let Some(line) = line.strip_suffix('\n') else { return line };
-                    let Some(line) = line.strip_suffix('\r') else { return line };
-                    line
-                }
-
-                LineOffset {
-                    offset,
-                    line: strip_n_r(line),
-                }
+            .map(move |(offset, line)| {
+                let line = match line.strip_suffix(delimiter) {
+                    Some(line) if delimiter == '\n' => line.strip_suffix('\r').unwrap_or(line),
+                    Some(line) => line,
+                    None => line,
+                };
+
+                LineOffset { offset, line }
             })
     }
 
@@ -189,6 +640,7 @@ mod iter_str_offsets {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::algorithm::{Configuration, NoopStats, SearchMode};
 
     fn factory() -> Parser {
         Parser::new('-', '+')
@@ -202,7 +654,9 @@ mod tests {
             parser.parse_without_source_code("--\n--"),
             Ok(ParsedField {
                 size: Size::new(2, 2),
-                unavailable: HashSet::new()
+                unavailable: HashSet::new(),
+                outside: HashSet::new(),
+                optional: HashSet::new(),
             })
         );
     }
@@ -220,7 +674,9 @@ mod tests {
                     set.insert(Pos::new(0, 2));
                     set.insert(Pos::new(1, 1));
                     set
-                }
+                },
+                outside: HashSet::new(),
+                optional: HashSet::new(),
             })
         );
     }
@@ -232,10 +688,247 @@ mod tests {
         assert_eq!(
             parser.parse_without_source_code("---\n--#"),
             Err(ParseError::UnexpectedCharacter {
+                pos: Pos::new(1, 2),
                 loc: (6, 1).into(),
                 char_busy: '+',
                 char_empty: '-',
+                char_outside: ' ',
             })
         );
     }
+
+    #[test]
+    fn tab_is_reported_instead_of_row_length() {
+        let err = factory()
+            .parse_without_source_code("----\n-\t-\n----")
+            .unwrap_err();
+
+        assert_eq!(
+            err,
+            ParseError::TabCharacter {
+                pos: Pos::new(1, 1),
+                loc: (6, 1).into(),
+            }
+        );
+        assert_eq!(err.to_string(), "Tab character at (1, 1)");
+    }
+
+    #[test]
+    fn single_line_spans_point_into_it() {
+        let parser = factory().with_row_delimiter('/');
+
+        let field = parser.parse_without_source_code("----/-+--/----/").unwrap();
+        assert_eq!(field.size, Size::new(3, 4));
+        assert_eq!(field.unavailable, [Pos::new(1, 1)].into());
+
+        assert!(matches!(
+            parser.parse_without_source_code("---/--#"),
+            Err(ParseError::UnexpectedCharacter { pos, loc, .. })
+                if pos == Pos::new(1, 2) && loc == (6, 1).into()
+        ));
+    }
+
+    #[test]
+    fn unexpected_char_message_has_coordinates() {
+        let err = factory()
+            .parse_without_source_code("----\n----\n---#")
+            .unwrap_err();
+
+        assert_eq!(err.to_string(), "Unexpected character at (2, 3)");
+    }
+
+    #[test]
+    fn spans_of_multibyte_chars_are_in_bytes() {
+        let parser = Parser::new(' ', '█').with_char_outside('.');
+
+        assert_eq!(
+            parser.parse_without_source_code("█  \n █ "),
+            Ok(ParsedField {
+                size: Size::new(2, 3),
+                unavailable: [Pos::new(0, 0), Pos::new(1, 1)].into_iter().collect(),
+                outside: HashSet::new(),
+                optional: HashSet::new(),
+            })
+        );
+        assert_eq!(
+            parser.parse_without_source_code("█  \n █x"),
+            Err(ParseError::UnexpectedCharacter {
+                pos: Pos::new(1, 2),
+                loc: (10, 1).into(),
+                char_busy: '█',
+                char_empty: ' ',
+                char_outside: '.',
+            })
+        );
+        assert_eq!(
+            parser.parse_without_source_code("█ █\n█ ▓"),
+            Err(ParseError::UnexpectedCharacter {
+                pos: Pos::new(1, 2),
+                loc: (12, 3).into(),
+                char_busy: '█',
+                char_empty: ' ',
+                char_outside: '.',
+            })
+        );
+        assert_eq!(
+            parser.parse_without_source_code("██\n█ █"),
+            Err(ParseError::FickleRowLength {
+                reference_row: (0, 6).into(),
+                bad_row: (7, 7).into(),
+                len_reference: 2,
+                len_actual: 3,
+            })
+        );
+    }
+
+    #[test]
+    fn bits_round_trip() {
+        let field = factory()
+            .parse_without_source_code("-----\n--+--\n+---+\n-----\n----+")
+            .unwrap();
+
+        let encoded = encode_bits(field.size, &field.unavailable);
+
+        assert!(encoded.starts_with("5x5\n"));
+        assert_eq!(parse_bits_without_source_code(&encoded), Ok(field));
+    }
+
+    #[test]
+    fn bad_bits() {
+        assert_eq!(
+            parse_bits_without_source_code("5x\nAAAAAA=="),
+            Err(ParseError::BadBitsSize { loc: (0, 2).into() })
+        );
+        assert!(matches!(
+            parse_bits_without_source_code("2x2\nAAAA"),
+            Err(ParseError::BadBits { loc, .. }) if loc == (4, 4).into()
+        ));
+        // 4 cells, but the fifth bit is set
+        assert!(matches!(
+            parse_bits_without_source_code("2x2\nEA=="),
+            Err(ParseError::BadBits { .. })
+        ));
+    }
+
+    #[test]
+    fn parses_outside_cells() {
+        let parser = factory().with_char_outside('.');
+
+        assert_eq!(
+            parser.parse_without_source_code("--.\n-+-"),
+            Ok(ParsedField {
+                size: Size::new(2, 3),
+                unavailable: [Pos::new(1, 1)].into_iter().collect(),
+                outside: [Pos::new(0, 2)].into_iter().collect(),
+                optional: HashSet::new(),
+            })
+        );
+    }
+
+    #[test]
+    fn parses_named_boards() {
+        let parser = factory();
+
+        let boards = parser
+            .parse_named_without_source_code(
+                "=== first ===\n--\n-+\n\n=== second ===\n---\n---\n---\n",
+            )
+            .unwrap();
+
+        let summary: Vec<_> = boards
+            .iter()
+            .map(|(name, field)| (name.as_str(), field.size))
+            .collect();
+        assert_eq!(
+            summary,
+            [("first", Size::new(2, 2)), ("second", Size::new(3, 3))]
+        );
+        assert!(boards[0].1.unavailable.contains(&Pos::new(1, 1)));
+    }
+
+    #[test]
+    fn parses_and_solves_several_boards() {
+        let parser = factory();
+
+        let boards = parser
+            .parse_many_without_source_code("----\n----\n\n---\n-+-\n---\n\n")
+            .unwrap();
+
+        assert_eq!(boards.len(), 2);
+        assert_eq!(boards[0].size, Size::new(2, 4));
+        assert_eq!(boards[1].size, Size::new(3, 3));
+        let found: Vec<_> = boards
+            .into_iter()
+            .map(|field| {
+                Configuration::from_parsed(field)
+                    .with_mode(SearchMode::Exhaustive)
+                    .with_complete(true)
+                    .run(&mut NoopStats)
+                    .len()
+            })
+            .collect();
+        // the ring around the busy cell could be cut into two L-s or J-s in four ways
+        assert_eq!(found, [4, 4]);
+    }
+
+    #[test]
+    fn many_boards_errors_point_into_the_whole_input() {
+        assert_eq!(
+            factory().parse_many_without_source_code("--\n--\n\n--\n-x"),
+            Err(ParseError::UnexpectedCharacter {
+                pos: Pos::new(1, 1),
+                loc: (11, 1).into(),
+                char_busy: '+',
+                char_empty: '-',
+                char_outside: ' ',
+            })
+        );
+    }
+
+    #[test]
+    fn bad_cell_is_found_in_its_field() {
+        let bad_cell = |report: Report| {
+            let sourced = report.downcast_ref::<SourcedParseError>().unwrap();
+            sourced.bad_cell().map(|(rows, pos)| (rows.join("\n"), pos))
+        };
+
+        assert_eq!(
+            bad_cell(
+                factory()
+                    .parse_many("--\n--\n\n---\n-#-\n---\n")
+                    .unwrap_err()
+            ),
+            Some(("---\n-#-\n---".to_owned(), Pos::new(1, 1)))
+        );
+        assert_eq!(
+            bad_cell(
+                factory()
+                    .with_row_delimiter('/')
+                    .parse("---/--#")
+                    .unwrap_err()
+            ),
+            Some(("---\n--#".to_owned(), Pos::new(1, 2)))
+        );
+        assert_eq!(bad_cell(factory().parse("---\n--").unwrap_err()), None);
+    }
+
+    #[test]
+    fn named_board_errors_point_into_the_whole_input() {
+        let parser = factory();
+
+        assert_eq!(
+            parser.parse_named_without_source_code("=== a ===\n--\n--\n=== b ===\n--\n-#"),
+            Err(ParseError::UnexpectedCharacter {
+                pos: Pos::new(1, 1),
+                loc: (30, 1).into(),
+                char_busy: '+',
+                char_empty: '-',
+                char_outside: ' ',
+            })
+        );
+        assert_eq!(
+            parser.parse_named_without_source_code("--\n--"),
+            Err(ParseError::MissingBoardHeader { loc: (0, 2).into() })
+        );
+    }
 }
@@ -0,0 +1,168 @@
+//! Named piece sets.
+//!
+//! The search places only tetrominoes, see [`TETRAS`], so each profile picks some of them, and
+//! may turn on the options which go along with them.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::algorithm::Configuration;
+use crate::tetra::{PieceKind, Tetra, TETRAS};
+
+/// Names accepted by [`PieceProfile::lookup`]
+pub const NAMES: [&str; 2] = ["classic-tetris", "no-skew"];
+
+/// Tetras in every orientation the search may place them in
+#[derive(Clone, Debug, PartialEq)]
+pub struct PieceSet {
+    pieces: Vec<&'static Tetra>,
+}
+
+impl PieceSet {
+    /// All orientations of the given kinds, in the order of [`TETRAS`]
+    pub fn of_kinds(kinds: &[PieceKind]) -> Self {
+        Self {
+            pieces: TETRAS
+                .iter()
+                .filter(|tetra| kinds.contains(&PieceKind::of(tetra)))
+                .collect(),
+        }
+    }
+
+    /// The 7 tetrominoes of Tetris in all their rotations, which are the 19 [`TETRAS`]. They are
+    /// never reflected, since reflections of `S` and `L` are the `Z` and `J` kinds
+    pub fn standard_tetris() -> Self {
+        Self::of_kinds(&PieceKind::ALL)
+    }
+
+    pub fn pieces(&self) -> &[&'static Tetra] {
+        &self.pieces
+    }
+
+    /// Distinct shapes regardless of their orientation
+    pub fn kinds(&self) -> BTreeSet<PieceKind> {
+        self.pieces
+            .iter()
+            .map(|tetra| PieceKind::of(tetra))
+            .collect()
+    }
+
+    /// Orientations of each kind
+    pub fn by_kind(&self) -> BTreeMap<PieceKind, Vec<&'static Tetra>> {
+        let mut kinds = BTreeMap::<_, Vec<_>>::new();
+        for tetra in &self.pieces {
+            kinds.entry(PieceKind::of(tetra)).or_default().push(*tetra);
+        }
+        kinds
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct PieceProfile {
+    pub pieces: PieceSet,
+    /// Turns on [`Configuration::gravity`]
+    pub gravity: bool,
+}
+
+impl PieceProfile {
+    /// The profile with one of [`NAMES`]. `classic-tetris` has the 7 one-sided tetrominoes in
+    /// all their rotations, which are all of [`TETRAS`], falling like in Tetris. `no-skew` has
+    /// all of them but the `S` and `Z` kinds
+    pub fn lookup(name: &str) -> Option<Self> {
+        match name {
+            "classic-tetris" => Some(Self {
+                pieces: PieceSet::standard_tetris(),
+                gravity: true,
+            }),
+            "no-skew" => Some(Self {
+                pieces: PieceSet::of_kinds(&[
+                    PieceKind::O,
+                    PieceKind::I,
+                    PieceKind::T,
+                    PieceKind::L,
+                    PieceKind::J,
+                ]),
+                gravity: false,
+            }),
+            _ => None,
+        }
+    }
+
+    /// Sets the pieces of the configuration and turns on the options of the profile, leaving the
+    /// other options as they are
+    pub fn apply(&self, conf: Configuration) -> Configuration {
+        let conf = conf.with_pieces(self.pieces.pieces.clone());
+        if self.gravity {
+            conf.with_gravity(true)
+        } else {
+            conf
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+    use crate::util::Size;
+
+    #[test]
+    fn classic_tetris_has_seven_shapes() {
+        let profile = PieceProfile::lookup("classic-tetris").unwrap();
+
+        assert_eq!(profile.pieces.kinds().len(), 7);
+        assert_eq!(profile.pieces.pieces().len(), TETRAS.len());
+    }
+
+    #[test]
+    fn classic_tetris_falls() {
+        let conf = Configuration::new(Size::new(4, 4), HashSet::new());
+        let profile = PieceProfile::lookup("classic-tetris").unwrap();
+
+        assert!(profile.apply(conf).gravity);
+    }
+
+    #[test]
+    fn profile_keeps_other_options() {
+        let conf = Configuration::new(Size::new(4, 4), HashSet::new()).with_gravity(true);
+        let profile = PieceProfile::lookup("no-skew").unwrap();
+
+        let conf = profile.apply(conf);
+
+        assert!(conf.gravity);
+        assert_eq!(conf.pieces.len(), TETRAS.len() - 4);
+    }
+
+    #[test]
+    fn standard_tetris_is_seven_kinds_in_nineteen_orientations() {
+        let standard = PieceSet::standard_tetris();
+
+        let orientations: Vec<_> = standard
+            .by_kind()
+            .into_iter()
+            .map(|(kind, tetras)| (kind, tetras.len()))
+            .collect();
+
+        assert_eq!(standard.pieces(), TETRAS.iter().collect::<Vec<_>>());
+        assert_eq!(
+            orientations,
+            [
+                (PieceKind::O, 1),
+                (PieceKind::I, 2),
+                (PieceKind::T, 4),
+                (PieceKind::L, 4),
+                (PieceKind::J, 4),
+                (PieceKind::S, 2),
+                (PieceKind::Z, 2),
+            ]
+        );
+    }
+
+    #[test]
+    fn all_names_are_known() {
+        assert!(NAMES
+            .iter()
+            .all(|name| PieceProfile::lookup(name).is_some()));
+        assert_eq!(PieceProfile::lookup("pentominoes"), None);
+    }
+}
@@ -0,0 +1,386 @@
+//! Custom piece shapes.
+//!
+//! Pieces are drawn with `#` for their cells and `.` for the rest of the bounding box, one piece
+//! after another, separated by empty lines.
+
+use std::collections::{BTreeSet, HashSet};
+
+use miette::{Diagnostic, SourceSpan};
+use thiserror::Error;
+
+use crate::parse_field::iter_str_offsets::{self, LineOffset};
+use crate::util::{Pos, Size};
+
+const CHAR_CELL: char = '#';
+const CHAR_EMPTY: char = '.';
+
+#[derive(Debug, Error, PartialEq, Diagnostic)]
+pub enum PieceError {
+    #[error("Unexpected character")]
+    UnexpectedCharacter {
+        #[label("Expected '{CHAR_CELL}' for a cell of the piece or '{CHAR_EMPTY}' for none")]
+        loc: SourceSpan,
+    },
+    #[error("Piece #{index} has no cells")]
+    Empty {
+        index: usize,
+        #[label("this piece")]
+        piece: SourceSpan,
+    },
+    #[error("Piece #{index} falls apart")]
+    Disconnected {
+        index: usize,
+        #[label("Cells of this piece should be connected by their sides")]
+        piece: SourceSpan,
+    },
+    #[error("Piece #{index} has holes")]
+    #[diagnostic(help("Allow such pieces with `allow_holes(true)` if it's intended"))]
+    Holes {
+        index: usize,
+        #[label("Some empty cells are enclosed by the piece")]
+        piece: SourceSpan,
+    },
+    #[error("Piece #{index} is concave")]
+    #[diagnostic(help("Allow such pieces with `allow_concave(true)`"))]
+    Concave {
+        index: usize,
+        #[label("A row or a column of this piece has a gap")]
+        piece: SourceSpan,
+    },
+}
+
+/// Cells of a piece, shifted to the top left corner
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Shape {
+    cells: BTreeSet<Pos>,
+    size: Size,
+}
+
+impl Shape {
+    fn from_cells(cells: BTreeSet<Pos>) -> Self {
+        let top = cells.iter().map(|pos| pos.row).min().unwrap_or(0);
+        let left = cells.iter().map(|pos| pos.col).min().unwrap_or(0);
+        let cells: BTreeSet<_> = cells
+            .into_iter()
+            .map(|pos| Pos::new(pos.row - top, pos.col - left))
+            .collect();
+        let size = Size::new(
+            cells.iter().map(|pos| pos.row + 1).max().unwrap_or(0),
+            cells.iter().map(|pos| pos.col + 1).max().unwrap_or(0),
+        );
+
+        Self { cells, size }
+    }
+
+    pub fn cells(&self) -> &BTreeSet<Pos> {
+        &self.cells
+    }
+
+    /// Size of the bounding box
+    pub fn size(&self) -> Size {
+        self.size
+    }
+
+    fn neighbours(&self, pos: Pos) -> impl Iterator<Item = Pos> + '_ {
+        let Pos { row, col } = pos;
+        [
+            row.checked_sub(1).map(|row| Pos::new(row, col)),
+            Some(Pos::new(row + 1, col)),
+            col.checked_sub(1).map(|col| Pos::new(row, col)),
+            Some(Pos::new(row, col + 1)),
+        ]
+        .into_iter()
+        .flatten()
+        .filter(|pos| pos.in_bounds(&self.size))
+    }
+
+    /// Cells reachable from `start` through cells of the same kind, either of the piece or not
+    fn flood(&self, start: impl IntoIterator<Item = Pos>) -> HashSet<Pos> {
+        let mut reached = HashSet::new();
+        let mut queue: Vec<_> = start.into_iter().collect();
+        while let Some(pos) = queue.pop() {
+            if !reached.insert(pos) {
+                continue;
+            }
+            let inside = self.cells.contains(&pos);
+            queue.extend(
+                self.neighbours(pos)
+                    .filter(|next| self.cells.contains(next) == inside),
+            );
+        }
+        reached
+    }
+
+    fn is_connected(&self) -> bool {
+        let first = self.cells.first().copied();
+        self.flood(first).len() == self.cells.len()
+    }
+
+    /// Whether some empty cells of the bounding box can't reach its border
+    fn has_holes(&self) -> bool {
+        let Size { rows, cols } = self.size;
+        let border = (0..rows)
+            .flat_map(|row| (0..cols).map(move |col| Pos::new(row, col)))
+            .filter(|pos| {
+                pos.row == 0 || pos.col == 0 || pos.row == rows - 1 || pos.col == cols - 1
+            })
+            .filter(|pos| !self.cells.contains(pos));
+        let outer = self.flood(border.collect::<Vec<_>>());
+        rows * cols - self.cells.len() > outer.len()
+    }
+
+    /// Whether some row or column has empty cells between cells of the piece
+    fn is_concave(&self) -> bool {
+        let has_gap = |line: Vec<usize>| {
+            line.first()
+                .zip(line.last())
+                .is_some_and(|(first, last)| last - first + 1 != line.len())
+        };
+        let rows = (0..self.size.rows).map(|row| {
+            self.cells
+                .iter()
+                .filter(|pos| pos.row == row)
+                .map(|pos| pos.col)
+                .collect()
+        });
+        let cols = (0..self.size.cols).map(|col| {
+            let mut rows: Vec<_> = self
+                .cells
+                .iter()
+                .filter(|pos| pos.col == col)
+                .map(|pos| pos.row)
+                .collect();
+            rows.sort_unstable();
+            rows
+        });
+        rows.chain(cols).any(has_gap)
+    }
+}
+
+/// Pieces read with [`PieceLoader::load`]
+#[derive(Debug, PartialEq)]
+pub struct LoadedPieces {
+    /// Distinct shapes, in the order of the first appearance
+    pub shapes: Vec<Shape>,
+    /// Pieces left out since they are the same as an earlier one
+    pub duplicates: Vec<Duplicate>,
+}
+
+/// Indices of pieces in the input, counting from 0
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Duplicate {
+    pub index: usize,
+    pub same_as: usize,
+}
+
+/// Reads custom pieces, rejecting the shapes which are unlikely to be intended.
+///
+/// Pieces should always be connected. By default concave pieces are allowed and pieces with
+/// holes are not.
+pub struct PieceLoader {
+    allow_concave: bool,
+    allow_holes: bool,
+}
+
+impl Default for PieceLoader {
+    fn default() -> Self {
+        Self {
+            allow_concave: true,
+            allow_holes: false,
+        }
+    }
+}
+
+impl PieceLoader {
+    /// Whether a row or a column of a piece could have a gap, like the one of a `U` shape.
+    /// Tetrominoes have none
+    pub fn allow_concave(mut self, value: bool) -> Self {
+        self.allow_concave = value;
+        self
+    }
+
+    /// Whether a piece could enclose empty cells of its bounding box, like a ring does. Such
+    /// pieces are concave too, so both should be allowed to accept them
+    pub fn allow_holes(mut self, value: bool) -> Self {
+        self.allow_holes = value;
+        self
+    }
+
+    /// Reads the pieces, leaving out the ones which differ from an earlier piece only by empty
+    /// rows and columns around them, since they would only slow the search down
+    pub fn load(&self, input: &str) -> Result<LoadedPieces, PieceError> {
+        // shapes with the index of their first appearance
+        let mut pieces: Vec<(usize, Shape)> = Vec::new();
+        let mut duplicates = Vec::new();
+        let mut index = 0;
+        let mut piece: Vec<LineOffset> = Vec::new();
+        for line in iter_str_offsets::lines_with_offsets(input).chain([LineOffset {
+            offset: input.len(),
+            line: "",
+        }]) {
+            if !line.line.trim().is_empty() {
+                piece.push(line);
+                continue;
+            }
+            if let (Some(first), Some(last)) = (piece.first(), piece.last()) {
+                let span = (first.offset, last.offset + last.line.len() - first.offset).into();
+                let shape = self.check(index, span, &piece)?;
+                match pieces.iter().find(|(_, known)| *known == shape) {
+                    Some((same_as, _)) => duplicates.push(Duplicate {
+                        index,
+                        same_as: *same_as,
+                    }),
+                    None => pieces.push((index, shape)),
+                }
+                index += 1;
+                piece.clear();
+            }
+        }
+
+        Ok(LoadedPieces {
+            shapes: pieces.into_iter().map(|(_, shape)| shape).collect(),
+            duplicates,
+        })
+    }
+
+    fn check(
+        &self,
+        index: usize,
+        piece: SourceSpan,
+        lines: &[LineOffset],
+    ) -> Result<Shape, PieceError> {
+        let mut cells = BTreeSet::new();
+        for (row, LineOffset { line, offset }) in lines.iter().enumerate() {
+            for (col, (char_offset, char)) in line.char_indices().enumerate() {
+                match char {
+                    CHAR_CELL => {
+                        cells.insert(Pos::new(row, col));
+                    }
+                    CHAR_EMPTY => {}
+                    _ => {
+                        return Err(PieceError::UnexpectedCharacter {
+                            loc: (offset + char_offset, char.len_utf8()).into(),
+                        })
+                    }
+                }
+            }
+        }
+
+        let shape = Shape::from_cells(cells);
+        if shape.cells.is_empty() {
+            Err(PieceError::Empty { index, piece })
+        } else if !shape.is_connected() {
+            Err(PieceError::Disconnected { index, piece })
+        } else if !self.allow_holes && shape.has_holes() {
+            Err(PieceError::Holes { index, piece })
+        } else if !self.allow_concave && shape.is_concave() {
+            Err(PieceError::Concave { index, piece })
+        } else {
+            Ok(shape)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TETROMINOES: &str = "##\n##\n\n####\n\n###\n.#.\n\n#.\n#.\n##\n\n.##\n##.";
+    const RING: &str = "###\n#.#\n###";
+
+    #[test]
+    fn loads_tetrominoes_in_any_mode() {
+        let pieces = PieceLoader::default()
+            .allow_concave(false)
+            .load(TETROMINOES)
+            .unwrap()
+            .shapes;
+
+        assert_eq!(pieces.len(), 5);
+        assert!(pieces.iter().all(|shape| shape.cells().len() == 4));
+        assert_eq!(pieces[3].size(), Size::new(3, 2));
+    }
+
+    #[test]
+    fn duplicates_are_left_out() {
+        let loaded = PieceLoader::default()
+            .load("##\n##\n\n####\n\n...\n.##\n.##\n\n##..\n....")
+            .unwrap();
+
+        assert_eq!(loaded.shapes.len(), 3);
+        assert_eq!(
+            loaded
+                .shapes
+                .iter()
+                .filter(|shape| shape.size() == Size::new(2, 2))
+                .count(),
+            1
+        );
+        assert_eq!(
+            loaded.duplicates,
+            [Duplicate {
+                index: 2,
+                same_as: 0
+            }]
+        );
+    }
+
+    #[test]
+    fn holes_are_rejected_by_default() {
+        let err = PieceLoader::default().load(&format!("##\n##\n\n{RING}"));
+
+        assert_eq!(
+            err,
+            Err(PieceError::Holes {
+                index: 1,
+                piece: (7, 11).into()
+            })
+        );
+    }
+
+    #[test]
+    fn holes_are_accepted_if_allowed() {
+        let pieces = PieceLoader::default()
+            .allow_holes(true)
+            .load(RING)
+            .unwrap()
+            .shapes;
+
+        assert_eq!(pieces[0].cells().len(), 8);
+        assert!(!pieces[0].cells().contains(&Pos::new(1, 1)));
+    }
+
+    #[test]
+    fn holes_are_still_concave() {
+        let loader = PieceLoader::default()
+            .allow_holes(true)
+            .allow_concave(false);
+
+        assert!(matches!(
+            loader.load(RING),
+            Err(PieceError::Concave { index: 0, .. })
+        ));
+        assert!(matches!(
+            loader.load("#.#\n###"),
+            Err(PieceError::Concave { index: 0, .. })
+        ));
+    }
+
+    #[test]
+    fn rejects_broken_pieces() {
+        let loader = PieceLoader::default().allow_holes(true);
+
+        assert!(matches!(
+            loader.load("#..\n..#"),
+            Err(PieceError::Disconnected { index: 0, .. })
+        ));
+        assert!(matches!(
+            loader.load("...\n\n#"),
+            Err(PieceError::Empty { index: 0, .. })
+        ));
+        assert_eq!(
+            loader.load("##\n#x"),
+            Err(PieceError::UnexpectedCharacter { loc: (4, 1).into() })
+        );
+    }
+}
@@ -0,0 +1,85 @@
+use std::fmt::{Display, Formatter};
+use std::time::Duration;
+
+use derive_more::Display;
+
+/// Phases of a run, in order
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Display)]
+pub enum Phase {
+    /// Reading the field and building the configuration
+    #[display(fmt = "parse")]
+    Parse,
+    /// Preparing the search state
+    #[display(fmt = "setup")]
+    Setup,
+    /// The search itself, without [`Phase::Dedup`]
+    #[display(fmt = "search")]
+    Search,
+    /// Telling whether the results are found already, which is done during the search, as each
+    /// of them is found
+    #[display(fmt = "dedup")]
+    Dedup,
+    /// Printing the results
+    #[display(fmt = "render")]
+    Render,
+}
+
+impl Phase {
+    pub const ALL: [Phase; 5] = [
+        Phase::Parse,
+        Phase::Setup,
+        Phase::Search,
+        Phase::Dedup,
+        Phase::Render,
+    ];
+}
+
+/// How long each phase of a run took
+#[derive(Debug, Default)]
+pub struct Profile {
+    elapsed: [Option<Duration>; Phase::ALL.len()],
+}
+
+impl Profile {
+    /// Adds up if the phase is recorded several times
+    pub fn record(&mut self, phase: Phase, elapsed: Duration) {
+        let total = &mut self.elapsed[phase as usize];
+        *total = Some(total.unwrap_or_default() + elapsed);
+    }
+}
+
+impl Display for Profile {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Profile:")?;
+        for phase in Phase::ALL {
+            // derived `Display` ignores the width
+            let label = phase.to_string();
+            match self.elapsed[phase as usize] {
+                Some(elapsed) => writeln!(f, "  {label:<8}{elapsed:.2?}")?,
+                None => writeln!(f, "  {label:<8}-")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lists_all_phases() {
+        let mut profile = Profile::default();
+        profile.record(Phase::Search, Duration::from_millis(5));
+        profile.record(Phase::Search, Duration::from_millis(5));
+        profile.record(Phase::Dedup, Duration::from_millis(2));
+
+        let output = profile.to_string();
+
+        for label in ["parse", "setup", "search", "dedup", "render"] {
+            assert!(output.contains(label), "{label} is missing in:\n{output}");
+        }
+        assert!(output.contains("search  10.00ms"));
+        assert!(output.contains("dedup   2.00ms"));
+    }
+}
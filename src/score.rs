@@ -0,0 +1,443 @@
+//! Ranking of placements by a user-defined expression, e.g. `coverage*1.0 - pieces*0.1`.
+//!
+//! The expression could use numbers, `+`, `-`, `*`, `/`, parentheses and the variables:
+//!
+//! - `coverage`: share of the playable cells covered by tetras, from 0 to 1
+//! - `pieces`: how many tetras are in the placement, including prefilled ones
+//! - `free`: how many playable cells are left free
+//! - `compactness`: share of the bounding box of the covered cells which is covered, from 0 to 1
+//!
+//! [`ScoreMode`] offers the common scores without writing an expression.
+
+use std::num::NonZeroUsize;
+
+use miette::{Diagnostic, Report, SourceSpan};
+use thiserror::Error;
+
+use crate::algorithm::{Configuration, PlacementResult};
+use crate::util::Pos;
+
+const VARIABLES: [&str; 4] = ["coverage", "pieces", "free", "compactness"];
+
+#[derive(Debug, Error, PartialEq, Diagnostic)]
+pub enum ScoreError {
+    #[error("Unexpected character")]
+    UnexpectedCharacter {
+        #[label("Expected a number, a variable, an operator or a parenthesis")]
+        loc: SourceSpan,
+    },
+    #[error("Unknown variable `{name}`")]
+    #[diagnostic(help("Known variables are `coverage`, `pieces`, `free` and `compactness`"))]
+    UnknownVariable {
+        name: String,
+        #[label("here")]
+        loc: SourceSpan,
+    },
+    #[error("Expected a number, a variable or an opening parenthesis")]
+    ExpectedOperand {
+        #[label("here")]
+        loc: SourceSpan,
+    },
+    #[error("Expected an operator")]
+    ExpectedOperator {
+        #[label("here")]
+        loc: SourceSpan,
+    },
+    #[error("Unclosed parenthesis")]
+    UnclosedParen {
+        #[label("this one")]
+        loc: SourceSpan,
+    },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Token {
+    Number(f64),
+    Variable(usize),
+    Plus,
+    Minus,
+    Star,
+    Slash,
+    Open,
+    Close,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Expr {
+    Number(f64),
+    /// Index in [`VARIABLES`]
+    Variable(usize),
+    Neg(Box<Expr>),
+    Binary(Box<Expr>, Token, Box<Expr>),
+}
+
+impl Expr {
+    fn eval(&self, vars: &[f64; VARIABLES.len()]) -> f64 {
+        match self {
+            Expr::Number(value) => *value,
+            Expr::Variable(idx) => vars[*idx],
+            Expr::Neg(expr) => -expr.eval(vars),
+            Expr::Binary(lhs, op, rhs) => {
+                let (lhs, rhs) = (lhs.eval(vars), rhs.eval(vars));
+                match op {
+                    Token::Plus => lhs + rhs,
+                    Token::Minus => lhs - rhs,
+                    Token::Star => lhs * rhs,
+                    Token::Slash => lhs / rhs,
+                    _ => unreachable!("not a binary operator"),
+                }
+            }
+        }
+    }
+}
+
+fn tokenize(source: &str) -> Result<Vec<(Token, SourceSpan)>, ScoreError> {
+    let mut tokens = Vec::new();
+    let mut chars = source.char_indices().peekable();
+    while let Some((start, char)) = chars.next() {
+        let mut end = start + char.len_utf8();
+        let mut take_while = |pred: fn(char) -> bool| {
+            while let Some((offset, char)) = chars.next_if(|(_, char)| pred(*char)) {
+                end = offset + char.len_utf8();
+            }
+            end
+        };
+        let token = match char {
+            _ if char.is_whitespace() => continue,
+            '+' => Token::Plus,
+            '-' => Token::Minus,
+            '*' => Token::Star,
+            '/' => Token::Slash,
+            '(' => Token::Open,
+            ')' => Token::Close,
+            '0'..='9' | '.' => {
+                let end = take_while(|char| char.is_ascii_digit() || char == '.');
+                let value =
+                    source[start..end]
+                        .parse()
+                        .map_err(|_| ScoreError::UnexpectedCharacter {
+                            loc: (start, end - start).into(),
+                        })?;
+                Token::Number(value)
+            }
+            _ if char.is_alphabetic() || char == '_' => {
+                let end = take_while(|char| char.is_alphanumeric() || char == '_');
+                let name = &source[start..end];
+                let idx = VARIABLES.iter().position(|known| *known == name).ok_or(
+                    ScoreError::UnknownVariable {
+                        name: name.to_owned(),
+                        loc: (start, end - start).into(),
+                    },
+                )?;
+                Token::Variable(idx)
+            }
+            _ => {
+                return Err(ScoreError::UnexpectedCharacter {
+                    loc: (start, char.len_utf8()).into(),
+                })
+            }
+        };
+        tokens.push((token, (start, end - start).into()));
+    }
+    Ok(tokens)
+}
+
+/// Recursive descent over the tokens, with the usual precedence of operators
+struct Parser {
+    tokens: Vec<(Token, SourceSpan)>,
+    next: usize,
+    /// Where the expression ends, to point at if it ends too early
+    end: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<Token> {
+        self.tokens.get(self.next).map(|(token, _)| *token)
+    }
+
+    fn loc(&self) -> SourceSpan {
+        self.tokens
+            .get(self.next)
+            .map_or((self.end, 0).into(), |(_, loc)| *loc)
+    }
+
+    fn sum(&mut self) -> Result<Expr, ScoreError> {
+        let mut expr = self.product()?;
+        while let Some(op @ (Token::Plus | Token::Minus)) = self.peek() {
+            self.next += 1;
+            expr = Expr::Binary(Box::new(expr), op, Box::new(self.product()?));
+        }
+        Ok(expr)
+    }
+
+    fn product(&mut self) -> Result<Expr, ScoreError> {
+        let mut expr = self.operand()?;
+        while let Some(op @ (Token::Star | Token::Slash)) = self.peek() {
+            self.next += 1;
+            expr = Expr::Binary(Box::new(expr), op, Box::new(self.operand()?));
+        }
+        Ok(expr)
+    }
+
+    fn operand(&mut self) -> Result<Expr, ScoreError> {
+        let loc = self.loc();
+        let token = self.peek().ok_or(ScoreError::ExpectedOperand { loc })?;
+        self.next += 1;
+        match token {
+            Token::Number(value) => Ok(Expr::Number(value)),
+            Token::Variable(idx) => Ok(Expr::Variable(idx)),
+            Token::Minus => Ok(Expr::Neg(Box::new(self.operand()?))),
+            Token::Open => {
+                let expr = self.sum()?;
+                match self.peek() {
+                    Some(Token::Close) => {
+                        self.next += 1;
+                        Ok(expr)
+                    }
+                    _ => Err(ScoreError::UnclosedParen { loc }),
+                }
+            }
+            _ => Err(ScoreError::ExpectedOperand { loc }),
+        }
+    }
+}
+
+/// Parsed scoring expression, the higher the score the better the placement
+#[derive(Clone, Debug, PartialEq)]
+pub struct Score {
+    expr: Expr,
+}
+
+impl Score {
+    pub fn parse(source: impl AsRef<str>) -> Result<Self, Report> {
+        let source = source.as_ref();
+
+        Self::parse_without_source_code(source)
+            .map_err(|err| Report::new(err).with_source_code(source.to_owned()))
+    }
+
+    /// Like [`Score::parse`], but the error isn't attached to the source code, so that it could
+    /// be matched
+    pub fn parse_without_source_code(source: &str) -> Result<Self, ScoreError> {
+        let mut parser = Parser {
+            tokens: tokenize(source)?,
+            next: 0,
+            end: source.len(),
+        };
+        let expr = parser.sum()?;
+        match parser.peek() {
+            None => Ok(Self { expr }),
+            Some(_) => Err(ScoreError::ExpectedOperator { loc: parser.loc() }),
+        }
+    }
+
+    pub fn of(&self, result: &PlacementResult, conf: &Configuration) -> f64 {
+        self.expr.eval(&variables(result, conf))
+    }
+
+    /// Sorts the results from the best score to the worst, keeping only the `top` ones if set.
+    /// Results with the same score keep their order
+    pub fn rank<'a>(
+        &self,
+        results: impl IntoIterator<Item = &'a PlacementResult>,
+        conf: &Configuration,
+        top: Option<NonZeroUsize>,
+    ) -> Vec<&'a PlacementResult> {
+        rank_by(results, top, |result| self.of(result, conf))
+    }
+}
+
+/// Values of [`VARIABLES`] for the result
+fn variables(result: &PlacementResult, conf: &Configuration) -> [f64; VARIABLES.len()] {
+    let playable = conf.playable_cells();
+    let covered = playable - result.free;
+    let coverage = if playable == 0 {
+        1.0
+    } else {
+        covered as f64 / playable as f64
+    };
+
+    let cells: Vec<_> = result
+        .placement
+        .iter()
+        .flat_map(|placed| placed.iter_relative_to_place())
+        .collect();
+    let span = |coord: fn(&Pos) -> usize| {
+        let min = cells.iter().map(coord).min().unwrap_or(0);
+        let max = cells.iter().map(coord).max().unwrap_or(0);
+        max - min + 1
+    };
+    let compactness = if cells.is_empty() {
+        0.0
+    } else {
+        cells.len() as f64 / (span(|pos| pos.row) * span(|pos| pos.col)) as f64
+    };
+
+    [
+        coverage,
+        result.placement.len() as f64,
+        result.free as f64,
+        compactness,
+    ]
+}
+
+fn rank_by<'a>(
+    results: impl IntoIterator<Item = &'a PlacementResult>,
+    top: Option<NonZeroUsize>,
+    score: impl Fn(&PlacementResult) -> f64,
+) -> Vec<&'a PlacementResult> {
+    let mut scored: Vec<_> = results
+        .into_iter()
+        .map(|result| (score(result), result))
+        .collect();
+    scored.sort_by(|(lhs, _), (rhs, _)| rhs.total_cmp(lhs));
+    scored.truncate(top.map_or(usize::MAX, NonZeroUsize::get));
+    scored.into_iter().map(|(_, result)| result).collect()
+}
+
+/// How to rank placements, see [`Configuration::with_score`]
+#[derive(Clone, Debug, PartialEq)]
+pub enum ScoreMode {
+    /// Share of the playable cells covered by tetras
+    Coverage,
+    /// Share of the bounding box of the covered cells which is covered, so that placements
+    /// clustered together are preferred
+    Compactness,
+    /// `coverage * weight + compactness * (1 - weight)`, the weight is from 0 to 1
+    Weighted(f64),
+    /// A scoring expression
+    Custom(Score),
+}
+
+impl ScoreMode {
+    /// The higher the better
+    pub fn of(&self, result: &PlacementResult, conf: &Configuration) -> f64 {
+        let [coverage, _, _, compactness] = variables(result, conf);
+        match self {
+            ScoreMode::Coverage => coverage,
+            ScoreMode::Compactness => compactness,
+            ScoreMode::Weighted(weight) => coverage * weight + compactness * (1.0 - weight),
+            ScoreMode::Custom(score) => score.of(result, conf),
+        }
+    }
+
+    /// Like [`Score::rank`]
+    pub fn rank<'a>(
+        &self,
+        results: impl IntoIterator<Item = &'a PlacementResult>,
+        conf: &Configuration,
+        top: Option<NonZeroUsize>,
+    ) -> Vec<&'a PlacementResult> {
+        rank_by(results, top, |result| self.of(result, conf))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tetra::{Placed, PlacedBoundariesChecked, TETRAS};
+    use crate::util::Size;
+    use std::collections::HashSet;
+
+    fn eval(source: &str, vars: [f64; 4]) -> f64 {
+        Score::parse_without_source_code(source)
+            .unwrap()
+            .expr
+            .eval(&vars)
+    }
+
+    #[test]
+    fn respects_precedence() {
+        assert_eq!(eval("1 + 2 * 3", [0.0; 4]), 7.0);
+        assert_eq!(eval("(1 + 2) * 3", [0.0; 4]), 9.0);
+        assert_eq!(eval("8 / 2 / 2 - -1", [0.0; 4]), 3.0);
+        assert_eq!(eval("coverage*1.0 - pieces*0.1", [0.5, 2.0, 4.0, 1.0]), 0.3);
+    }
+
+    #[test]
+    fn points_at_errors() {
+        let parse = Score::parse_without_source_code;
+
+        assert_eq!(
+            parse("coverage - holes"),
+            Err(ScoreError::UnknownVariable {
+                name: "holes".to_owned(),
+                loc: (11, 5).into()
+            })
+        );
+        assert_eq!(
+            parse("(free + 1"),
+            Err(ScoreError::UnclosedParen { loc: (0, 1).into() })
+        );
+        assert_eq!(
+            parse("free 1"),
+            Err(ScoreError::ExpectedOperator { loc: (5, 1).into() })
+        );
+        assert_eq!(
+            parse("free *"),
+            Err(ScoreError::ExpectedOperand { loc: (6, 0).into() })
+        );
+        assert_eq!(
+            parse("free % 2"),
+            Err(ScoreError::UnexpectedCharacter { loc: (5, 1).into() })
+        );
+    }
+
+    #[test]
+    fn ranks_results_by_score() {
+        let conf = Configuration::new(Size::new(2, 4), HashSet::new());
+        let square = |col| {
+            PlacedBoundariesChecked::in_boundaries(
+                Placed::new(&TETRAS[0], Pos::new(0, col)),
+                conf.size,
+            )
+            .unwrap()
+        };
+        let sparse = PlacementResult {
+            placement: [square(0)].into(),
+            free: 4,
+            order: None,
+        };
+        let full = PlacementResult {
+            placement: [square(0), square(2)].into(),
+            free: 0,
+            order: None,
+        };
+
+        let by_coverage = Score::parse_without_source_code("coverage - pieces * 0.1").unwrap();
+        assert_eq!(
+            by_coverage.rank([&sparse, &full], &conf, None),
+            [&full, &sparse]
+        );
+
+        let by_pieces = Score::parse_without_source_code("-pieces").unwrap();
+        assert_eq!(
+            by_pieces.rank([&full, &sparse], &conf, NonZeroUsize::new(1)),
+            [&sparse]
+        );
+    }
+
+    #[test]
+    fn fuller_tighter_placement_scores_higher() {
+        let conf = Configuration::new(Size::new(2, 6), HashSet::new())
+            .with_score(ScoreMode::Weighted(0.5));
+        let square = |col| {
+            PlacedBoundariesChecked::in_boundaries(
+                Placed::new(&TETRAS[0], Pos::new(0, col)),
+                conf.size,
+            )
+            .unwrap()
+        };
+        let result = |cols: &[usize]| PlacementResult {
+            placement: cols.iter().map(|col| square(*col)).collect(),
+            free: 12 - cols.len() * 4,
+            order: None,
+        };
+        let (tight, sparse) = (result(&[0, 2]), result(&[0, 4]));
+
+        assert_eq!(ScoreMode::Compactness.of(&tight, &conf), 1.0);
+        assert_eq!(ScoreMode::Compactness.of(&sparse, &conf), 8.0 / 12.0);
+        assert!(conf.score_of(&tight) > conf.score_of(&sparse));
+        assert_eq!(conf.ranked([&sparse, &tight], None), [&tight, &sparse]);
+    }
+}
@@ -1,52 +1,348 @@
-use crate::algorithm::PlacementResult;
-use crate::tetra::{Tetra as BaseTetra, TETRAS};
-use crate::util::Pos;
-use serde::Serialize;
-use std::collections::{BTreeMap, BTreeSet, HashMap};
+use crate::algorithm::{add_coverage, fingerprint, Configuration, PlacementResult, ResultSink};
+use crate::parse_field::{ParseError, SourcedParseError};
+use crate::tetra::{Placed, PlacedBoundariesChecked, Tetra as BaseTetra, TETRAS};
+use crate::util::{Pos, Size};
+use grid::Grid;
+use miette::{Diagnostic, Report};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::io::{self, BufRead, Write};
+use std::num::NonZeroUsize;
+use std::ops::ControlFlow;
 
 #[derive(Debug, Serialize)]
 pub struct Output {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    source: Option<Provenance>,
     tetras: BTreeMap<usize, Tetra>,
-    placements: BTreeSet<Placement>,
+    /// See [`Configuration::is_complete_feasible`]
+    feasible_complete: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    status: Option<Status>,
+    /// `None` only if nothing is found and the status is requested, see [`Output::with_status`]
+    placements: Option<Vec<Placement>>,
+    /// How many times each tetra appears in the placements
+    #[serde(skip_serializing_if = "Option::is_none")]
+    usage: Option<BTreeMap<usize, usize>>,
+    /// How many placements cover each cell, row by row
+    #[serde(skip_serializing_if = "Option::is_none")]
+    heatmap: Option<Vec<Vec<usize>>>,
 }
 
 impl Output {
-    pub fn new(placements: &BTreeSet<PlacementResult>) -> Self {
-        let tetras_reverse: HashMap<&'static BaseTetra, usize> =
-            TETRAS.iter().enumerate().map(|(a, b)| (b, a)).collect();
-
+    /// Placements are written in the given order
+    pub fn new<'a>(
+        placements: impl IntoIterator<Item = &'a PlacementResult>,
+        conf: &Configuration,
+    ) -> Self {
         let placements = placements
-            .iter()
-            .map(|placement| Placement {
-                free: placement.free,
-                tetras: placement
-                    .placement
-                    .iter()
-                    .map(|tetra_pos| {
-                        let tetra_id = *tetras_reverse
-                            .get(tetra_pos.tetra)
-                            .expect("All tetras should be presented in the map");
-                        TetraPos {
-                            tetra: tetra_id,
-                            pos: tetra_pos.position,
-                        }
-                    })
-                    .collect(),
-            })
-            .collect();
-
-        let tetras = tetras_reverse
             .into_iter()
-            .map(|(tetra, id)| {
-                let tetra: Tetra = tetra.into();
-                (id, tetra)
-            })
+            .map(|placement| Placement::new(placement, conf))
             .collect();
 
-        Self { placements, tetras }
+        Self {
+            source: None,
+            placements: Some(placements),
+            tetras: tetras(),
+            feasible_complete: conf.is_complete_feasible(),
+            status: None,
+            usage: None,
+            heatmap: None,
+        }
+    }
+
+    /// Adds the `status` field, and writes `null` rather than an empty list of placements if
+    /// nothing is found, so that an empty result could be told apart from no result at all
+    pub fn with_status(mut self) -> Self {
+        let found = self
+            .placements
+            .as_ref()
+            .is_some_and(|list| !list.is_empty());
+        if found {
+            self.status = Some(Status::Found);
+        } else {
+            self.status = Some(Status::NoSolution);
+            self.placements = None;
+        }
+        self
+    }
+
+    /// See [`crate::algorithm::aggregate_piece_usage`]
+    pub fn with_usage(mut self, usage: BTreeMap<usize, usize>) -> Self {
+        self.usage = Some(usage);
+        self
+    }
+
+    /// See [`crate::algorithm::coverage_heatmap`]
+    pub fn with_heatmap(mut self, heatmap: &Grid<usize>) -> Self {
+        self.heatmap = Some(matrix(heatmap));
+        self
+    }
+
+    /// Adds the `source` field, telling which field the placements are of
+    pub fn with_source(mut self, source: Provenance) -> Self {
+        self.source = Some(source);
+        self
+    }
+}
+
+/// Where the field comes from, so that outputs of many fields could be told apart
+#[derive(Clone, Debug, Serialize, PartialEq, Eq)]
+pub struct Provenance {
+    /// The file name, or e.g. `stdin`
+    input: String,
+    /// The number of the field, from 1, if several fields are read from the input at once
+    #[serde(skip_serializing_if = "Option::is_none")]
+    board: Option<usize>,
+}
+
+impl Provenance {
+    pub fn new(input: impl Into<String>) -> Self {
+        Self {
+            input: input.into(),
+            board: None,
+        }
+    }
+
+    pub fn with_board(mut self, board: usize) -> Self {
+        self.board = Some(board);
+        self
+    }
+}
+
+fn matrix(grid: &Grid<usize>) -> Vec<Vec<usize>> {
+    (0..grid.rows())
+        .map(|row| grid.iter_row(row).copied().collect())
+        .collect()
+}
+
+/// Whether the search found anything, see [`Output::with_status`]
+#[derive(Debug, Serialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Status {
+    Found,
+    NoSolution,
+}
+
+/// A failure written instead of [`Output`], so that callers could tell errors apart without
+/// reading the human-readable diagnostic
+#[derive(Debug, Serialize, PartialEq)]
+pub struct ErrorOutput {
+    /// The message with its causes, separated by `: `
+    error: String,
+    /// Variant of [`ParseError`], if the field couldn't be parsed
+    kind: Option<&'static str>,
+    /// Where the error is in the input
+    span: Option<Span>,
+}
+
+/// Part of the input, in bytes
+#[derive(Debug, Serialize, PartialEq)]
+pub struct Span {
+    offset: usize,
+    length: usize,
+}
+
+impl ErrorOutput {
+    pub fn new(report: &Report) -> Self {
+        let error = report
+            .chain()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join(": ");
+        let parse_error = report
+            .downcast_ref::<SourcedParseError>()
+            .map(|sourced| &sourced.error)
+            .or_else(|| report.downcast_ref::<ParseError>());
+        let span = parse_error
+            .and_then(Diagnostic::labels)
+            .and_then(|mut labels| labels.next())
+            .map(|label| Span {
+                offset: label.offset(),
+                length: label.len(),
+            });
+
+        Self {
+            error,
+            kind: parse_error.map(ParseError::kind),
+            span,
+        }
+    }
+}
+
+fn tetras() -> BTreeMap<usize, Tetra> {
+    TETRAS
+        .iter()
+        .enumerate()
+        .map(|(id, tetra)| (id, tetra.into()))
+        .collect()
+}
+
+/// Writes the same structure as [`Output`] does, but takes placements one by one, so that they
+/// don't have to be in memory all at once
+pub fn write_streaming(
+    mut out: impl Write,
+    conf: &Configuration,
+    source: Option<&Provenance>,
+    placements: impl Iterator<Item = io::Result<Placement>>,
+    with_usage: bool,
+    with_heatmap: bool,
+    with_status: bool,
+) -> io::Result<()> {
+    let mut usage = BTreeMap::<usize, usize>::new();
+    let mut heatmap = Grid::init(conf.size.rows, conf.size.cols, 0);
+    let mut placements = placements.peekable();
+    write!(out, "{{")?;
+    if let Some(source) = source {
+        write!(out, "\"source\":")?;
+        serde_json::to_writer(&mut out, source)?;
+        write!(out, ",")?;
+    }
+    write!(out, "\"tetras\":")?;
+    serde_json::to_writer(&mut out, &tetras())?;
+    write!(
+        out,
+        ",\"feasible_complete\":{}",
+        conf.is_complete_feasible()
+    )?;
+    if with_status {
+        let status = match placements.peek() {
+            Some(_) => Status::Found,
+            None => Status::NoSolution,
+        };
+        write!(out, ",\"status\":")?;
+        serde_json::to_writer(&mut out, &status)?;
+        if status == Status::NoSolution {
+            write!(out, ",\"placements\":null")?;
+            if with_usage {
+                write!(out, ",\"usage\":{{}}")?;
+            }
+            if with_heatmap {
+                write!(out, ",\"heatmap\":")?;
+                serde_json::to_writer(&mut out, &matrix(&heatmap))?;
+            }
+            return write!(out, "}}");
+        }
+    }
+    write!(out, ",\"placements\":[")?;
+    for (idx, placement) in placements.enumerate() {
+        if idx > 0 {
+            write!(out, ",")?;
+        }
+        let placement = placement?;
+        for TetraPos { tetra, .. } in &placement.tetras {
+            *usage.entry(*tetra).or_default() += 1;
+        }
+        if with_heatmap {
+            let result = placement.to_result(conf.size).ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "Placement doesn't fit into the field",
+                )
+            })?;
+            add_coverage(&mut heatmap, &result);
+        }
+        serde_json::to_writer(&mut out, &placement)?;
+    }
+    write!(out, "]")?;
+    if with_usage {
+        write!(out, ",\"usage\":")?;
+        serde_json::to_writer(&mut out, &usage)?;
+    }
+    if with_heatmap {
+        write!(out, ",\"heatmap\":")?;
+        serde_json::to_writer(&mut out, &matrix(&heatmap))?;
+    }
+    write!(out, "}}")
+}
+
+/// Writes placements in JSON Lines format, one placement per line
+#[derive(Debug)]
+pub struct JsonLinesWriter<W> {
+    out: W,
+}
+
+impl<W: Write> JsonLinesWriter<W> {
+    pub fn new(out: W) -> Self {
+        Self { out }
+    }
+
+    pub fn write(&mut self, placement: &Placement) -> io::Result<()> {
+        serde_json::to_writer(&mut self.out, placement)?;
+        writeln!(self.out)
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+}
+
+/// Writes distinct results in JSON Lines format as the search finds them, keeping only their
+/// fingerprints in memory, see [`Configuration::run_into`]. Stops the search once
+/// [`Configuration::results_limit`] results are written, or once writing fails
+pub struct JsonLinesSink<'a, W> {
+    writer: JsonLinesWriter<W>,
+    conf: &'a Configuration,
+    limit: Option<NonZeroUsize>,
+    written: HashSet<u64>,
+    error: Option<io::Error>,
+}
+
+impl<'a, W: Write> JsonLinesSink<'a, W> {
+    pub fn new(out: W, conf: &'a Configuration) -> Self {
+        Self {
+            writer: JsonLinesWriter::new(out),
+            conf,
+            limit: conf.results_limit,
+            written: HashSet::new(),
+            error: None,
+        }
+    }
+
+    /// Flushes the output and tells how many results are written, or the error which stopped
+    /// the search
+    pub fn finish(mut self) -> io::Result<usize> {
+        if let Some(err) = self.error {
+            return Err(err);
+        }
+        self.writer.flush()?;
+        Ok(self.written.len())
+    }
+}
+
+impl<W: std::fmt::Debug> std::fmt::Debug for JsonLinesSink<'_, W> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("JsonLinesSink")
+            .field("writer", &self.writer)
+            .field("limit", &self.limit)
+            .field("written", &self.written.len())
+            .field("error", &self.error)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<W: Write + std::fmt::Debug> ResultSink for JsonLinesSink<'_, W> {
+    fn accept(&mut self, result: PlacementResult) -> ControlFlow<()> {
+        if self.written.insert(fingerprint(&result)) {
+            if let Err(err) = self.writer.write(&Placement::new(&result, self.conf)) {
+                self.error = Some(err);
+                return ControlFlow::Break(());
+            }
+        }
+        match self.limit {
+            Some(limit) if self.written.len() >= limit.get() => ControlFlow::Break(()),
+            _ => ControlFlow::Continue(()),
+        }
     }
 }
 
+/// Reads placements written by [`JsonLinesWriter`]
+pub fn read_json_lines(input: impl BufRead) -> impl Iterator<Item = io::Result<Placement>> {
+    input
+        .lines()
+        .map(|line| line.and_then(|line| Ok(serde_json::from_str(&line)?)))
+}
+
 #[derive(Debug, Serialize)]
 pub struct Tetra {
     positions: Vec<Pos>,
@@ -55,19 +351,390 @@ pub struct Tetra {
 impl From<&'_ BaseTetra> for Tetra {
     fn from(value: &BaseTetra) -> Self {
         Self {
-            positions: value.iter().map(|x| *x).collect(),
+            positions: value.iter().copied().collect(),
         }
     }
 }
 
-#[derive(Debug, Serialize, Ord, PartialOrd, Eq, PartialEq)]
+#[derive(Debug, Serialize, Deserialize, PartialOrd, PartialEq)]
 pub struct Placement {
     tetras: BTreeSet<TetraPos>,
     free: usize,
+    /// See [`PlacementResult::fill_percent`]
+    #[serde(default)]
+    fill_percent: f64,
+    /// See [`Configuration::score`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    score: Option<f64>,
+    /// The same tetras in the order they were placed, see [`PlacementResult::order`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    placement_order: Option<Vec<TetraPos>>,
 }
 
-#[derive(Debug, Serialize, Ord, PartialOrd, Eq, PartialEq)]
+impl Placement {
+    pub fn new(result: &PlacementResult, conf: &Configuration) -> Self {
+        let tetra_pos = |placed: &PlacedBoundariesChecked| {
+            let source = if conf.is_prefilled(placed) {
+                Source::Prefilled
+            } else {
+                Source::Solved
+            };
+            TetraPos::new(placed, &TETRAS, source).expect("All tetras are from the list")
+        };
+        Self {
+            free: result.free,
+            fill_percent: result.fill_percent(conf),
+            score: conf.score_of(result),
+            tetras: result.placement.iter().map(tetra_pos).collect(),
+            placement_order: result
+                .order
+                .as_ref()
+                .map(|order| order.iter().map(tetra_pos).collect()),
+        }
+    }
+
+    /// Restores the placement, e.g. after reading it back with [`read_json_lines`].
+    ///
+    /// `None` if some tetra doesn't exist or doesn't fit into the field of the given size.
+    pub fn to_result(&self, size: Size) -> Option<PlacementResult> {
+        let placement = self
+            .tetras
+            .iter()
+            .map(|tetra_pos| tetra_pos.to_placed(&TETRAS, size))
+            .collect::<Option<_>>()?;
+        let order = match &self.placement_order {
+            Some(order) => Some(
+                order
+                    .iter()
+                    .map(|tetra_pos| tetra_pos.to_placed(&TETRAS, size))
+                    .collect::<Option<_>>()?,
+            ),
+            None => None,
+        };
+
+        Some(PlacementResult {
+            placement,
+            free: self.free,
+            order,
+        })
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Ord, PartialOrd, Eq, PartialEq)]
 pub struct TetraPos {
     tetra: usize,
     pos: Pos,
+    source: Source,
+}
+
+impl TetraPos {
+    /// `None` if the tetra is not in `tetras`, its id is the index there
+    pub fn new(placed: &Placed, tetras: &[BaseTetra], source: Source) -> Option<Self> {
+        Some(Self {
+            tetra: tetras.iter().position(|tetra| tetra == placed.tetra)?,
+            pos: placed.position,
+            source,
+        })
+    }
+
+    /// Looks the tetra up by its id in `tetras` and checks that it fits into the field of the
+    /// given size
+    pub fn to_placed(
+        &self,
+        tetras: &'static [BaseTetra],
+        size: Size,
+    ) -> Option<PlacedBoundariesChecked> {
+        PlacedBoundariesChecked::in_boundaries(Placed::new(tetras.get(self.tetra)?, self.pos), size)
+    }
+}
+
+/// Whether a tetra was put by the user or found by the search
+#[derive(Debug, Serialize, Deserialize, Ord, PartialOrd, Eq, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Source {
+    Prefilled,
+    Solved,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tetra::{Placed, TETRAS};
+    use crate::util::Size;
+    use std::collections::HashSet;
+
+    struct StatsDummy;
+
+    impl crate::algorithm::CollectStats for StatsDummy {
+        fn recursions_inc(&mut self) {}
+
+        fn results_inc(&mut self) {}
+    }
+
+    #[test]
+    fn parse_error_is_structured() {
+        use miette::WrapErr;
+
+        let report = crate::parse_field::Parser::default()
+            .parse("---\n-#-")
+            .wrap_err("Failed to parse field from STDIN")
+            .unwrap_err();
+
+        let output = serde_json::to_value(ErrorOutput::new(&report)).unwrap();
+
+        assert_eq!(
+            output,
+            serde_json::json!({
+                "error": "Failed to parse field from STDIN: Unexpected character at (1, 1)",
+                "kind": "UnexpectedCharacter",
+                "span": {"offset": 5, "length": 1},
+            })
+        );
+    }
+
+    #[test]
+    fn tetra_pos_round_trip() {
+        let size = Size::new(4, 4);
+        let placed =
+            PlacedBoundariesChecked::in_boundaries(Placed::new(&TETRAS[5], Pos::new(1, 2)), size)
+                .unwrap();
+
+        let tetra_pos = TetraPos::new(&placed, &TETRAS, Source::Solved).unwrap();
+
+        assert_eq!(tetra_pos.tetra, 5);
+        assert_eq!(tetra_pos.to_placed(&TETRAS, size), Some(placed));
+        assert_eq!(tetra_pos.to_placed(&TETRAS, Size::new(2, 2)), None);
+        assert_eq!(tetra_pos.to_placed(&TETRAS[..5], size), None);
+    }
+
+    #[test]
+    fn placement_order_lists_the_same_tetras() {
+        let conf = Configuration::new(Size::new(4, 4), HashSet::new())
+            .with_mode(crate::algorithm::SearchMode::Exhaustive)
+            .with_placement_order(true);
+
+        let results = conf.run(&mut StatsDummy);
+
+        assert!(!results.is_empty());
+        for result in &results {
+            let placement = Placement::new(result, &conf);
+            let order = placement.placement_order.as_ref().unwrap();
+            assert_eq!(order.len(), placement.tetras.len());
+            assert_eq!(
+                order.iter().collect::<BTreeSet<_>>(),
+                placement.tetras.iter().collect()
+            );
+            let restored = placement.to_result(conf.size).unwrap();
+            assert_eq!(restored.order, result.order);
+        }
+
+        let unordered = Configuration::new(Size::new(4, 4), HashSet::new()).run(&mut StatsDummy);
+        assert!(unordered.iter().all(|result| result.order.is_none()));
+    }
+
+    #[test]
+    fn reports_complete_feasibility() {
+        let conf = Configuration::new(Size::new(3, 3), HashSet::new());
+        let output = Output::new(&BTreeSet::new(), &conf);
+
+        let json = serde_json::to_value(&output).unwrap();
+
+        assert_eq!(json["feasible_complete"], false);
+
+        let mut streamed = Vec::new();
+        write_streaming(
+            &mut streamed,
+            &conf,
+            None,
+            std::iter::empty(),
+            false,
+            false,
+            false,
+        )
+        .unwrap();
+        let streamed: serde_json::Value = serde_json::from_slice(&streamed).unwrap();
+        assert_eq!(streamed, json);
+    }
+
+    #[test]
+    fn status_tells_empty_results_apart() {
+        let conf = Configuration::new(Size::new(3, 3), HashSet::new());
+        let empty = || Output::new(&BTreeSet::new(), &conf);
+
+        let plain = serde_json::to_value(empty()).unwrap();
+        assert_eq!(plain["placements"], serde_json::json!([]));
+        assert!(plain.get("status").is_none());
+
+        let with_status = serde_json::to_value(empty().with_status()).unwrap();
+        assert_eq!(with_status["placements"], serde_json::Value::Null);
+        assert_eq!(with_status["status"], "no_solution");
+
+        let mut streamed = Vec::new();
+        write_streaming(
+            &mut streamed,
+            &conf,
+            None,
+            std::iter::empty(),
+            false,
+            false,
+            true,
+        )
+        .unwrap();
+        let streamed: serde_json::Value = serde_json::from_slice(&streamed).unwrap();
+        assert_eq!(streamed, with_status);
+
+        let conf = Configuration::new(Size::new(2, 2), HashSet::new())
+            .with_mode(crate::algorithm::SearchMode::Exhaustive);
+        let found = Output::new(&conf.run(&mut StatsDummy), &conf).with_status();
+        let found = serde_json::to_value(found).unwrap();
+        assert_eq!(found["status"], "found");
+        assert_eq!(found["placements"].as_array().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn empty_and_single_results_are_valid_json() {
+        let conf = Configuration::new(Size::new(2, 2), HashSet::new())
+            .with_mode(crate::algorithm::SearchMode::Exhaustive);
+        let single = conf.run(&mut StatsDummy);
+        assert_eq!(single.len(), 1);
+
+        for results in [BTreeSet::new(), single] {
+            for (with_usage, with_heatmap, with_status) in
+                (0..8).map(|bits| (bits & 1 != 0, bits & 2 != 0, bits & 4 != 0))
+            {
+                let source = Provenance::new("board.txt").with_board(2);
+                let mut output = Output::new(&results, &conf).with_source(source.clone());
+                if with_usage {
+                    output = output.with_usage(crate::algorithm::aggregate_piece_usage(&results));
+                }
+                if with_heatmap {
+                    output =
+                        output.with_heatmap(&crate::algorithm::coverage_heatmap(&results, &conf));
+                }
+                if with_status {
+                    output = output.with_status();
+                }
+                let json = serde_json::to_string_pretty(&output).unwrap();
+                let json: serde_json::Value = serde_json::from_str(&json).unwrap();
+
+                let mut streamed = Vec::new();
+                let placements = results
+                    .iter()
+                    .map(|result| Ok(Placement::new(result, &conf)));
+                write_streaming(
+                    &mut streamed,
+                    &conf,
+                    Some(&source),
+                    placements,
+                    with_usage,
+                    with_heatmap,
+                    with_status,
+                )
+                .unwrap();
+                let streamed: serde_json::Value =
+                    serde_json::from_str(std::str::from_utf8(&streamed).unwrap()).unwrap();
+
+                assert_eq!(streamed, json);
+            }
+        }
+    }
+
+    #[test]
+    fn prefilled_and_solved_tetras_are_tagged() {
+        let square = &TETRAS[0];
+        let conf = Configuration::new(Size::new(4, 4), HashSet::new())
+            .with_prefilled(Placed::new(square, Pos::new(0, 0)))
+            .unwrap();
+
+        let results = conf.run(&mut StatsDummy);
+        let output = Output::new(&results, &conf);
+
+        let placements = output.placements.unwrap();
+        assert!(!placements.is_empty());
+        for placement in &placements {
+            let prefilled: Vec<_> = placement
+                .tetras
+                .iter()
+                .filter(|x| x.source == Source::Prefilled)
+                .collect();
+            assert_eq!(prefilled.len(), 1);
+            assert_eq!(prefilled[0].tetra, 0);
+            assert_eq!(prefilled[0].pos, Pos::new(0, 0));
+            assert_eq!(placement.tetras.len(), 4);
+            assert!(placement
+                .tetras
+                .iter()
+                .filter(|x| x.pos != Pos::new(0, 0))
+                .all(|x| x.source == Source::Solved));
+        }
+    }
+
+    struct SpillingStats<'a> {
+        conf: &'a Configuration,
+        writer: JsonLinesWriter<std::fs::File>,
+    }
+
+    impl crate::algorithm::CollectStats for SpillingStats<'_> {
+        fn recursions_inc(&mut self) {}
+
+        fn results_inc(&mut self) {}
+
+        fn spill_threshold(&self) -> Option<std::num::NonZeroUsize> {
+            std::num::NonZeroUsize::new(10)
+        }
+
+        fn spill(&mut self, results: BTreeSet<PlacementResult>) {
+            for result in &results {
+                self.writer
+                    .write(&Placement::new(result, self.conf))
+                    .unwrap();
+            }
+        }
+    }
+
+    #[test]
+    fn spilled_results_are_written_into_file() {
+        let conf = Configuration::new(Size::new(4, 4), HashSet::new())
+            .with_mode(crate::algorithm::SearchMode::Exhaustive);
+        let path = std::env::temp_dir().join(format!("spill-{}.jsonl", std::process::id()));
+        let mut stats = SpillingStats {
+            conf: &conf,
+            writer: JsonLinesWriter::new(std::fs::File::create(&path).unwrap()),
+        };
+
+        let in_memory = conf.run(&mut stats);
+        stats.writer.flush().unwrap();
+
+        let file = std::io::BufReader::new(std::fs::File::open(&path).unwrap());
+        let spilled = read_json_lines(file)
+            .map(|placement| placement.unwrap().to_result(conf.size).unwrap())
+            .collect::<Vec<_>>();
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(in_memory.is_empty());
+        assert_eq!(spilled.len(), 117);
+        assert_eq!(spilled.into_iter().collect::<HashSet<_>>().len(), 117);
+    }
+
+    #[test]
+    fn sink_writes_distinct_results_up_to_limit() {
+        let fresh = || {
+            Configuration::new(Size::new(4, 4), HashSet::new())
+                .with_mode(crate::algorithm::SearchMode::Exhaustive)
+        };
+        let conf = fresh();
+        let mut sink = JsonLinesSink::new(Vec::new(), &conf);
+        conf.run_into(&mut sink, &mut StatsDummy);
+        assert_eq!(sink.written.len(), 117);
+        let written = read_json_lines(sink.writer.out.as_slice())
+            .map(|placement| placement.unwrap().to_result(conf.size).unwrap())
+            .collect::<BTreeSet<_>>();
+        assert_eq!(written, conf.run(&mut StatsDummy));
+
+        let limited = fresh().with_results_limit(NonZeroUsize::new(5).unwrap());
+        let mut sink = JsonLinesSink::new(Vec::new(), &limited);
+        let outcome = limited.run_into(&mut sink, &mut StatsDummy);
+        assert_eq!(outcome, crate::algorithm::RunOutcome::LimitReached);
+        assert_eq!(sink.finish().unwrap(), 5);
+    }
 }
@@ -0,0 +1,176 @@
+use std::collections::HashSet;
+
+use derive_more::Display;
+
+use crate::parse_field::ParsedField;
+use crate::util::{Pos, Size};
+
+/// Symmetries of a rectangle
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Display)]
+pub enum Transform {
+    #[display(fmt = "identity")]
+    Identity,
+    #[display(fmt = "rotation by 90° clockwise")]
+    Rotate90,
+    #[display(fmt = "rotation by 180°")]
+    Rotate180,
+    #[display(fmt = "rotation by 270° clockwise")]
+    Rotate270,
+    #[display(fmt = "reflection across the vertical axis")]
+    FlipHorizontal,
+    #[display(fmt = "reflection across the horizontal axis")]
+    FlipVertical,
+    #[display(fmt = "reflection across the main diagonal")]
+    Transpose,
+    #[display(fmt = "reflection across the anti-diagonal")]
+    AntiTranspose,
+}
+
+impl Transform {
+    pub const ALL: [Transform; 8] = [
+        Transform::Identity,
+        Transform::Rotate90,
+        Transform::Rotate180,
+        Transform::Rotate270,
+        Transform::FlipHorizontal,
+        Transform::FlipVertical,
+        Transform::Transpose,
+        Transform::AntiTranspose,
+    ];
+
+    /// Size of a field of the given size after the transform
+    pub fn apply_to_size(self, size: Size) -> Size {
+        match self {
+            Transform::Identity
+            | Transform::Rotate180
+            | Transform::FlipHorizontal
+            | Transform::FlipVertical => size,
+            Transform::Rotate90
+            | Transform::Rotate270
+            | Transform::Transpose
+            | Transform::AntiTranspose => Size::new(size.cols, size.rows),
+        }
+    }
+
+    /// Where a cell of a field of the given size goes after the transform
+    pub fn apply_to_pos(self, pos: Pos, size: Size) -> Pos {
+        let Pos { row, col } = pos;
+        let (last_row, last_col) = (size.rows - 1, size.cols - 1);
+        match self {
+            Transform::Identity => pos,
+            Transform::Rotate90 => Pos::new(col, last_row - row),
+            Transform::Rotate180 => Pos::new(last_row - row, last_col - col),
+            Transform::Rotate270 => Pos::new(last_col - col, row),
+            Transform::FlipHorizontal => Pos::new(row, last_col - col),
+            Transform::FlipVertical => Pos::new(last_row - row, col),
+            Transform::Transpose => Pos::new(col, row),
+            Transform::AntiTranspose => Pos::new(last_col - col, last_row - row),
+        }
+    }
+
+    pub fn apply(self, field: &ParsedField) -> ParsedField {
+        let transform_all = |cells: &HashSet<Pos>| {
+            cells
+                .iter()
+                .map(|pos| self.apply_to_pos(*pos, field.size))
+                .collect()
+        };
+
+        ParsedField {
+            size: self.apply_to_size(field.size),
+            unavailable: transform_all(&field.unavailable),
+            outside: transform_all(&field.outside),
+            optional: transform_all(&field.optional),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Display)]
+pub enum Comparison {
+    #[display(fmt = "identical")]
+    Identical,
+    /// The transform maps the first field onto the second one
+    #[display(fmt = "equal under {_0}")]
+    Equivalent(Transform),
+    #[display(fmt = "different")]
+    Different,
+}
+
+/// Transforms which map the field onto itself, the identity first. Rotations by 90° are only
+/// possible for square fields
+pub fn symmetries(field: &ParsedField) -> Vec<Transform> {
+    Transform::ALL
+        .into_iter()
+        .filter(|transform| transform.apply(field) == *field)
+        .collect()
+}
+
+/// Checks whether two fields are the same up to rotation and reflection
+pub fn compare(a: &ParsedField, b: &ParsedField) -> Comparison {
+    if a == b {
+        return Comparison::Identical;
+    }
+
+    Transform::ALL
+        .into_iter()
+        .find(|transform| transform.apply(a) == *b)
+        .map_or(Comparison::Different, Comparison::Equivalent)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_field::Parser;
+
+    fn parse(input: &str) -> ParsedField {
+        Parser::new('-', 'x').parse(input).unwrap()
+    }
+
+    #[test]
+    fn rotated_board_is_equal_under_rotation() {
+        let board = parse("-x-\n---");
+        let rotated = parse("--\n-x\n--");
+
+        let comparison = compare(&board, &rotated);
+
+        assert_eq!(comparison, Comparison::Equivalent(Transform::Rotate90));
+        assert!(comparison.to_string().starts_with("equal under rotation"));
+    }
+
+    #[test]
+    fn different_boards() {
+        assert_eq!(
+            compare(&parse("x-\n--"), &parse("xx\n--")),
+            Comparison::Different
+        );
+        assert_eq!(
+            compare(&parse("x-\n--"), &parse("x-\n--")),
+            Comparison::Identical
+        );
+    }
+
+    #[test]
+    fn empty_square_has_all_symmetries() {
+        assert_eq!(symmetries(&parse("---\n---\n---")), Transform::ALL);
+        assert_eq!(symmetries(&parse("x--\nx--\nxx-")), [Transform::Identity]);
+        assert_eq!(
+            symmetries(&parse("x--x\n----")),
+            [Transform::Identity, Transform::FlipHorizontal]
+        );
+    }
+
+    #[test]
+    fn every_transform_keeps_cells_in_bounds() {
+        let size = Size::new(2, 3);
+        for transform in Transform::ALL {
+            let new_size = transform.apply_to_size(size);
+            for row in 0..size.rows {
+                for col in 0..size.cols {
+                    assert!(transform
+                        .apply_to_pos(Pos::new(row, col), size)
+                        .in_bounds(&new_size));
+                }
+            }
+        }
+    }
+}
@@ -1,7 +1,12 @@
 use super::util::Pos;
-use crate::util::Size;
+use crate::symmetry::Transform;
+use crate::util::{stable_hash, Size};
 
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
 use std::ops::{Add, Deref};
+use std::str::FromStr;
 
 #[derive(Clone, Hash, Ord, PartialOrd, Eq, PartialEq, Debug)]
 pub struct Tetra {
@@ -97,6 +102,140 @@ impl Tetra {
     pub fn col_shift(&self) -> &usize {
         &self.col_shift
     }
+
+    /// Draws the tetra the way custom pieces are drawn, see [`crate::pieces`]: `#` for its cells
+    /// and `.` for the rest of the bounding box, a line per row
+    pub fn to_ascii(&self) -> String {
+        let mut ascii = String::with_capacity(self.size.rows * (self.size.cols + 1));
+        for row in 0..self.size.rows {
+            ascii.extend((0..self.size.cols).map(|col| {
+                if self.positions.contains(&Pos::new(row, col)) {
+                    '#'
+                } else {
+                    '.'
+                }
+            }));
+            ascii.push('\n');
+        }
+        ascii
+    }
+
+    /// The same tetra rotated by 90° clockwise
+    pub fn rotated(&self) -> &'static Tetra {
+        self.transformed(Transform::Rotate90)
+    }
+
+    /// The same tetra turned or flipped by the transform
+    pub fn transformed(&self, transform: Transform) -> &'static Tetra {
+        let transformed: Vec<_> = self
+            .positions
+            .iter()
+            .map(|pos| transform.apply_to_pos(*pos, self.size))
+            .collect();
+
+        TETRAS
+            .iter()
+            .find(|tetra| transformed.iter().all(|pos| tetra.positions.contains(pos)))
+            .expect("All orientations are in the list")
+    }
+}
+
+/// Tetra shapes regardless of their orientation
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum PieceKind {
+    O,
+    I,
+    T,
+    L,
+    J,
+    S,
+    Z,
+}
+
+impl PieceKind {
+    pub const ALL: [PieceKind; 7] = [
+        PieceKind::O,
+        PieceKind::I,
+        PieceKind::T,
+        PieceKind::L,
+        PieceKind::J,
+        PieceKind::S,
+        PieceKind::Z,
+    ];
+
+    /// Kind of the tetra in any orientation
+    pub fn of(tetra: &Tetra) -> Self {
+        Self::ALL
+            .into_iter()
+            .find(|kind| (0..4).any(|quarter_turns| kind.oriented(quarter_turns) == tetra))
+            .expect("All tetras are of some kind")
+    }
+
+    /// Tetra of this kind, rotated from the base orientation by the given number of quarter turns
+    /// clockwise. Base orientations are: `I` is horizontal, `T` points down, `L` and `J` are
+    /// vertical with the foot at the bottom, `S` and `Z` are horizontal.
+    pub fn oriented(self, quarter_turns: usize) -> &'static Tetra {
+        let base = match self {
+            PieceKind::O => 0,
+            PieceKind::I => 1,
+            PieceKind::T => 3,
+            PieceKind::L => 8,
+            PieceKind::J => 12,
+            PieceKind::S => 15,
+            PieceKind::Z => 17,
+        };
+
+        (0..quarter_turns % 4).fold(&TETRAS[base], |tetra, _| tetra.rotated())
+    }
+}
+
+impl FromStr for PieceKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_uppercase().as_str() {
+            "O" => Ok(PieceKind::O),
+            "I" => Ok(PieceKind::I),
+            "T" => Ok(PieceKind::T),
+            "L" => Ok(PieceKind::L),
+            "J" => Ok(PieceKind::J),
+            "S" => Ok(PieceKind::S),
+            "Z" => Ok(PieceKind::Z),
+            _ => Err(format!(
+                "unknown piece kind `{s}`, expected one of O, I, T, L, J, S, Z"
+            )),
+        }
+    }
+}
+
+/// Describes each tetra with a header of its id, i.e. the index in [`TETRAS`], its kind and the
+/// size of its bounding box, e.g. `#1 I 1x4`, followed by [`Tetra::to_ascii`]. Tetras are
+/// separated by empty lines, and the ones which are not in [`TETRAS`] get `-` for the id and kind
+pub fn describe_pieces<'a>(pieces: impl IntoIterator<Item = &'a Tetra>) -> String {
+    pieces
+        .into_iter()
+        .map(|tetra| {
+            let (id, kind) = match TETRAS.iter().position(|known| known == tetra) {
+                Some(id) => (id.to_string(), format!("{:?}", PieceKind::of(tetra))),
+                None => ("-".to_owned(), "-".to_owned()),
+            };
+            let Size { rows, cols } = tetra.size;
+            format!("#{id} {kind} {rows}x{cols}\n{}", tetra.to_ascii())
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Hash of the piece definitions which is stable across runs, builds and platforms, unlike
+/// [`std::hash::Hash`] based ones. Depends on the pieces order.
+pub fn pieces_fingerprint<'a>(pieces: impl IntoIterator<Item = &'a Tetra>) -> u64 {
+    stable_hash(pieces.into_iter().flat_map(|tetra| {
+        tetra
+            .positions
+            .iter()
+            .flat_map(|pos| [pos.row, pos.col])
+            .chain([tetra.size.rows, tetra.size.cols, tetra.col_shift])
+    }))
 }
 
 impl IntoIterator for Tetra {
@@ -108,12 +247,40 @@ impl IntoIterator for Tetra {
     }
 }
 
-#[derive(Clone, Hash, Ord, PartialOrd, Eq, PartialEq, Debug)]
+#[derive(Clone, Hash, Ord, PartialOrd, Eq, PartialEq, Debug, Serialize, Deserialize)]
 pub struct Placed {
+    #[serde(with = "tetra_id")]
     pub tetra: &'static Tetra,
     pub position: Pos,
 }
 
+/// (De)serialises a tetra as its index in [`TETRAS`]
+mod tetra_id {
+    use super::{Tetra, TETRAS};
+    use serde::{de, ser, Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S>(tetra: &&'static Tetra, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        TETRAS
+            .iter()
+            .position(|x| x == *tetra)
+            .ok_or_else(|| ser::Error::custom("Unknown tetra"))?
+            .serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<&'static Tetra, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let id = usize::deserialize(deserializer)?;
+        TETRAS
+            .get(id)
+            .ok_or_else(|| de::Error::custom(format!("Unknown tetra id: {id}")))
+    }
+}
+
 impl Placed {
     pub fn new(tetra: &'static Tetra, position: Pos) -> Self {
         Self { tetra, position }
@@ -125,19 +292,21 @@ pub struct PlacedBoundariesChecked(Placed);
 
 impl PlacedBoundariesChecked {
     pub fn in_boundaries(placed: Placed, boundaries: Size) -> Option<Self> {
-        let Placed { tetra, position } = placed;
+        let Placed { tetra, position } = &placed;
 
-        let tetra_size = tetra.size();
-        let tetra_col_shift = *tetra.col_shift();
-
-        if tetra_col_shift > position.col
-            || position.col + tetra_size.cols - tetra_col_shift > boundaries.cols
-            || position.row + tetra_size.rows > boundaries.rows
-        {
+        // cheap rejection by the bounding box before looking at each cell
+        if position.row + tetra.size().rows > boundaries.rows {
             return None;
         }
 
-        Some(Self(placed))
+        let col_shift = *tetra.col_shift();
+        let fits = tetra.iter().all(|pos| {
+            pos.add(position)
+                .checked_sub_col(col_shift)
+                .is_some_and(|pos| pos.in_bounds(&boundaries))
+        });
+
+        fits.then_some(Self(placed))
     }
 
     pub fn iter_relative_to_place(&self) -> impl Iterator<Item = Pos> + '_ {
@@ -147,9 +316,9 @@ impl PlacedBoundariesChecked {
         }) = self;
 
         tetra.iter().map(|pos| {
-            let mut pos = pos.add(relative);
-            pos.col -= tetra.col_shift;
-            pos
+            pos.add(relative)
+                .checked_sub_col(*tetra.col_shift())
+                .expect("Column shift is checked in `in_boundaries`")
         })
     }
 }
@@ -171,74 +340,181 @@ impl From<PlacedBoundariesChecked> for Placed {
 /// Yields finite shuffled tetra iterators.
 ///
 /// ```
-/// let mut generator = RandomTetras::new();
+/// use brutal_tetris_hacker::tetra::Shuffler;
+///
+/// let mut generator = Shuffler::new();
 /// let mut tetras = generator.finite_iter();
-/// assert!(matches!(tetras.next(), Some(Tetra { .. })));
+/// assert!(tetras.next().is_some());
 /// ```
 #[derive(Debug)]
-pub struct Shuffler {
-    rng: rand::rngs::ThreadRng,
+pub struct Shuffler<R = StdRng> {
+    rng: R,
 }
 
 impl Shuffler {
     pub fn new() -> Self {
-        let rng = rand::thread_rng();
+        Self::with_rng(StdRng::from_entropy())
+    }
+}
+
+impl<R> Shuffler<R>
+where
+    R: Rng + Send,
+{
+    /// Uses the given generator, e.g. a seeded one to get reproducible shuffles
+    pub fn with_rng(rng: R) -> Self {
         Self { rng }
     }
 
-    pub fn finite_iter(&mut self) -> impl Iterator<Item = &'static Tetra> {
-        use rand::Rng;
+    /// As many random tetras as there are in [`TETRAS`], possibly repeated. They are drawn one by
+    /// one, so nothing is allocated
+    pub fn finite_iter(&mut self) -> impl ExactSizeIterator<Item = &'static Tetra> + '_ {
+        self.finite_ids().map(|idx| &TETRAS[idx])
+    }
+
+    /// Same as [`Shuffler::finite_iter`], but yields indices in [`TETRAS`]
+    pub fn finite_ids(&mut self) -> FiniteIds<'_, R> {
+        FiniteIds {
+            rng: &mut self.rng,
+            left: TETRAS_COUNT,
+        }
+    }
+
+    /// One random index among the given ones, which the random search draws on every step
+    pub fn pick_from(&mut self, ids: &[usize]) -> usize {
+        ids[self.rng.gen_range(0..ids.len())]
+    }
+}
+
+impl Default for Shuffler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Random indices in [`TETRAS`], see [`Shuffler::finite_ids`]
+#[derive(Debug)]
+pub struct FiniteIds<'a, R> {
+    rng: &'a mut R,
+    left: usize,
+}
+
+impl<R: Rng> Iterator for FiniteIds<'_, R> {
+    type Item = usize;
 
-        let tetras = array_macro::array![_ => self.rng.gen_range(0..TETRAS_COUNT); TETRAS_COUNT];
-        tetras.into_iter().map(|idx| &TETRAS[idx])
+    fn next(&mut self) -> Option<Self::Item> {
+        self.left = self.left.checked_sub(1)?;
+        Some(self.rng.gen_range(0..TETRAS_COUNT))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.left, Some(self.left))
     }
 }
 
+impl<R: Rng> ExactSizeIterator for FiniteIds<'_, R> {}
+
 #[cfg(test)]
 mod test {
     use super::*;
 
+    #[test]
+    fn rotations_cycle_through_orientations() {
+        let orientations = |kind: PieceKind| {
+            (0..4)
+                .map(|turns| kind.oriented(turns))
+                .collect::<std::collections::HashSet<_>>()
+                .len()
+        };
+
+        assert_eq!(orientations(PieceKind::O), 1);
+        assert_eq!(orientations(PieceKind::I), 2);
+        assert_eq!(orientations(PieceKind::S), 2);
+        assert_eq!(orientations(PieceKind::T), 4);
+        assert_eq!(orientations(PieceKind::L), 4);
+        assert_eq!(PieceKind::T.oriented(1), T_LOOK_LEFT);
+        assert_eq!(PieceKind::T.oriented(4), PieceKind::T.oriented(0));
+    }
+
+    #[test]
+    fn every_tetra_has_a_kind() {
+        let count = |kind| {
+            TETRAS
+                .iter()
+                .filter(|tetra| PieceKind::of(tetra) == kind)
+                .count()
+        };
+
+        assert_eq!(count(PieceKind::O), 1);
+        assert_eq!(count(PieceKind::Z), 2);
+        assert_eq!(count(PieceKind::J), 4);
+        assert_eq!(PieceKind::of(T_LOOK_LEFT), PieceKind::T);
+    }
+
+    #[test]
+    fn describes_every_piece() {
+        let description = describe_pieces(&TETRAS);
+
+        assert_eq!(description.split("\n\n").count(), TETRAS_COUNT);
+        assert!(description.starts_with("#0 O 2x2\n##\n##\n\n#1 I 1x4\n####\n"));
+        assert!(description.contains(&format!("#3 T 2x3\n{}", TETRAS[3].to_ascii())));
+    }
+
+    #[test]
+    fn builtin_pieces_fingerprint_is_stable() {
+        assert_eq!(pieces_fingerprint(&TETRAS), 0x3a17e088113e38a6);
+        assert_ne!(
+            pieces_fingerprint(&TETRAS[1..]),
+            pieces_fingerprint(&TETRAS)
+        );
+    }
+
+    #[test]
+    fn seeded_shuffler_is_reproducible() {
+        fn assert_send<T: Send>(_: &T) {}
+
+        let mut first = Shuffler::with_rng(StdRng::seed_from_u64(42));
+        let mut second = Shuffler::with_rng(StdRng::seed_from_u64(42));
+        assert_send(&first);
+
+        let tetras: Vec<_> = first.finite_iter().collect();
+        assert_eq!(tetras.len(), TETRAS_COUNT);
+        assert_eq!(tetras, second.finite_iter().collect::<Vec<_>>());
+    }
+
     #[test]
     fn check_for_3x3() {
-        assert!(matches!(
-            PlacedBoundariesChecked::in_boundaries(
-                Placed::new(I_HORIZONTAL, Pos::new(0, 0)),
-                Size::new(3, 3)
-            ),
-            None
-        ));
+        assert!(PlacedBoundariesChecked::in_boundaries(
+            Placed::new(I_HORIZONTAL, Pos::new(0, 0)),
+            Size::new(3, 3)
+        )
+        .is_none());
     }
 
     #[test]
     fn check_for_horizontal_i_in_4x4() {
-        assert!(matches!(
-            PlacedBoundariesChecked::in_boundaries(
-                Placed::new(I_HORIZONTAL, Pos::new(0, 0)),
-                Size::new(4, 4)
-            ),
-            Some(_)
-        ));
+        assert!(PlacedBoundariesChecked::in_boundaries(
+            Placed::new(I_HORIZONTAL, Pos::new(0, 0)),
+            Size::new(4, 4)
+        )
+        .is_some());
     }
 
     #[test]
     fn check_horizontal_i_in_4x4_at_col_1() {
-        assert!(matches!(
-            PlacedBoundariesChecked::in_boundaries(
-                Placed::new(I_HORIZONTAL, Pos::new(0, 1)),
-                Size::new(4, 4)
-            ),
-            None
-        ));
+        assert!(PlacedBoundariesChecked::in_boundaries(
+            Placed::new(I_HORIZONTAL, Pos::new(0, 1)),
+            Size::new(4, 4)
+        )
+        .is_none());
     }
 
     #[test]
     fn checj_t_at_right_border() {
-        assert!(matches!(
-            PlacedBoundariesChecked::in_boundaries(
-                Placed::new(T_LOOK_LEFT, Pos::new(0, 2)),
-                Size::new(3, 3)
-            ),
-            Some(_)
-        ));
+        assert!(PlacedBoundariesChecked::in_boundaries(
+            Placed::new(T_LOOK_LEFT, Pos::new(0, 2)),
+            Size::new(3, 3)
+        )
+        .is_some());
     }
 }
@@ -0,0 +1,191 @@
+use std::fmt::{Display, Formatter};
+
+use grid::Grid;
+use miette::Diagnostic;
+use thiserror::Error;
+
+use crate::algorithm::{CellKind, CollectStats, Configuration};
+use crate::tetra::{PlacedBoundariesChecked, TETRAS};
+use crate::util::Pos;
+
+/// The search tree grows fast, so only small fields could be traced
+pub const MAX_TRACED_CELLS: usize = 16;
+
+#[derive(Debug, Error, PartialEq, Diagnostic)]
+pub enum TraceError {
+    #[error("Field has {0} playable cells, but only fields up to {MAX_TRACED_CELLS} cells could be traced")]
+    #[diagnostic(help("The search tree of such a field is too large to be drawn"))]
+    FieldTooLarge(usize),
+}
+
+/// Records the search tree to render it in Graphviz DOT format.
+///
+/// Nodes are states of the field, edges are tetras placed into it. Leaves are accepted placements
+/// or dead ends.
+pub struct DotTrace {
+    grid: Grid<char>,
+    nodes: Vec<Node>,
+    /// Parent, child and the edge label
+    edges: Vec<(usize, usize, String)>,
+    /// Nodes from the root to the current one, with tetras placed between them
+    path: Vec<(usize, PlacedBoundariesChecked)>,
+}
+
+struct Node {
+    label: String,
+    accepted: Option<bool>,
+}
+
+impl DotTrace {
+    pub fn new(conf: &Configuration) -> Result<Self, TraceError> {
+        let playable = conf.playable_cells();
+        if playable > MAX_TRACED_CELLS {
+            return Err(TraceError::FieldTooLarge(playable));
+        }
+
+        let mut grid = Grid::new(conf.size.rows, conf.size.cols);
+        for pos in (0..conf.size.rows)
+            .flat_map(|row| (0..conf.size.cols).map(move |col| Pos::new(row, col)))
+        {
+            grid[pos.row][pos.col] = match conf.cell_kind(&pos) {
+                CellKind::Playable => '-',
+                CellKind::Blocked => 'x',
+                CellKind::Outside => ' ',
+            };
+        }
+
+        let mut trace = Self {
+            grid,
+            nodes: Vec::new(),
+            edges: Vec::new(),
+            path: Vec::new(),
+        };
+        trace.add_node();
+        Ok(trace)
+    }
+
+    pub fn nodes_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn edges_count(&self) -> usize {
+        self.edges.len()
+    }
+
+    fn add_node(&mut self) -> usize {
+        let label = (0..self.grid.rows())
+            .map(|row| self.grid.iter_row(row).collect::<String>() + "\\l")
+            .collect();
+        self.nodes.push(Node {
+            label,
+            accepted: None,
+        });
+        self.nodes.len() - 1
+    }
+
+    fn current(&self) -> usize {
+        self.path.last().map_or(0, |(node, _)| *node)
+    }
+}
+
+impl CollectStats for DotTrace {
+    fn recursions_inc(&mut self) {}
+
+    fn results_inc(&mut self) {}
+
+    fn tetra_placed(&mut self, placed: &PlacedBoundariesChecked) {
+        let label = (b'A' + (self.path.len() % 26) as u8) as char;
+        for pos in placed.iter_relative_to_place() {
+            self.grid[pos.row][pos.col] = label;
+        }
+
+        let parent = self.current();
+        let child = self.add_node();
+        let id = TETRAS.iter().position(|x| x == placed.tetra).unwrap();
+        let pos = placed.position;
+        self.edges
+            .push((parent, child, format!("{id}@{},{}", pos.row, pos.col)));
+        self.path.push((child, placed.clone()));
+    }
+
+    fn tetra_removed(&mut self) {
+        let (_, placed) = self.path.pop().unwrap();
+        for pos in placed.iter_relative_to_place() {
+            self.grid[pos.row][pos.col] = '-';
+        }
+    }
+
+    fn leaf_reached(&mut self, accepted: bool) {
+        let current = self.current();
+        self.nodes[current].accepted = Some(accepted);
+    }
+}
+
+impl Display for DotTrace {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "digraph search {{")?;
+        writeln!(f, "  node [shape=box, fontname=\"monospace\"];")?;
+        for (id, node) in self.nodes.iter().enumerate() {
+            let style = match node.accepted {
+                Some(true) => ", style=filled, fillcolor=palegreen",
+                Some(false) => ", style=filled, fillcolor=lightgray",
+                None => "",
+            };
+            writeln!(f, "  n{id} [label=\"{}\"{style}];", node.label)?;
+        }
+        for (parent, child, label) in &self.edges {
+            writeln!(f, "  n{parent} -> n{child} [label=\"{label}\"];")?;
+        }
+        writeln!(f, "}}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algorithm::SearchMode;
+
+    fn trace(board: &str) -> DotTrace {
+        let conf = Configuration::from_board_str(board)
+            .unwrap()
+            .with_mode(SearchMode::Exhaustive);
+        let mut trace = DotTrace::new(&conf).unwrap();
+        conf.run(&mut trace);
+        trace
+    }
+
+    #[test]
+    fn traces_the_only_placement() {
+        let trace = trace("--\n--");
+
+        assert_eq!(trace.nodes_count(), 2);
+        assert_eq!(trace.edges_count(), 1);
+        let dot = trace.to_string();
+        assert!(dot.starts_with("digraph search {"));
+        assert!(dot.contains("n0 [label=\"--\\l--\\l\"];"));
+        assert!(dot.contains("n1 [label=\"AA\\lAA\\l\", style=filled, fillcolor=palegreen];"));
+        assert!(dot.contains("n0 -> n1 [label=\"0@0,0\"];"));
+    }
+
+    #[test]
+    fn traces_two_levels() {
+        let trace = trace("---\n---\n--x");
+
+        // every tetra except the two I-s fits at the root, 4 of them leave room for another one
+        assert_eq!(trace.nodes_count(), 1 + 17 + 4);
+        assert_eq!(trace.edges_count(), trace.nodes_count() - 1);
+        let dot = trace.to_string();
+        assert_eq!(dot.matches("palegreen").count(), 4);
+        assert_eq!(dot.matches("lightgray").count(), 17 - 4);
+    }
+
+    #[test]
+    fn refuses_large_fields() {
+        let conf = Configuration::from_board_str("-----\n-----\n-----\n-----").unwrap();
+
+        assert_eq!(
+            DotTrace::new(&conf).err(),
+            Some(TraceError::FieldTooLarge(20))
+        );
+    }
+}
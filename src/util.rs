@@ -1,11 +1,12 @@
 use std::fmt::{Debug, Formatter};
-use std::ops::Add;
+use std::ops::{Add, Sub};
+use std::str::FromStr;
 
 use derive_more::Display;
 use grid::Grid;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
-#[derive(Copy, Clone, Hash, Ord, PartialOrd, Eq, PartialEq, Display, Serialize)]
+#[derive(Copy, Clone, Hash, Ord, PartialOrd, Eq, PartialEq, Display, Serialize, Deserialize)]
 #[display(fmt = "({row}, {col})")]
 pub struct Pos {
     pub row: usize,
@@ -38,10 +39,32 @@ impl Add for &Pos {
     }
 }
 
+impl Sub for &Pos {
+    type Output = Pos;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        Pos {
+            row: self.row - rhs.row,
+            col: self.col - rhs.col,
+        }
+    }
+}
+
 impl Pos {
     pub fn new(row: usize, col: usize) -> Self {
         Self { row, col }
     }
+
+    /// Shifts the position to the left, or returns `None` if it goes beyond the column 0
+    pub fn checked_sub_col(&self, cols: usize) -> Option<Pos> {
+        self.col
+            .checked_sub(cols)
+            .map(|col| Pos { row: self.row, col })
+    }
+
+    pub fn in_bounds(&self, size: &Size) -> bool {
+        self.row < size.rows && self.col < size.cols
+    }
 }
 
 pub trait PosInGrid<T> {
@@ -81,3 +104,89 @@ impl From<(usize, usize)> for Size {
         Self::new(rows, cols)
     }
 }
+
+/// Parses `<ROWS>x<COLS>`, e.g. `4x6`
+impl FromStr for Size {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (rows, cols) = s.split_once('x').ok_or("expected format `<ROWS>x<COLS>`")?;
+        let rows = rows.parse().map_err(|err| format!("bad rows: {err}"))?;
+        let cols = cols.parse().map_err(|err| format!("bad columns: {err}"))?;
+        Ok(Self::new(rows, cols))
+    }
+}
+
+/// FNV-1a hash of the numbers, which is stable across runs, builds and platforms, unlike
+/// [`std::hash::Hash`] based ones
+pub fn stable_hash(numbers: impl IntoIterator<Item = usize>) -> u64 {
+    const OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+    const PRIME: u64 = 0x100000001b3;
+
+    numbers
+        .into_iter()
+        .flat_map(|number| (number as u64).to_le_bytes())
+        .fold(OFFSET_BASIS, |hash, byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(PRIME)
+        })
+}
+
+/// Formats the number with commas between groups of thousands, e.g. `1,234,567`, regardless of
+/// the locale
+pub fn group_thousands(number: usize) -> String {
+    let digits = number.to_string();
+    let mut grouped = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            grouped.push(',');
+        }
+        grouped.push(digit);
+    }
+    grouped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checked_sub_col_at_column_0() {
+        assert_eq!(Pos::new(3, 0).checked_sub_col(0), Some(Pos::new(3, 0)));
+        assert_eq!(Pos::new(3, 0).checked_sub_col(1), None);
+    }
+
+    #[test]
+    fn checked_sub_col_within_row() {
+        assert_eq!(Pos::new(1, 2).checked_sub_col(2), Some(Pos::new(1, 0)));
+    }
+
+    #[test]
+    fn sub_pos() {
+        assert_eq!(&Pos::new(3, 4) - &Pos::new(1, 2), Pos::new(2, 2));
+    }
+
+    #[test]
+    fn parse_size() {
+        assert_eq!("4x6".parse(), Ok(Size::new(4, 6)));
+        assert!("4*6".parse::<Size>().is_err());
+    }
+
+    #[test]
+    fn in_bounds_excludes_edges() {
+        let size = Size::new(2, 3);
+
+        assert!(Pos::new(1, 2).in_bounds(&size));
+        assert!(!Pos::new(2, 0).in_bounds(&size));
+        assert!(!Pos::new(0, 3).in_bounds(&size));
+    }
+
+    #[test]
+    fn groups_thousands() {
+        assert_eq!(group_thousands(0), "0");
+        assert_eq!(group_thousands(999), "999");
+        assert_eq!(group_thousands(1_000), "1,000");
+        assert_eq!(group_thousands(123_456), "123,456");
+        assert_eq!(group_thousands(1_234_567), "1,234,567");
+        assert_eq!(group_thousands(usize::MAX), "18,446,744,073,709,551,615");
+    }
+}
@@ -0,0 +1,122 @@
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+#[test]
+fn malformed_field_gives_json_error() {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_brutal-tetris-hacker"))
+        .args(["--stdin", "--output-format", "json"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(b"---\n-#-\n")
+        .unwrap();
+
+    let output = child.wait_with_output().unwrap();
+    let error: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+
+    assert!(!output.status.success());
+    assert_eq!(error["kind"], "UnexpectedCharacter");
+    assert_eq!(error["span"], serde_json::json!({"offset": 5, "length": 1}));
+}
+
+#[test]
+fn field_is_given_on_command_line() {
+    let output = Command::new(env!("CARGO_BIN_EXE_brutal-tetris-hacker"))
+        .args(["--field", "----/--xx/--xx", "--output-format", "json"])
+        .stderr(Stdio::null())
+        .output()
+        .unwrap();
+    let found: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+
+    // the random search doesn't always find the placements, but the ones it does cover the field
+    assert!(output.status.success());
+    assert_eq!(found["feasible_complete"], true);
+    assert!(found["placements"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .all(|placement| placement["free"] == 0
+            && placement["tetras"].as_array().unwrap().len() == 2));
+}
+
+#[test]
+fn failed_board_ends_json_list() {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_brutal-tetris-hacker"))
+        .args([
+            "--stdin",
+            "--output-format",
+            "json",
+            "--on-no-solution",
+            "error",
+            "--complete",
+        ])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+    // the first board is already full, so that it's solved whatever tetras the search draws
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(b"xx\nxx\n\n-x\nx-\n")
+        .unwrap();
+
+    let output = child.wait_with_output().unwrap();
+    let boards: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+
+    assert!(!output.status.success());
+    assert!(boards[0]["placements"].is_array());
+    assert_eq!(
+        boards[1]["error"],
+        "Failed to solve board 2: No placement satisfies the configuration"
+    );
+}
+
+#[test]
+fn json_tells_field_file_and_board() {
+    let path = std::env::temp_dir().join(format!("boards-{}.txt", std::process::id()));
+    std::fs::write(&path, "----\n----\n\n--\n--\n").unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_brutal-tetris-hacker"))
+        .arg("--field-file")
+        .arg(&path)
+        .args(["--output-format", "json"])
+        .stderr(Stdio::null())
+        .output()
+        .unwrap();
+    std::fs::remove_file(&path).unwrap();
+    let boards: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+
+    assert!(output.status.success());
+    let name = path.display().to_string();
+    assert_eq!(
+        boards[0]["source"],
+        serde_json::json!({"input": name, "board": 1})
+    );
+    assert_eq!(
+        boards[1]["source"],
+        serde_json::json!({"input": name, "board": 2})
+    );
+}
+
+#[test]
+fn tail_free_prints_fewest_free_cells() {
+    for (field, free) in [("----/----/----/----", "0"), ("---/---/---", "1")] {
+        let output = Command::new(env!("CARGO_BIN_EXE_brutal-tetris-hacker"))
+            .args(["--field", field, "--tail-free", "--output-format", "json"])
+            .stderr(Stdio::null())
+            .output()
+            .unwrap();
+
+        assert!(output.status.success());
+        assert_eq!(String::from_utf8(output.stdout).unwrap().trim(), free);
+    }
+}